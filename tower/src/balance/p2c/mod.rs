@@ -38,4 +38,4 @@ mod test;
 
 pub use layer::MakeBalanceLayer;
 pub use make::{MakeBalance, MakeFuture};
-pub use service::Balance;
+pub use service::{Balance, Drained};
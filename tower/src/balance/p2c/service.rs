@@ -1,11 +1,20 @@
 use super::super::error;
 use crate::discover::{Change, Discover};
-use crate::load::Load;
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+use crate::load::{Load, Outstanding};
 use crate::ready_cache::{error::Failed, ReadyCache};
 use crate::util::rng::{sample_floyd2, HasherRng, Rng};
 use futures_util::future::{self, TryFutureExt};
+use std::future::Future;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::time::Duration;
 use std::{
     fmt,
     pin::Pin,
@@ -23,6 +32,11 @@ use tracing::{debug, trace};
 /// `&mut self`. You can achieve this easily by wrapping your [`Discover`] in [`Box::pin`] before you
 /// construct the [`Balance`] instance. For more details, see [#319].
 ///
+/// By default, services removed by the [`Discover`] are dropped immediately. With
+/// [`Balance::drain_removed`], removed services instead stop receiving new
+/// requests, but are kept in a background task until the requests they are
+/// still processing have completed.
+///
 /// [`Box::pin`]: std::boxed::Box::pin()
 /// [#319]: https://github.com/tower-rs/tower/issues/319
 pub struct Balance<D, Req>
@@ -37,9 +51,36 @@ where
 
     rng: Box<dyn Rng + Send + Sync>,
 
+    drain: Option<Drain<D::Key, D::Service>>,
+    on_drained: SharedOnDrained<D::Key>,
+
     _req: PhantomData<Req>,
 }
 
+/// How a removed service finished draining.
+///
+/// This is passed to the hook set with [`Balance::on_drained`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Drained {
+    /// All of the service's outstanding requests completed.
+    Completed,
+    /// The drain timeout expired while requests were still outstanding.
+    TimedOut,
+}
+
+type OnDrained<K> = Box<dyn FnMut(&K, Drained) + Send + Sync>;
+
+/// The hook set with [`Balance::on_drained`], shared with the drain tasks.
+type SharedOnDrained<K> = Arc<Mutex<Option<OnDrained<K>>>>;
+
+/// Drains the services that were removed from the balancer.
+struct Drain<K, S> {
+    /// Spawns a task that drains a removed service.
+    spawn: Box<dyn Fn(K, S) + Send + Sync>,
+    /// The number of services that are still draining.
+    draining: Arc<AtomicUsize>,
+}
+
 impl<D: Discover, Req> fmt::Debug for Balance<D, Req>
 where
     D: fmt::Debug,
@@ -50,6 +91,13 @@ where
         f.debug_struct("Balance")
             .field("discover", &self.discover)
             .field("services", &self.services)
+            .field(
+                "draining",
+                &self
+                    .drain
+                    .as_ref()
+                    .map(|d| d.draining.load(Ordering::Acquire)),
+            )
             .finish()
     }
 }
@@ -74,16 +122,94 @@ where
             discover,
             services: ReadyCache::default(),
             ready_index: None,
+            drain: None,
+            on_drained: Arc::new(Mutex::new(None)),
 
             _req: PhantomData,
         }
     }
 
+    /// Drains services that are removed by the [`Discover`], rather than
+    /// dropping them immediately.
+    ///
+    /// A removed service no longer receives new requests, but is moved to a
+    /// background task, spawned on the Tokio runtime, that keeps it until all
+    /// of its outstanding requests have completed, as reported by its
+    /// [`Outstanding`] implementation, or until `timeout` has elapsed since it
+    /// was removed. It is then dropped, and the hook set with
+    /// [`Balance::on_drained`], if any, is called.
+    #[cfg(feature = "tokio-rt")]
+    pub fn drain_removed(self, timeout: Duration) -> Self
+    where
+        D::Key: Send + 'static,
+        D::Service: Outstanding + Send + 'static,
+    {
+        self.drain_removed_with_executor(timeout, TokioExecutor::new())
+    }
+
+    /// Drains services that are removed by the [`Discover`] in tasks spawned
+    /// with `executor`, rather than dropping them immediately.
+    ///
+    /// The drain tasks use a Tokio timer to enforce `timeout`, so `executor`
+    /// must poll them within a Tokio runtime with the time driver enabled.
+    /// A drain task that `executor` drops before it completes drops its
+    /// service without calling the [`Balance::on_drained`] hook, but is no
+    /// longer counted by [`Balance::draining_len`].
+    ///
+    /// See [`Balance::drain_removed`] for details.
+    pub fn drain_removed_with_executor<E>(mut self, timeout: Duration, executor: E) -> Self
+    where
+        D::Key: Send + 'static,
+        D::Service: Outstanding + Send + 'static,
+        E: Executor + Send + Sync + 'static,
+    {
+        let draining = Arc::new(AtomicUsize::new(0));
+        let on_drained = self.on_drained.clone();
+        let count = draining.clone();
+        let spawn = move |key, service| {
+            let draining = Draining::new(count.clone());
+            executor.spawn(Box::pin(drain(
+                key,
+                service,
+                timeout,
+                on_drained.clone(),
+                draining,
+            )));
+        };
+        self.drain = Some(Drain {
+            spawn: Box::new(spawn),
+            draining,
+        });
+        self
+    }
+
+    /// Sets a hook that is called with the key of each removed service once it
+    /// has finished draining.
+    ///
+    /// This has no effect unless draining is enabled with
+    /// [`Balance::drain_removed`], which may be called before or after this.
+    pub fn on_drained<F>(self, f: F) -> Self
+    where
+        F: FnMut(&D::Key, Drained) + Send + Sync + 'static,
+    {
+        *self.on_drained.lock().expect("on_drained hook poisoned") = Some(Box::new(f));
+        self
+    }
+
     /// Returns the number of endpoints currently tracked by the balancer.
+    ///
+    /// This does not include removed endpoints that are still draining.
     pub fn len(&self) -> usize {
         self.services.len()
     }
 
+    /// Returns the number of removed endpoints that are still draining.
+    pub fn draining_len(&self) -> usize {
+        self.drain
+            .as_ref()
+            .map_or(0, |d| d.draining.load(Ordering::Acquire))
+    }
+
     /// Returns whether or not the balancer is empty.
     pub fn is_empty(&self) -> bool {
         self.services.is_empty()
@@ -115,12 +241,22 @@ where
                 None => return Poll::Ready(None),
                 Some(Change::Remove(key)) => {
                     trace!("remove");
-                    self.services.evict(&key);
+                    match self.drain.as_ref() {
+                        Some(drain) => {
+                            for svc in self.services.remove(&key) {
+                                (drain.spawn)(key.clone(), svc);
+                            }
+                        }
+                        None => {
+                            self.services.evict(&key);
+                        }
+                    }
                 }
                 Some(Change::Insert(key, svc)) => {
                     trace!("insert");
-                    // If this service already existed in the set, it will be
-                    // replaced as the new one becomes ready.
+                    // If this service already existed in the set, it keeps
+                    // being used until the new one becomes ready and replaces
+                    // it.
                     self.services.push(key, svc);
                 }
             }
@@ -256,3 +392,53 @@ where
             .map_err(Into::into)
     }
 }
+
+/// Keeps a removed service until its outstanding requests have completed, or
+/// until `timeout` elapses.
+async fn drain<K, S>(
+    key: K,
+    service: S,
+    timeout: Duration,
+    on_drained: SharedOnDrained<K>,
+    draining: Draining,
+) where
+    S: Outstanding,
+{
+    let mut sleep = Box::pin(tokio::time::sleep(timeout));
+    // The service is dropped along with this future, before the hook is
+    // called.
+    let drained = std::future::poll_fn(move |cx| {
+        if service.poll_drained(cx).is_ready() {
+            return Poll::Ready(Drained::Completed);
+        }
+        sleep.as_mut().poll(cx).map(|()| Drained::TimedOut)
+    })
+    .await;
+    debug!(?drained, "endpoint drained");
+    drop(draining);
+    if let Some(on_drained) = on_drained
+        .lock()
+        .expect("on_drained hook poisoned")
+        .as_mut()
+    {
+        on_drained(&key, drained);
+    }
+}
+
+/// Counts a drain task in [`Balance::draining_len`] until it is dropped, so
+/// that tasks dropped by their executor before completing are not counted
+/// forever.
+struct Draining(Arc<AtomicUsize>);
+
+impl Draining {
+    fn new(count: Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::AcqRel);
+        Draining(count)
+    }
+}
+
+impl Drop for Draining {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
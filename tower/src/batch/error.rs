@@ -0,0 +1,117 @@
+//! Error types for the `Batch` middleware.
+
+use crate::BoxError;
+use std::{fmt, sync::Arc};
+
+/// An error produced by a [`Service`] wrapped by a [`Batch`]
+///
+/// [`Service`]: crate::Service
+/// [`Batch`]: crate::batch::Batch
+#[derive(Debug)]
+pub struct ServiceError {
+    inner: Arc<BoxError>,
+}
+
+/// An error produced when the a batch's worker closes unexpectedly.
+pub struct Closed {
+    _p: (),
+}
+
+/// An error produced when the batched service doesn't return exactly one
+/// response for each request of a batch.
+pub struct Mismatch {
+    requests: usize,
+    responses: usize,
+}
+
+// ===== impl ServiceError =====
+
+impl ServiceError {
+    pub(crate) fn new(inner: BoxError) -> ServiceError {
+        let inner = Arc::new(inner);
+        ServiceError { inner }
+    }
+
+    // Private to avoid exposing `Clone` trait as part of the public API
+    pub(crate) fn clone(&self) -> ServiceError {
+        ServiceError {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "batched service failed: {}", self.inner)
+    }
+}
+
+impl std::error::Error for ServiceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&**self.inner)
+    }
+}
+
+// ===== impl Closed =====
+
+impl Closed {
+    pub(crate) fn new() -> Self {
+        Closed { _p: () }
+    }
+}
+
+impl fmt::Debug for Closed {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("Closed").finish()
+    }
+}
+
+impl fmt::Display for Closed {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("batch's worker closed unexpectedly")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+// ===== impl Mismatch =====
+
+impl Mismatch {
+    pub(crate) fn new(requests: usize, responses: usize) -> Self {
+        Mismatch {
+            requests,
+            responses,
+        }
+    }
+
+    /// Returns the number of requests in the batch.
+    pub fn requests(&self) -> usize {
+        self.requests
+    }
+
+    /// Returns the number of responses the batched service returned.
+    pub fn responses(&self) -> usize {
+        self.responses
+    }
+}
+
+impl fmt::Debug for Mismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Mismatch")
+            .field("requests", &self.requests)
+            .field("responses", &self.responses)
+            .finish()
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "batched service returned {} responses for {} requests",
+            self.responses, self.requests
+        )
+    }
+}
+
+impl std::error::Error for Mismatch {}
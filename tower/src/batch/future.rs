@@ -0,0 +1,65 @@
+//! Future types for the [`Batch`] middleware.
+//!
+//! [`Batch`]: crate::batch::Batch
+
+use super::{error::Closed, message};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+pin_project! {
+    /// Future that completes when the batch containing the submitted request has been
+    /// processed by the batched service.
+    #[derive(Debug)]
+    pub struct ResponseFuture<T> {
+        #[pin]
+        state: ResponseState<T>,
+    }
+}
+
+pin_project! {
+    #[project = ResponseStateProj]
+    #[derive(Debug)]
+    enum ResponseState<T> {
+        Failed {
+            error: Option<crate::BoxError>,
+        },
+        Rx {
+            #[pin]
+            rx: message::Rx<T>,
+        },
+    }
+}
+
+impl<T> ResponseFuture<T> {
+    pub(crate) fn new(rx: message::Rx<T>) -> Self {
+        ResponseFuture {
+            state: ResponseState::Rx { rx },
+        }
+    }
+
+    pub(crate) fn failed(err: crate::BoxError) -> Self {
+        ResponseFuture {
+            state: ResponseState::Failed { error: Some(err) },
+        }
+    }
+}
+
+impl<T> Future for ResponseFuture<T> {
+    type Output = Result<T, crate::BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            ResponseStateProj::Failed { error } => {
+                Poll::Ready(Err(error.take().expect("polled after error")))
+            }
+            ResponseStateProj::Rx { rx } => match ready!(rx.poll(cx)) {
+                Ok(result) => Poll::Ready(result),
+                Err(_) => Poll::Ready(Err(Closed::new().into())),
+            },
+        }
+    }
+}
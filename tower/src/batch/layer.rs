@@ -0,0 +1,98 @@
+use super::service::Batch;
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+use std::{fmt, marker::PhantomData, time::Duration};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Collects requests into batches in front of an inner batched service.
+///
+/// With the `tokio-rt` feature, the Tokio executor is used by default to run the background
+/// worker, which means that this layer can only be used on the Tokio runtime. Use
+/// [`BatchLayer::with_executor`] to spawn it elsewhere. In either case, the worker uses a Tokio
+/// timer to flush batches, so it must be polled within a Tokio runtime with the time driver
+/// enabled.
+///
+/// See the module documentation for more details.
+pub struct BatchLayer<
+    Request,
+    #[cfg(feature = "tokio-rt")] E = TokioExecutor,
+    #[cfg(not(feature = "tokio-rt"))] E,
+> {
+    max_size: usize,
+    max_latency: Duration,
+    executor: E,
+    _p: PhantomData<fn(Request)>,
+}
+
+#[cfg(feature = "tokio-rt")]
+impl<Request> BatchLayer<Request> {
+    /// Creates a new [`BatchLayer`] that flushes batches of up to `max_size` requests, after at
+    /// most `max_latency`.
+    ///
+    /// See [`Batch::new`] for details.
+    pub const fn new(max_size: usize, max_latency: Duration) -> Self {
+        BatchLayer {
+            max_size,
+            max_latency,
+            executor: TokioExecutor::new(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<Request, E> BatchLayer<Request, E> {
+    /// Creates a new [`BatchLayer`] that flushes batches of up to `max_size` requests, after at
+    /// most `max_latency`, and spawns the background workers of its [`Batch`]es with `executor`.
+    ///
+    /// The workers still use a Tokio timer to flush batches, so `executor` must poll them within
+    /// a Tokio runtime with the time driver enabled. See [`Batch::with_executor`] for details.
+    pub const fn with_executor(max_size: usize, max_latency: Duration, executor: E) -> Self {
+        BatchLayer {
+            max_size,
+            max_latency,
+            executor,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, Request, Response, E> Layer<S> for BatchLayer<Request, E>
+where
+    S: Service<Vec<Request>, Response = Vec<Response>> + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<crate::BoxError> + Send + Sync,
+    Request: Send + 'static,
+    Response: Send + 'static,
+    E: Executor,
+{
+    type Service = Batch<Request, Response>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Batch::with_executor(service, self.max_size, self.max_latency, &self.executor)
+    }
+}
+
+impl<Request, E: fmt::Debug> fmt::Debug for BatchLayer<Request, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchLayer")
+            .field("max_size", &self.max_size)
+            .field("max_latency", &self.max_latency)
+            .field("executor", &self.executor)
+            .finish()
+    }
+}
+
+impl<Request, E: Clone> Clone for BatchLayer<Request, E> {
+    fn clone(&self) -> Self {
+        BatchLayer {
+            max_size: self.max_size,
+            max_latency: self.max_latency,
+            executor: self.executor.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<Request, E: Copy> Copy for BatchLayer<Request, E> {}
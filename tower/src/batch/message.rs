@@ -0,0 +1,15 @@
+use tokio::sync::oneshot;
+
+/// Message sent to the batch worker
+#[derive(Debug)]
+pub(crate) struct Message<Request, Response> {
+    pub(crate) request: Request,
+    pub(crate) tx: Tx<Response>,
+    pub(crate) span: tracing::Span,
+}
+
+/// Response sender
+pub(crate) type Tx<Response> = oneshot::Sender<Result<Response, crate::BoxError>>;
+
+/// Response receiver
+pub(crate) type Rx<Response> = oneshot::Receiver<Result<Response, crate::BoxError>>;
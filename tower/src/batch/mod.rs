@@ -0,0 +1,55 @@
+//! Middleware that collects requests into batches for a batched service.
+//!
+//! Some backends, such as databases or machine learning models, process a group of requests much
+//! more efficiently than the same requests one at a time. This module provides [`Batch`], a
+//! [`Service`] that accepts individual requests, and collects them into a batch for an inner
+//! service that accepts a `Vec` of requests and returns a `Vec` with one response for each of
+//! them. The response to each request is then returned to its caller.
+//!
+//! Like [`Buffer`], a [`Batch`] is [`Clone`]: the handles send requests over a channel to a
+//! background worker that owns the inner service. The worker flushes the batch it is collecting
+//! once it holds `max_size` requests, or once `max_latency` has elapsed since its first request
+//! was received, so that requests don't wait for a batch to fill up when traffic is light.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower::batch::Batch;
+//! # #[cfg(all(feature = "util", feature = "tokio-rt"))]
+//! use tower::{service_fn, Service, ServiceExt};
+//!
+//! # #[cfg(all(feature = "util", feature = "tokio-rt"))]
+//! # async fn example() -> Result<(), tower::BoxError> {
+//! // A service that doubles a batch of numbers at once.
+//! let doubler = service_fn(|batch: Vec<u32>| async move {
+//!     Ok::<_, tower::BoxError>(batch.into_iter().map(|n| n * 2).collect::<Vec<_>>())
+//! });
+//!
+//! let mut batch = Batch::new(doubler, 100, Duration::from_millis(5));
+//! let response = batch.ready().await?.call(21).await?;
+//! assert_eq!(response, 42);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Errors
+//!
+//! If the inner service fails a batch, every request in the batch fails with a
+//! [`ServiceError`](error::ServiceError). If it returns a different number of responses than
+//! there were requests, every request in the batch fails with a [`Mismatch`](error::Mismatch)
+//! error. If the inner service fails in [`poll_ready`](crate::Service::poll_ready), the batch
+//! is closed, and the error is returned to all callers.
+//!
+//! [`Service`]: crate::Service
+//! [`Buffer`]: crate::buffer::Buffer
+
+pub mod error;
+pub mod future;
+mod layer;
+mod message;
+mod service;
+mod worker;
+
+pub use self::layer::BatchLayer;
+pub use self::service::Batch;
@@ -0,0 +1,179 @@
+use super::{
+    future::ResponseFuture,
+    message::Message,
+    worker::{Handle, Worker},
+};
+
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::PollSender;
+use tower_service::Service;
+
+/// Collects requests into batches in front of an inner batched service.
+///
+/// See the module documentation for more details.
+#[derive(Debug)]
+pub struct Batch<Req, Rsp> {
+    tx: PollSender<Message<Req, Rsp>>,
+    handle: Handle,
+}
+
+impl<Req, Rsp> Batch<Req, Rsp>
+where
+    Req: Send + 'static,
+    Rsp: Send + 'static,
+{
+    /// Creates a new [`Batch`] wrapping `service`.
+    ///
+    /// Requests are collected into a batch until it holds `max_size` requests, or until
+    /// `max_latency` has elapsed since the first request of the batch was received, whichever
+    /// comes first. The batch is then passed to `service`, which must return one response for
+    /// each request, in the same order.
+    ///
+    /// Up to `max_size` requests can be queued while a batch is being dispatched, before
+    /// backpressure is applied to callers.
+    ///
+    /// The default Tokio executor is used to run the background worker, which means that this
+    /// method must be called while on the Tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_size` is zero.
+    #[cfg(feature = "tokio-rt")]
+    pub fn new<S>(service: S, max_size: usize, max_latency: Duration) -> Self
+    where
+        S: Service<Vec<Req>, Response = Vec<Rsp>> + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+    {
+        Self::with_executor(service, max_size, max_latency, &TokioExecutor::new())
+    }
+
+    /// Creates a new [`Batch`] wrapping `service`, spawning the background worker with
+    /// `executor`.
+    ///
+    /// This behaves like [`Batch::new`], except that the worker does not have to be spawned on
+    /// the Tokio runtime. The worker still uses a Tokio timer to flush batches after
+    /// `max_latency`, so it must be polled within a Tokio runtime with the time driver enabled
+    /// once it receives requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_size` is zero.
+    pub fn with_executor<S, E>(
+        service: S,
+        max_size: usize,
+        max_latency: Duration,
+        executor: &E,
+    ) -> Self
+    where
+        S: Service<Vec<Req>, Response = Vec<Rsp>> + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+        E: Executor + ?Sized,
+    {
+        let (service, worker) = Self::pair(service, max_size, max_latency);
+        executor.spawn(Box::pin(worker));
+        service
+    }
+
+    /// Creates a new [`Batch`] wrapping `service`, but returns the background worker.
+    ///
+    /// This is useful if you do not want to spawn directly onto the tokio runtime
+    /// but instead want to use your own executor. This will return the [`Batch`] and
+    /// the background `Worker` that you can then spawn.
+    ///
+    /// Creating the pair doesn't require a Tokio runtime, but the worker uses a Tokio timer to
+    /// flush batches after `max_latency`, so it must be polled within a Tokio runtime with the
+    /// time driver enabled once it receives requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_size` is zero.
+    pub fn pair<S>(
+        service: S,
+        max_size: usize,
+        max_latency: Duration,
+    ) -> (Self, Worker<S, Req, Rsp>)
+    where
+        S: Service<Vec<Req>, Response = Vec<Rsp>> + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+    {
+        assert!(max_size > 0, "batch max_size must be greater than zero");
+        let (tx, rx) = mpsc::channel(max_size);
+        let handle = Handle::new();
+        let worker = Worker::new(service, rx, max_size, max_latency, &handle);
+        let batch = Self {
+            tx: PollSender::new(tx),
+            handle,
+        };
+        (batch, worker)
+    }
+
+    fn get_worker_error(&self) -> crate::BoxError {
+        self.handle.get_error_on_closed()
+    }
+}
+
+impl<Req, Rsp> Service<Req> for Batch<Req, Rsp>
+where
+    Req: Send + 'static,
+    Rsp: Send + 'static,
+{
+    type Response = Rsp;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<Rsp>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // First, check if the worker is still alive.
+        if self.tx.is_closed() {
+            // If the inner service has errored, then we error here.
+            return Poll::Ready(Err(self.get_worker_error()));
+        }
+
+        // Poll the sender to acquire a permit.
+        self.tx
+            .poll_reserve(cx)
+            .map_err(|_| self.get_worker_error())
+    }
+
+    fn call(&mut self, request: Req) -> Self::Future {
+        tracing::trace!("sending request to batch worker");
+
+        // get the current Span so that we can explicitly propagate it to the worker
+        let span = tracing::Span::current();
+
+        // If we've made it here, then a channel permit has already been
+        // acquired, so we can freely allocate a oneshot.
+        let (tx, rx) = oneshot::channel();
+
+        match self.tx.send_item(Message { request, tx, span }) {
+            Ok(()) => ResponseFuture::new(rx),
+            // If the channel is closed, propagate the error from the worker.
+            Err(_) => {
+                tracing::trace!("batch channel closed");
+                ResponseFuture::failed(self.get_worker_error())
+            }
+        }
+    }
+}
+
+impl<Req, Rsp> Clone for Batch<Req, Rsp>
+where
+    Req: Send + 'static,
+    Rsp: Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            tx: self.tx.clone(),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use super::{
+    error::{Closed, Mismatch, ServiceError},
+    message::{Message, Tx},
+};
+use crate::time::saturating_add;
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use pin_project_lite::pin_project;
+use std::sync::{Arc, Mutex};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc,
+    time::{sleep_until, Instant, Sleep},
+};
+use tower_service::Service;
+
+pin_project! {
+    /// Task that collects requests into batches and dispatches them to the batched service. This
+    /// type should not be used directly, instead `Batch` requires an `Executor` that can accept
+    /// this task.
+    ///
+    /// The struct is `pub` in the private module and the type is *not* re-exported
+    /// as part of the public API. This is the "sealed" pattern to include "private"
+    /// types in public traits that are not meant for consumers of the library to
+    /// implement (only call).
+    #[derive(Debug)]
+    pub struct Worker<T, Request, Response>
+    where
+        T: Service<Vec<Request>>,
+    {
+        rx: mpsc::Receiver<Message<Request, Response>>,
+        service: T,
+        // The batch being collected.
+        pending: Vec<Message<Request, Response>>,
+        // Batches that were dispatched to the service, but haven't completed yet.
+        in_flight: FuturesUnordered<Flush<T::Future, Response>>,
+        max_size: usize,
+        max_latency: Duration,
+        // Fires when the batch being collected must be flushed. The timer is only created once
+        // the first request is received, so that building a worker doesn't require a Tokio
+        // time driver.
+        deadline: Option<Pin<Box<Sleep>>>,
+        finish: bool,
+        failed: Option<ServiceError>,
+        handle: Handle,
+    }
+}
+
+pin_project! {
+    /// Fans out the responses of a batch to the callers.
+    #[derive(Debug)]
+    struct Flush<F, Response> {
+        #[pin]
+        future: F,
+        txs: Vec<Tx<Response>>,
+    }
+}
+
+/// Get the error out
+#[derive(Debug)]
+pub(crate) struct Handle {
+    inner: Arc<Mutex<Option<ServiceError>>>,
+}
+
+impl<T, Request, Response> Worker<T, Request, Response>
+where
+    T: Service<Vec<Request>, Response = Vec<Response>>,
+    T::Error: Into<crate::BoxError>,
+{
+    pub(crate) fn new(
+        service: T,
+        rx: mpsc::Receiver<Message<Request, Response>>,
+        max_size: usize,
+        max_latency: Duration,
+        handle: &Handle,
+    ) -> Worker<T, Request, Response> {
+        Worker {
+            rx,
+            service,
+            pending: Vec::with_capacity(max_size),
+            in_flight: FuturesUnordered::new(),
+            max_size,
+            max_latency,
+            deadline: None,
+            finish: false,
+            failed: None,
+            handle: handle.clone(),
+        }
+    }
+
+    /// Receives requests into the pending batch, until the batch is full or no request is
+    /// queued.
+    fn poll_collect(&mut self, cx: &mut Context<'_>) {
+        while !self.finish && self.pending.len() < self.max_size {
+            let msg = match self.rx.poll_recv(cx) {
+                Poll::Ready(Some(msg)) => msg,
+                Poll::Ready(None) => {
+                    // No more more requests _ever_.
+                    self.finish = true;
+                    break;
+                }
+                Poll::Pending => break,
+            };
+
+            let _guard = msg.span.enter();
+            if let Some(ref failed) = self.failed {
+                tracing::trace!("notifying caller about worker failure");
+                let _ = msg.tx.send(Err(failed.clone().into()));
+                continue;
+            }
+            if msg.tx.is_closed() {
+                tracing::trace!("dropping cancelled request");
+                continue;
+            }
+
+            tracing::trace!("adding request to batch");
+            if self.pending.is_empty() {
+                let deadline = saturating_add(Instant::now(), self.max_latency);
+                match self.deadline {
+                    Some(ref mut sleep) => sleep.as_mut().reset(deadline),
+                    None => self.deadline = Some(Box::pin(sleep_until(deadline))),
+                }
+            }
+            drop(_guard);
+            self.pending.push(msg);
+        }
+    }
+
+    /// Dispatches the pending batch to the service, which must be ready.
+    fn flush(&mut self) {
+        // Don't batch requests that nobody is waiting for anymore.
+        self.pending.retain(|msg| !msg.tx.is_closed());
+        if self.pending.is_empty() {
+            return;
+        }
+
+        tracing::debug!(batch.size = self.pending.len(), "flushing batch");
+        let (requests, txs) = self
+            .pending
+            .drain(..)
+            .map(|msg| (msg.request, msg.tx))
+            .unzip();
+        let future = self.service.call(requests);
+        self.in_flight.push(Flush { future, txs });
+    }
+
+    fn failed(&mut self, error: crate::BoxError) {
+        // The underlying service failed when we called `poll_ready` on it with the given `error`.
+        // As with `Buffer`, the error is *first* exposed to the `Batch` handles, *then* the
+        // channel is closed, so that a caller either fails to send its request or receives the
+        // error from the worker.
+        let error = ServiceError::new(error);
+        *self.handle.inner.lock().unwrap() = Some(error.clone());
+        self.rx.close();
+
+        for msg in self.pending.drain(..) {
+            let _ = msg.tx.send(Err(error.clone().into()));
+        }
+        self.failed = Some(error);
+    }
+}
+
+impl<T, Request, Response> Future for Worker<T, Request, Response>
+where
+    T: Service<Vec<Request>, Response = Vec<Response>>,
+    T::Error: Into<crate::BoxError>,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            // Drive the batches that were already dispatched.
+            while let Poll::Ready(Some(())) = self.in_flight.poll_next_unpin(cx) {}
+
+            self.poll_collect(cx);
+
+            if self.pending.is_empty() {
+                if self.finish && self.in_flight.is_empty() {
+                    return Poll::Ready(());
+                }
+                return Poll::Pending;
+            }
+
+            // Wait for the batch to fill up, unless it waited for long enough, or no more
+            // requests will be received.
+            let full = self.pending.len() >= self.max_size;
+            if !full && !self.finish {
+                if let Some(ref mut deadline) = self.deadline {
+                    ready!(deadline.as_mut().poll(cx));
+                }
+            }
+
+            tracing::trace!("waiting for service readiness");
+            match self.service.poll_ready(cx) {
+                Poll::Ready(Ok(())) => self.flush(),
+                Poll::Pending => {
+                    tracing::trace!(service.ready = false, message = "delay");
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err(e)) => {
+                    let error = e.into();
+                    tracing::debug!({ %error }, "service failed");
+                    self.failed(error);
+                }
+            }
+        }
+    }
+}
+
+// ===== impl Flush =====
+
+impl<F, Response, E> Future for Flush<F, Response>
+where
+    F: Future<Output = Result<Vec<Response>, E>>,
+    E: Into<crate::BoxError>,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.future.poll(cx));
+        let txs = std::mem::take(this.txs);
+        match result {
+            Ok(responses) if responses.len() == txs.len() => {
+                for (tx, response) in txs.into_iter().zip(responses) {
+                    // An error means the request had been canceled in-between.
+                    let _ = tx.send(Ok(response));
+                }
+            }
+            Ok(responses) => {
+                tracing::debug!(
+                    batch.size = txs.len(),
+                    responses = responses.len(),
+                    "batched service returned the wrong number of responses"
+                );
+                let requests = txs.len();
+                for tx in txs {
+                    let _ = tx.send(Err(Mismatch::new(requests, responses.len()).into()));
+                }
+            }
+            Err(e) => {
+                let error = ServiceError::new(e.into());
+                for tx in txs {
+                    let _ = tx.send(Err(error.clone().into()));
+                }
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+// ===== impl Handle =====
+
+impl Handle {
+    pub(crate) fn new() -> Handle {
+        Handle {
+            inner: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub(crate) fn get_error_on_closed(&self) -> crate::BoxError {
+        self.inner
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|svc_err| svc_err.clone().into())
+            .unwrap_or_else(|| Closed::new().into())
+    }
+}
+
+impl Clone for Handle {
+    fn clone(&self) -> Handle {
+        Handle {
+            inner: self.inner.clone(),
+        }
+    }
+}
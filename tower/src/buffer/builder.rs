@@ -0,0 +1,298 @@
+use super::{
+    codel::Codel,
+    overflow::Overflow,
+    service::{clones, Buffer, Classify},
+    worker::Worker,
+};
+use crate::executor::Executor;
+use std::{fmt, marker::PhantomData, sync::Arc};
+use tower_service::Service;
+
+/// Builds a [`Buffer`] with options other than its `bound`.
+///
+/// The options, such as the number of workers, the [`Overflow`] policy, [`Codel`] load shedding
+/// and priorities, can be combined freely. The [`Buffer`] is then created with [`Builder::spawn`],
+/// which spawns its workers with an [`Executor`], or with [`Builder::pair`], which returns the
+/// workers so that they can be spawned by other means. A [`BufferLayer`] can also carry these
+/// options.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "util")]
+/// # async fn example(executor: impl tower::executor::Executor) {
+/// use tower::buffer::{Builder, Overflow};
+/// use tower::service_fn;
+///
+/// let service = service_fn(|req: u32| async move { Ok::<_, std::convert::Infallible>(req) });
+/// let buffer = Builder::new(1024)
+///     .workers(4)
+///     .overflow(Overflow::Reject)
+///     .spawn(service, &executor);
+/// # drop(buffer);
+/// # }
+/// ```
+///
+/// [`BufferLayer`]: crate::buffer::BufferLayer
+pub struct Builder<Req, W = SingleWorker, C = fn(&Req) -> u8> {
+    bound: usize,
+    workers: W,
+    overflow: Overflow,
+    codel: Option<Codel>,
+    classify: Option<C>,
+    _p: PhantomData<fn(Req)>,
+}
+
+/// The workers of a [`Buffer`] built by a [`Builder`] with a single worker.
+///
+/// This is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SingleWorker {
+    _p: (),
+}
+
+/// The workers of a [`Buffer`] built by a [`Builder`] with several workers.
+///
+/// Each worker drives its own clone of the service, so the service must be [`Clone`].
+///
+/// This is set with [`Builder::workers`].
+#[derive(Clone, Copy, Debug)]
+pub struct Workers {
+    n: usize,
+}
+
+// ===== impl Builder =====
+
+impl<Req> Builder<Req> {
+    /// Creates a new [`Builder`] for a [`Buffer`] that queues up to `bound` requests.
+    ///
+    /// See [`Buffer::pair`] for details on choosing a `bound`.
+    pub const fn new(bound: usize) -> Self {
+        Builder {
+            bound,
+            workers: SingleWorker { _p: () },
+            overflow: Overflow::Wait,
+            codel: None,
+            classify: None,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<Req, W, C> Builder<Req, W, C> {
+    /// Runs `workers` background workers that take requests from the same queue.
+    ///
+    /// A single worker drives its service's [`poll_ready`] and [`call`] one request at a time. If
+    /// the service does expensive work in [`call`] before returning its future, this limits the
+    /// throughput of the buffer. With several workers, each worker drives its own clone of the
+    /// service, so that several requests are dispatched in parallel.
+    ///
+    /// If the service of any worker fails, the buffer is closed: every worker stops dispatching
+    /// requests, and the error is returned to all callers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `workers` is zero.
+    ///
+    /// [`call`]: crate::Service::call
+    /// [`poll_ready`]: crate::Service::poll_ready
+    pub fn workers(self, workers: usize) -> Builder<Req, Workers, C> {
+        assert!(workers > 0, "a buffer needs at least one worker");
+        Builder {
+            bound: self.bound,
+            workers: Workers { n: workers },
+            overflow: self.overflow,
+            codel: self.codel,
+            classify: self.classify,
+            _p: PhantomData,
+        }
+    }
+
+    /// Handles requests with `overflow` when `bound` requests are already queued.
+    ///
+    /// By default, [`poll_ready`] waits for room in the queue. With any other policy,
+    /// [`poll_ready`] never applies backpressure, so the buffer fails fast instead. See
+    /// [`Overflow`] for details.
+    ///
+    /// Unless `overflow` is [`Overflow::Unbounded`], creating the buffer panics if its `bound` is
+    /// zero.
+    ///
+    /// [`poll_ready`]: crate::Service::poll_ready
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sheds requests which waited in the queue for too long.
+    ///
+    /// The workers track how long each request waited before being dispatched, and fail requests
+    /// that waited past the target delay with a [`Shed`] error once the queue is persistently
+    /// backed up. Meanwhile, the newest requests are dispatched first. See [`Codel`] for details.
+    ///
+    /// [`Shed`]: crate::buffer::error::Shed
+    pub fn codel(mut self, codel: Codel) -> Self {
+        self.codel = Some(codel);
+        self
+    }
+
+    /// Dispatches queued requests by the priority that `classify` returns for each request.
+    ///
+    /// By default, queued requests are dispatched in the order they were queued. Instead,
+    /// requests with a higher priority are dispatched first, and requests with the same priority
+    /// are dispatched in the order they were queued. When the service is saturated, this lets
+    /// urgent requests, such as health checks, skip ahead of bulk traffic.
+    ///
+    /// So that a steady stream of high priority requests can't starve the others, the oldest
+    /// queued request is dispatched next once 16 requests in a row have skipped ahead of it.
+    ///
+    /// `classify` is cloned into each [`Buffer`] built by this builder, and then shared by the
+    /// clones of that [`Buffer`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "util")]
+    /// # async fn example(executor: impl tower::executor::Executor) {
+    /// use tower::buffer::Builder;
+    /// use tower::service_fn;
+    ///
+    /// let service = service_fn(|req: &'static str| async move { Ok::<_, std::convert::Infallible>(req) });
+    /// // Health checks skip ahead of other requests.
+    /// let health = String::from("/health");
+    /// let buffer = Builder::new(1024)
+    ///     .priority(move |req: &&str| if *req == health { 1 } else { 0 })
+    ///     .spawn(service, &executor);
+    /// # drop(buffer);
+    /// # }
+    /// ```
+    pub fn priority<F>(self, classify: F) -> Builder<Req, W, F>
+    where
+        F: Fn(&Req) -> u8 + Clone + Send + Sync + 'static,
+    {
+        Builder {
+            bound: self.bound,
+            workers: self.workers,
+            overflow: self.overflow,
+            codel: self.codel,
+            classify: Some(classify),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<Req, W, C> Builder<Req, W, C>
+where
+    C: Fn(&Req) -> u8 + Clone + Send + Sync + 'static,
+{
+    fn classify(&self) -> Option<Classify<Req>> {
+        self.classify
+            .clone()
+            .map(|classify| Arc::new(classify) as Classify<Req>)
+    }
+}
+
+impl<Req, C> Builder<Req, SingleWorker, C>
+where
+    Req: Send + 'static,
+    C: Fn(&Req) -> u8 + Clone + Send + Sync + 'static,
+{
+    /// Creates a new [`Buffer`] wrapping `service`, spawning its worker with `executor`.
+    pub fn spawn<S, E>(&self, service: S, executor: &E) -> Buffer<Req, S::Future>
+    where
+        S: Service<Req> + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+        E: Executor + ?Sized,
+    {
+        let (buffer, worker) = self.pair(service);
+        executor.spawn(Box::pin(worker));
+        buffer
+    }
+
+    /// Creates a new [`Buffer`] wrapping `service`, but returns the background worker.
+    ///
+    /// See [`Buffer::pair`] for details.
+    pub fn pair<S>(&self, service: S) -> (Buffer<Req, S::Future>, Worker<S, Req>)
+    where
+        S: Service<Req> + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+    {
+        let (buffer, mut workers) = Buffer::pair_inner(
+            vec![service],
+            self.bound,
+            self.overflow,
+            self.codel,
+            self.classify(),
+        );
+        (buffer, workers.pop().expect("one worker must be created"))
+    }
+}
+
+impl<Req, C> Builder<Req, Workers, C>
+where
+    Req: Send + 'static,
+    C: Fn(&Req) -> u8 + Clone + Send + Sync + 'static,
+{
+    /// Creates a new [`Buffer`] wrapping clones of `service`, spawning its workers with
+    /// `executor`.
+    pub fn spawn<S, E>(&self, service: S, executor: &E) -> Buffer<Req, S::Future>
+    where
+        S: Service<Req> + Clone + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+        E: Executor + ?Sized,
+    {
+        let (buffer, workers) = self.pair(service);
+        for worker in workers {
+            executor.spawn(Box::pin(worker));
+        }
+        buffer
+    }
+
+    /// Creates a new [`Buffer`] wrapping clones of `service`, but returns the background
+    /// workers.
+    ///
+    /// See [`Buffer::pair`] for details.
+    pub fn pair<S>(&self, service: S) -> (Buffer<Req, S::Future>, Vec<Worker<S, Req>>)
+    where
+        S: Service<Req> + Clone + Send + 'static,
+        S::Future: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+    {
+        Buffer::pair_inner(
+            clones(service, self.workers.n),
+            self.bound,
+            self.overflow,
+            self.codel,
+            self.classify(),
+        )
+    }
+}
+
+impl<Req, W: Clone, C: Clone> Clone for Builder<Req, W, C> {
+    fn clone(&self) -> Self {
+        Builder {
+            bound: self.bound,
+            workers: self.workers.clone(),
+            overflow: self.overflow,
+            codel: self.codel,
+            classify: self.classify.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<Req, W: Copy, C: Copy> Copy for Builder<Req, W, C> {}
+
+impl<Req, W: fmt::Debug, C> fmt::Debug for Builder<Req, W, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builder")
+            .field("bound", &self.bound)
+            .field("workers", &self.workers)
+            .field("overflow", &self.overflow)
+            .field("codel", &self.codel)
+            .field("prioritized", &self.classify.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,139 @@
+use crate::time::saturating_add;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Configuration for queue-latency-based load shedding in a [`Buffer`].
+///
+/// This implements a variant of the [CoDel] ("controlled delay") queue
+/// management algorithm, as adapted for RPC servers. The worker tracks how
+/// long each request waited in the buffer before being dispatched to the
+/// inner service (its *sojourn time*). If the minimum sojourn time observed
+/// over an `interval` exceeds `target`, the queue is considered to be
+/// standing rather than absorbing a burst, and the buffer enters an
+/// overloaded state. While overloaded, any request that waited longer than
+/// twice the `target` is rejected with a [`Shed`] error instead of being
+/// dispatched, since its caller has most likely given up on it already.
+///
+/// While overloaded, the buffer also switches to *adaptive LIFO*: the most
+/// recently queued requests are dispatched first, since their callers are the
+/// most likely to still be waiting for them, while older requests wait until
+/// the overload clears, or are shed. Requests with a higher [priority] are
+/// still dispatched first.
+///
+/// The overloaded state is re-evaluated once per `interval`, so the buffer
+/// stops shedding, and dispatches requests in the order they were queued
+/// again, once requests are dispatched promptly again.
+///
+/// [`Buffer`]: crate::buffer::Buffer
+/// [`Shed`]: crate::buffer::error::Shed
+/// [CoDel]: https://queue.acm.org/detail.cfm?id=2209336
+/// [priority]: crate::buffer::Builder::priority
+#[derive(Debug, Clone, Copy)]
+pub struct Codel {
+    target: Duration,
+    interval: Duration,
+}
+
+/// The CoDel state tracked by a buffer's worker.
+#[derive(Debug)]
+pub(crate) struct CodelState {
+    config: Codel,
+    /// The minimum sojourn time observed in the current interval.
+    min_delay: Option<Duration>,
+    /// When the current interval ends.
+    interval_end: Option<Instant>,
+    overloaded: bool,
+}
+
+// ===== impl Codel =====
+
+impl Codel {
+    /// Returns a new CoDel configuration.
+    ///
+    /// `target` is the acceptable standing queue delay, and `interval` is the
+    /// window over which the minimum queue delay is measured. Typical values
+    /// are a `target` of 5 milliseconds and an `interval` of 100
+    /// milliseconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(target: Duration, interval: Duration) -> Self {
+        assert!(interval > Duration::ZERO, "CoDel interval must be non-zero");
+        Codel { target, interval }
+    }
+
+    /// Returns the target queue delay.
+    pub fn target(&self) -> Duration {
+        self.target
+    }
+
+    /// Returns the interval over which queue delay is measured.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+impl Default for Codel {
+    fn default() -> Self {
+        Codel::new(Duration::from_millis(5), Duration::from_millis(100))
+    }
+}
+
+// ===== impl CodelState =====
+
+impl CodelState {
+    pub(crate) fn new(config: Codel) -> Self {
+        CodelState {
+            config,
+            min_delay: None,
+            interval_end: None,
+            overloaded: false,
+        }
+    }
+
+    /// Returns `true` if the queue was standing over the last interval.
+    pub(crate) fn is_overloaded(&self) -> bool {
+        self.overloaded
+    }
+
+    /// Records that a request which was enqueued at `enqueued_at` is about to
+    /// be dispatched, and returns `true` if it should be shed instead.
+    pub(crate) fn should_shed(&mut self, enqueued_at: Instant) -> bool {
+        let now = Instant::now();
+        let delay = now.saturating_duration_since(enqueued_at);
+
+        let interval = self.config.interval;
+        let interval_end = *self
+            .interval_end
+            .get_or_insert_with(|| saturating_add(now, interval));
+        if now >= interval_end {
+            self.overloaded = self
+                .min_delay
+                .map_or(false, |min_delay| min_delay > self.config.target);
+            self.min_delay = None;
+            self.interval_end = Some(saturating_add(now, self.config.interval));
+            tracing::trace!(
+                overloaded = self.overloaded,
+                "buffer CoDel interval elapsed"
+            );
+        }
+
+        self.min_delay = Some(self.min_delay.map_or(delay, |min| min.min(delay)));
+
+        self.overloaded && delay > self.config.target.saturating_mul(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_durations_dont_overflow() {
+        let mut state = CodelState::new(Codel::new(Duration::MAX, Duration::MAX));
+        let enqueued_at = Instant::now();
+        assert!(!state.should_shed(enqueued_at));
+        assert!(!state.should_shed(enqueued_at));
+    }
+}
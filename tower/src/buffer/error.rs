@@ -17,6 +17,26 @@ pub struct Closed {
     _p: (),
 }
 
+/// An error produced when a request is shed by a buffer's worker because it
+/// waited in the queue for too long.
+///
+/// See [`Codel`] for details.
+///
+/// [`Codel`]: crate::buffer::Codel
+pub struct Shed {
+    _p: (),
+}
+
+/// An error produced when a request is rejected or dropped because a buffer's
+/// queue is full.
+///
+/// See [`Overflow`] for details.
+///
+/// [`Overflow`]: crate::buffer::Overflow
+pub struct Full {
+    _p: (),
+}
+
 // ===== impl ServiceError =====
 
 impl ServiceError {
@@ -66,3 +86,47 @@ impl fmt::Display for Closed {
 }
 
 impl std::error::Error for Closed {}
+
+// ===== impl Shed =====
+
+impl Shed {
+    pub(crate) fn new() -> Self {
+        Shed { _p: () }
+    }
+}
+
+impl fmt::Debug for Shed {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("Shed").finish()
+    }
+}
+
+impl fmt::Display for Shed {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("request waited in the buffer for too long")
+    }
+}
+
+impl std::error::Error for Shed {}
+
+// ===== impl Full =====
+
+impl Full {
+    pub(crate) fn new() -> Self {
+        Full { _p: () }
+    }
+}
+
+impl fmt::Debug for Full {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("Full").finish()
+    }
+}
+
+impl fmt::Display for Full {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("buffer is full")
+    }
+}
+
+impl std::error::Error for Full {}
@@ -2,11 +2,12 @@
 //!
 //! [`Buffer`]: crate::buffer::Buffer
 
-use super::{error::Closed, message};
+use super::{error::Closed, message, shutdown::Shared};
 use pin_project_lite::pin_project;
 use std::{
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{ready, Context, Poll},
 };
 
@@ -68,7 +69,7 @@ where
                 }
                 ResponseStateProj::Rx { rx } => match ready!(rx.poll(cx)) {
                     Ok(Ok(fut)) => this.state.set(ResponseState::Poll { fut }),
-                    Ok(Err(e)) => return Poll::Ready(Err(e.into())),
+                    Ok(Err(e)) => return Poll::Ready(Err(e)),
                     Err(_) => return Poll::Ready(Err(Closed::new().into())),
                 },
                 ResponseStateProj::Poll { fut } => return fut.poll(cx).map_err(Into::into),
@@ -76,3 +77,29 @@ where
         }
     }
 }
+
+/// Future that completes when the worker of a [`Buffer`] has completed.
+///
+/// This is returned by [`Shutdown::shutdown`] and [`Shutdown::drained`].
+///
+/// [`Buffer`]: crate::buffer::Buffer
+/// [`Shutdown::shutdown`]: crate::buffer::Shutdown::shutdown
+/// [`Shutdown::drained`]: crate::buffer::Shutdown::drained
+#[derive(Debug)]
+pub struct Drained {
+    shared: Arc<Shared>,
+}
+
+impl Drained {
+    pub(crate) fn new(shared: Arc<Shared>) -> Self {
+        Drained { shared }
+    }
+}
+
+impl Future for Drained {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.shared.poll_drained(cx)
+    }
+}
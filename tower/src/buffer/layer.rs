@@ -1,19 +1,38 @@
-use super::service::Buffer;
-use std::{fmt, marker::PhantomData};
+use super::{
+    builder::{Builder, SingleWorker, Workers},
+    codel::Codel,
+    overflow::Overflow,
+    service::Buffer,
+};
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+use std::fmt;
 use tower_layer::Layer;
 use tower_service::Service;
 
 /// Adds an mpsc buffer in front of an inner service.
 ///
-/// The default Tokio executor is used to run the given service,
-/// which means that this layer can only be used on the Tokio runtime.
+/// With the `tokio-rt` feature, the Tokio executor is used by default to run the given service,
+/// which means that this layer can only be used on the Tokio runtime. Use
+/// [`BufferLayer::with_executor`] to run it elsewhere.
+///
+/// The layer carries the options of a [`Builder`], which can be set with the methods of the same
+/// name.
 ///
 /// See the module documentation for more details.
-pub struct BufferLayer<Request> {
-    bound: usize,
-    _p: PhantomData<fn(Request)>,
+pub struct BufferLayer<
+    Request,
+    #[cfg(feature = "tokio-rt")] E = TokioExecutor,
+    #[cfg(not(feature = "tokio-rt"))] E,
+    W = SingleWorker,
+    C = fn(&Request) -> u8,
+> {
+    builder: Builder<Request, W, C>,
+    executor: E,
 }
 
+#[cfg(feature = "tokio-rt")]
 impl<Request> BufferLayer<Request> {
     /// Creates a new [`BufferLayer`] with the provided `bound`.
     ///
@@ -35,38 +54,114 @@ impl<Request> BufferLayer<Request> {
     /// [`poll_ready`]: crate::Service::poll_ready
     pub const fn new(bound: usize) -> Self {
         BufferLayer {
-            bound,
-            _p: PhantomData,
+            builder: Builder::new(bound),
+            executor: TokioExecutor::new(),
+        }
+    }
+}
+
+impl<Request, E> BufferLayer<Request, E> {
+    /// Creates a new [`BufferLayer`] with the provided `bound`, that spawns the background workers
+    /// of its [`Buffer`]s with `executor`.
+    ///
+    /// See [`Buffer::with_executor`] for details.
+    pub const fn with_executor(bound: usize, executor: E) -> Self {
+        BufferLayer {
+            builder: Builder::new(bound),
+            executor,
         }
     }
 }
 
-impl<S, Request> Layer<S> for BufferLayer<Request>
+impl<Request, E, W, C> BufferLayer<Request, E, W, C> {
+    /// Runs `workers` background workers for each [`Buffer`], with clones of the inner service.
+    ///
+    /// See [`Builder::workers`] for details.
+    pub fn workers(self, workers: usize) -> BufferLayer<Request, E, Workers, C> {
+        BufferLayer {
+            builder: self.builder.workers(workers),
+            executor: self.executor,
+        }
+    }
+
+    /// Handles requests with `overflow` when the queue is full.
+    ///
+    /// See [`Builder::overflow`] for details.
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.builder = self.builder.overflow(overflow);
+        self
+    }
+
+    /// Sheds requests which waited in the queue for too long.
+    ///
+    /// See [`Builder::codel`] for details.
+    pub fn codel(mut self, codel: Codel) -> Self {
+        self.builder = self.builder.codel(codel);
+        self
+    }
+
+    /// Dispatches queued requests by the priority that `classify` returns for each request.
+    ///
+    /// See [`Builder::priority`] for details.
+    pub fn priority<F>(self, classify: F) -> BufferLayer<Request, E, W, F>
+    where
+        F: Fn(&Request) -> u8 + Clone + Send + Sync + 'static,
+    {
+        BufferLayer {
+            builder: self.builder.priority(classify),
+            executor: self.executor,
+        }
+    }
+}
+
+impl<S, Request, E, C> Layer<S> for BufferLayer<Request, E, SingleWorker, C>
 where
     S: Service<Request> + Send + 'static,
     S::Future: Send,
     S::Error: Into<crate::BoxError> + Send + Sync,
     Request: Send + 'static,
+    E: Executor,
+    C: Fn(&Request) -> u8 + Clone + Send + Sync + 'static,
+{
+    type Service = Buffer<Request, S::Future>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        self.builder.spawn(service, &self.executor)
+    }
+}
+
+impl<S, Request, E, C> Layer<S> for BufferLayer<Request, E, Workers, C>
+where
+    S: Service<Request> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<crate::BoxError> + Send + Sync,
+    Request: Send + 'static,
+    E: Executor,
+    C: Fn(&Request) -> u8 + Clone + Send + Sync + 'static,
 {
     type Service = Buffer<Request, S::Future>;
 
     fn layer(&self, service: S) -> Self::Service {
-        Buffer::new(service, self.bound)
+        self.builder.spawn(service, &self.executor)
     }
 }
 
-impl<Request> fmt::Debug for BufferLayer<Request> {
+impl<Request, E: fmt::Debug, W: fmt::Debug, C> fmt::Debug for BufferLayer<Request, E, W, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BufferLayer")
-            .field("bound", &self.bound)
+            .field("builder", &self.builder)
+            .field("executor", &self.executor)
             .finish()
     }
 }
 
-impl<Request> Clone for BufferLayer<Request> {
+impl<Request, E: Clone, W: Clone, C: Clone> Clone for BufferLayer<Request, E, W, C> {
     fn clone(&self) -> Self {
-        *self
+        BufferLayer {
+            builder: self.builder.clone(),
+            executor: self.executor.clone(),
+        }
     }
 }
 
-impl<Request> Copy for BufferLayer<Request> {}
+impl<Request, E: Copy, W: Copy, C: Copy> Copy for BufferLayer<Request, E, W, C> {}
@@ -1,5 +1,5 @@
-use super::error::ServiceError;
 use tokio::sync::oneshot;
+use tokio::time::Instant;
 
 /// Message sent over buffer
 #[derive(Debug)]
@@ -7,10 +7,13 @@ pub(crate) struct Message<Request, Fut> {
     pub(crate) request: Request,
     pub(crate) tx: Tx<Fut>,
     pub(crate) span: tracing::Span,
+    /// When the message was sent, if the worker needs to know how long it
+    /// waited in the queue.
+    pub(crate) enqueued_at: Option<Instant>,
 }
 
 /// Response sender
-pub(crate) type Tx<Fut> = oneshot::Sender<Result<Fut, ServiceError>>;
+pub(crate) type Tx<Fut> = oneshot::Sender<Result<Fut, crate::BoxError>>;
 
 /// Response receiver
-pub(crate) type Rx<Fut> = oneshot::Receiver<Result<Fut, ServiceError>>;
+pub(crate) type Rx<Fut> = oneshot::Receiver<Result<Fut, crate::BoxError>>;
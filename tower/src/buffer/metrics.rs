@@ -0,0 +1,239 @@
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The number of queue wait histogram buckets.
+///
+/// Bucket `i` counts waits of at most `2^i` microseconds, except for the last
+/// bucket, which counts all longer waits.
+const BUCKETS: usize = 32;
+
+/// A handle to the metrics of a [`Buffer`].
+///
+/// This is returned by [`Buffer::metrics`], and reports the state of the
+/// buffer's queue as it changes, so it may be kept around and read
+/// periodically, for example to export the metrics to a metrics library.
+///
+/// Recording metrics only takes a few atomic operations per request.
+///
+/// [`Buffer`]: crate::buffer::Buffer
+/// [`Buffer::metrics`]: crate::buffer::Buffer::metrics
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    shared: Arc<Shared>,
+}
+
+/// A snapshot of how long requests waited in a [`Buffer`]'s queue.
+///
+/// The wait of a request is measured from when it is sent to the buffer until
+/// the worker calls the inner service with it. Requests that were never
+/// dispatched are not recorded.
+///
+/// [`Buffer`]: crate::buffer::Buffer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Histogram {
+    counts: [u64; BUCKETS],
+    sum: Duration,
+}
+
+/// Metrics shared between the buffer handles and the worker.
+#[derive(Debug)]
+pub(crate) struct Shared {
+    capacity: usize,
+    enqueued: AtomicU64,
+    // Requests that were either dispatched or dropped.
+    dequeued: AtomicU64,
+    dropped: AtomicU64,
+    wait_counts: [AtomicU64; BUCKETS],
+    wait_sum_nanos: AtomicU64,
+    // Whether a `Metrics` handle was created, so that queue waits must be
+    // measured.
+    track_wait: AtomicBool,
+}
+
+// ===== impl Metrics =====
+
+impl Metrics {
+    pub(crate) fn new(shared: Arc<Shared>) -> Self {
+        shared.track_wait.store(true, Ordering::Release);
+        Metrics { shared }
+    }
+
+    /// Returns the number of requests that are waiting in the queue, and have
+    /// not been dispatched to the inner service or dropped yet.
+    pub fn depth(&self) -> usize {
+        let dequeued = self.shared.dequeued.load(Ordering::Acquire);
+        let enqueued = self.shared.enqueued.load(Ordering::Acquire);
+        enqueued.saturating_sub(dequeued) as usize
+    }
+
+    /// Returns the maximum number of requests that may be queued.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    /// Returns the total number of requests that were sent to the buffer.
+    pub fn enqueued(&self) -> u64 {
+        self.shared.enqueued.load(Ordering::Acquire)
+    }
+
+    /// Returns the total number of requests that were taken off the queue
+    /// without being dispatched to the inner service.
+    ///
+    /// This includes requests whose callers dropped the response future
+    /// before they were dispatched, requests shed by [`Codel`], requests
+    /// rejected or evicted by the [`Overflow`] policy, requests failed because
+    /// the inner service failed, and requests left in the queue or waiting
+    /// for the inner service to become ready when the workers were dropped.
+    ///
+    /// [`Codel`]: crate::buffer::Codel
+    /// [`Overflow`]: crate::buffer::Overflow
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Acquire)
+    }
+
+    /// Returns a snapshot of how long dispatched requests waited in the queue.
+    ///
+    /// So that buffers whose metrics are never read don't have to read the
+    /// clock for every request, only requests sent after [`Buffer::metrics`]
+    /// was first called are recorded.
+    ///
+    /// [`Buffer::metrics`]: crate::buffer::Buffer::metrics
+    pub fn queue_wait(&self) -> Histogram {
+        let mut counts = [0; BUCKETS];
+        for (count, bucket) in counts.iter_mut().zip(&self.shared.wait_counts) {
+            *count = bucket.load(Ordering::Acquire);
+        }
+        Histogram {
+            counts,
+            sum: Duration::from_nanos(self.shared.wait_sum_nanos.load(Ordering::Acquire)),
+        }
+    }
+}
+
+// ===== impl Histogram =====
+
+impl Histogram {
+    /// Returns the number of recorded waits.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the sum of all recorded waits.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Returns the buckets of the histogram, as pairs of the bucket's
+    /// inclusive upper bound and the number of waits in the bucket.
+    ///
+    /// Bucket bounds grow exponentially from 1 microsecond. The last bucket
+    /// has an upper bound of [`Duration::MAX`].
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| (bucket_bound(i), count))
+    }
+
+    /// Returns an upper bound of the wait at quantile `q`, which must be in
+    /// `0.0..=1.0`, or `None` if no waits were recorded.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.buckets().find_map(|(bound, n)| {
+            seen += n;
+            (seen >= rank).then_some(bound)
+        })
+    }
+}
+
+fn bucket_bound(i: usize) -> Duration {
+    if i + 1 == BUCKETS {
+        Duration::MAX
+    } else {
+        Duration::from_micros(1 << i)
+    }
+}
+
+fn bucket_index(wait: Duration) -> usize {
+    let micros = wait.as_micros();
+    if micros <= 1 {
+        return 0;
+    }
+    // The smallest `i` such that `micros <= 2^i`.
+    let i = (128 - (micros - 1).leading_zeros()) as usize;
+    i.min(BUCKETS - 1)
+}
+
+// ===== impl Shared =====
+
+impl Shared {
+    pub(crate) fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Shared {
+            capacity,
+            enqueued: AtomicU64::new(0),
+            dequeued: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            wait_counts: Default::default(),
+            wait_sum_nanos: AtomicU64::new(0),
+            track_wait: AtomicBool::new(false),
+        })
+    }
+
+    /// Records that a request was sent to the worker.
+    pub(crate) fn enqueued(&self) {
+        self.enqueued.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Records that a request was not dispatched to the inner service.
+    pub(crate) fn dropped(&self) {
+        self.dropped_many(1);
+    }
+
+    /// Records that `n` requests were not dispatched to the inner service.
+    pub(crate) fn dropped_many(&self, n: u64) {
+        self.dropped.fetch_add(n, Ordering::AcqRel);
+        self.dequeued.fetch_add(n, Ordering::AcqRel);
+    }
+
+    /// Returns `true` if queue waits must be measured.
+    pub(crate) fn tracks_wait(&self) -> bool {
+        self.track_wait.load(Ordering::Acquire)
+    }
+
+    /// Records that a request is dispatched after waiting for `wait`, if it
+    /// was measured.
+    pub(crate) fn dispatched(&self, wait: Option<Duration>) {
+        if let Some(wait) = wait {
+            self.wait_counts[bucket_index(wait)].fetch_add(1, Ordering::AcqRel);
+            let nanos = u64::try_from(wait.as_nanos()).unwrap_or(u64::MAX);
+            self.wait_sum_nanos.fetch_add(nanos, Ordering::AcqRel);
+        }
+        self.dequeued.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_index_is_smallest_covering_bucket() {
+        assert_eq!(bucket_index(Duration::ZERO), 0);
+        assert_eq!(bucket_index(Duration::from_micros(1)), 0);
+        assert_eq!(bucket_index(Duration::from_micros(2)), 1);
+        assert_eq!(bucket_index(Duration::from_micros(3)), 2);
+        assert_eq!(bucket_index(Duration::from_micros(1024)), 10);
+        assert_eq!(bucket_index(Duration::from_micros(1025)), 11);
+        assert_eq!(bucket_index(Duration::from_secs(3600)), BUCKETS - 1);
+        for i in 0..BUCKETS {
+            assert!(bucket_index(bucket_bound(i)) >= i.min(BUCKETS - 1));
+        }
+    }
+}
@@ -11,11 +11,11 @@
 //! # Examples
 //!
 //! ```rust
-//! # #[cfg(feature = "util")]
+//! # #[cfg(all(feature = "util", feature = "tokio-rt"))]
 //! use tower::buffer::Buffer;
-//! # #[cfg(feature = "util")]
+//! # #[cfg(all(feature = "util", feature = "tokio-rt"))]
 //! use tower::{Service, ServiceExt};
-//! # #[cfg(feature = "util")]
+//! # #[cfg(all(feature = "util", feature = "tokio-rt"))]
 //! async fn mass_produce<S: Service<usize>>(svc: S)
 //! where
 //!   S: 'static + Send,
@@ -34,14 +34,62 @@
 //! }
 //! ```
 //!
+//! # Load shedding
+//!
+//! By default, requests wait in the buffer for as long as it takes the inner service to process
+//! the requests ahead of them. Under sustained overload, this means requests may sit in the
+//! queue long after their callers have given up on them. [`Builder::codel`] configures the
+//! buffer to instead shed requests that waited for too long, using the [`Codel`] algorithm.
+//!
+//! # Overflow
+//!
+//! When the queue is full, [`poll_ready`](crate::Service::poll_ready) waits for room in the
+//! queue, propagating backpressure to callers. [`Builder::overflow`] selects another
+//! [`Overflow`] policy, such as failing fast with a [`Full`](error::Full) error.
+//!
+//! # Priorities
+//!
+//! The worker dispatches requests in the order they were queued. [`Builder::priority`] takes
+//! a function that classifies each request with a priority instead, so that urgent requests skip
+//! ahead of the others when the service is saturated, without starving the others forever.
+//!
+//! # Multiple workers
+//!
+//! The worker dispatches requests to the service one at a time. If the service does expensive
+//! work in [`call`](crate::Service::call), [`Builder::workers`] runs several workers, each
+//! with its own clone of the service, that take requests from the same queue.
+//!
+//! # Shutdown
+//!
+//! The worker completes once every [`Buffer`] handle has been dropped. To stop a buffer that is
+//! still shared, [`Buffer::shutdown_handle`] returns a [`Shutdown`] handle that closes the buffer
+//! to new requests, and waits for the requests already queued to be dispatched.
+//!
+//! # Metrics
+//!
+//! [`Buffer::metrics`] returns a [`Metrics`] handle reporting the current queue depth, the
+//! number of requests that were enqueued or dropped, and a [`Histogram`] of how long requests
+//! waited before being dispatched. These can be exported to any metrics library.
+//!
 //! [`Service`]: crate::Service
 
+mod builder;
+mod codel;
 pub mod error;
 pub mod future;
 mod layer;
 mod message;
+mod metrics;
+mod overflow;
+mod queue;
 mod service;
+mod shutdown;
 mod worker;
 
+pub use self::builder::{Builder, SingleWorker, Workers};
+pub use self::codel::Codel;
 pub use self::layer::BufferLayer;
+pub use self::metrics::{Histogram, Metrics};
+pub use self::overflow::Overflow;
 pub use self::service::Buffer;
+pub use self::shutdown::Shutdown;
@@ -0,0 +1,29 @@
+/// What a [`Buffer`] does with a request when its queue is full.
+///
+/// [`Buffer`]: crate::buffer::Buffer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// [`poll_ready`] waits until there is room in the queue, applying
+    /// backpressure to callers.
+    ///
+    /// This is the default.
+    ///
+    /// [`poll_ready`]: crate::Service::poll_ready
+    #[default]
+    Wait,
+    /// The request is rejected immediately with a [`Full`] error.
+    ///
+    /// [`Full`]: crate::buffer::error::Full
+    Reject,
+    /// The oldest queued request is dropped to make room for the request, and
+    /// fails with a [`Full`] error.
+    ///
+    /// If the buffer has [priorities], the oldest request with the lowest
+    /// priority is dropped.
+    ///
+    /// [`Full`]: crate::buffer::error::Full
+    /// [priorities]: crate::buffer::Builder::priority
+    DropOldest,
+    /// The queue grows without bound, and the buffer's `bound` is ignored.
+    Unbounded,
+}
@@ -0,0 +1,529 @@
+use super::{metrics, overflow::Overflow};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, MutexGuard,
+};
+use std::task::{ready, Context, Poll, Wake, Waker};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::{PollSemaphore, PollSender};
+
+/// How many messages in a row may be received before the oldest queued
+/// message, because they have a higher priority.
+///
+/// Once reached, the oldest message is received next, so that a steady
+/// stream of high priority messages can't starve the others.
+const STARVATION_LIMIT: u32 = 16;
+
+/// Creates the queue of a buffer, with one receiving end per worker.
+///
+/// Unless `prioritized` is `true`, all messages must be sent with the same
+/// priority. If `adaptive_lifo` is `true`, the workers may switch the queue
+/// to dispatch the newest messages first with [`Queue::set_lifo`].
+pub(crate) fn queue<T: Send + 'static>(
+    bound: usize,
+    overflow: Overflow,
+    prioritized: bool,
+    adaptive_lifo: bool,
+    workers: usize,
+    metrics: Arc<metrics::Shared>,
+) -> (Sender<T>, Vec<Queue<T>>) {
+    if overflow == Overflow::Wait && !prioritized && !adaptive_lifo {
+        let (tx, rx) = mpsc::channel(bound);
+        return (
+            Sender::Channel(PollSender::new(tx)),
+            Queue::new(Channel { rx, metrics }, workers),
+        );
+    }
+
+    let bound = match overflow {
+        Overflow::Unbounded => None,
+        _ => {
+            assert!(bound > 0, "buffer bound must be greater than zero");
+            Some(bound)
+        }
+    };
+    // Like a bounded channel, wait for a permit before sending a message.
+    let semaphore = match overflow {
+        Overflow::Wait => bound.map(|bound| Arc::new(Semaphore::new(bound))),
+        _ => None,
+    };
+    let deque = Arc::new(Deque {
+        state: Mutex::new(DequeState {
+            items: Items::default(),
+            closed: false,
+            senders: 0,
+            receivers: workers,
+            idle: Vec::new(),
+        }),
+        bound,
+        overflow,
+        semaphore,
+        lifo: AtomicBool::new(false),
+        metrics,
+    });
+    let queues = (0..workers).map(|_| Queue::Deque(deque.clone())).collect();
+    (Sender::Deque(DequeSender::new(deque)), queues)
+}
+
+/// The sending end of a buffer's queue, owned by each `Buffer` handle.
+#[derive(Debug)]
+pub(crate) enum Sender<T> {
+    Channel(PollSender<T>),
+    Deque(DequeSender<T>),
+}
+
+#[derive(Debug)]
+pub(crate) struct DequeSender<T> {
+    deque: Arc<Deque<T>>,
+    semaphore: Option<PollSemaphore>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+/// The result of sending a message that didn't fail.
+pub(crate) enum Sent<T> {
+    Queued,
+    /// The message was queued, and the returned oldest message was dropped
+    /// to make room for it.
+    Evicted(T),
+}
+
+/// Why sending a message failed.
+pub(crate) enum SendError {
+    Closed,
+    Full,
+}
+
+/// The receiving end of a buffer's queue, owned by a worker.
+///
+/// When a buffer has several workers, they share the queue.
+#[derive(Debug)]
+pub(crate) enum Queue<T> {
+    Single(Channel<T>),
+    Shared(Arc<Mutex<Shared<T>>>),
+    Deque(Arc<Deque<T>>),
+}
+
+/// The receiving end of a channel, which records the messages that are
+/// dropped with it.
+#[derive(Debug)]
+pub(crate) struct Channel<T> {
+    rx: mpsc::Receiver<T>,
+    metrics: Arc<metrics::Shared>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Shared<T> {
+    rx: Channel<T>,
+    /// Workers that are waiting for a message.
+    ///
+    /// The channel only wakes the last worker that polled it, so a worker
+    /// that receives a message wakes the next idle worker in turn.
+    idle: Vec<Waker>,
+}
+
+/// A queue that supports priorities, overflow policies and LIFO order,
+/// unlike a channel.
+#[derive(Debug)]
+pub(crate) struct Deque<T> {
+    state: Mutex<DequeState<T>>,
+    bound: Option<usize>,
+    overflow: Overflow,
+    semaphore: Option<Arc<Semaphore>>,
+    /// Whether the newest messages are received first, while the buffer is
+    /// overloaded.
+    lifo: AtomicBool,
+    /// Records the messages that are dropped with the queue.
+    metrics: Arc<metrics::Shared>,
+}
+
+#[derive(Debug)]
+struct DequeState<T> {
+    items: Items<T>,
+    closed: bool,
+    senders: usize,
+    receivers: usize,
+    /// Workers that are waiting for a message.
+    idle: Vec<Waker>,
+}
+
+/// Queued messages, by priority.
+#[derive(Debug)]
+struct Items<T> {
+    /// The messages of each priority, with their sequence number, in the
+    /// order they were queued.
+    levels: BTreeMap<u8, VecDeque<(u64, T)>>,
+    len: usize,
+    next_seq: u64,
+    /// How many messages were dispatched before the oldest message in a row.
+    passed_over: u32,
+}
+
+// ===== impl Sender =====
+
+impl<T: Send + 'static> Sender<T> {
+    pub(crate) fn is_closed(&self) -> bool {
+        match self {
+            Sender::Channel(tx) => tx.is_closed(),
+            Sender::Deque(tx) => tx.deque.lock().closed,
+        }
+    }
+
+    pub(crate) fn poll_reserve(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        match self {
+            Sender::Channel(tx) => tx.poll_reserve(cx).map_err(|_| SendError::Closed),
+            Sender::Deque(tx) => tx.poll_reserve(cx),
+        }
+    }
+
+    /// Sends a message with the given priority, where messages with higher
+    /// priorities are received first.
+    pub(crate) fn send(&mut self, item: T, priority: u8) -> Result<Sent<T>, (T, SendError)> {
+        match self {
+            Sender::Channel(tx) => match tx.send_item(item) {
+                Ok(()) => Ok(Sent::Queued),
+                Err(e) => {
+                    let item = e.into_inner().expect("item must be returned");
+                    Err((item, SendError::Closed))
+                }
+            },
+            Sender::Deque(tx) => {
+                let sent = tx.deque.push(item, priority)?;
+                if let Some(permit) = tx.permit.take() {
+                    // The permit is returned when the message is received.
+                    permit.forget();
+                }
+                Ok(sent)
+            }
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Sender::Channel(tx) => Sender::Channel(tx.clone()),
+            Sender::Deque(tx) => Sender::Deque(DequeSender::new(tx.deque.clone())),
+        }
+    }
+}
+
+// ===== impl DequeSender =====
+
+impl<T> DequeSender<T> {
+    fn new(deque: Arc<Deque<T>>) -> Self {
+        deque.lock().senders += 1;
+        DequeSender {
+            semaphore: deque.semaphore.clone().map(PollSemaphore::new),
+            deque,
+            permit: None,
+        }
+    }
+
+    fn poll_reserve(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        let semaphore = match self.semaphore {
+            Some(ref mut semaphore) if self.permit.is_none() => semaphore,
+            // Either a permit was already acquired, or there is always room
+            // in the queue, or the message is rejected when it is sent.
+            _ => return Poll::Ready(Ok(())),
+        };
+        match ready!(semaphore.poll_acquire(cx)) {
+            Some(permit) => {
+                self.permit = Some(permit);
+                Poll::Ready(Ok(()))
+            }
+            // The semaphore is closed with the queue.
+            None => Poll::Ready(Err(SendError::Closed)),
+        }
+    }
+}
+
+impl<T> Drop for DequeSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.deque.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            // Let the workers observe that the queue has ended.
+            state.wake_idle();
+        }
+    }
+}
+
+// ===== impl Queue =====
+
+impl<T> Queue<T> {
+    /// Returns `workers` queues receiving from `rx`.
+    fn new(rx: Channel<T>, workers: usize) -> Vec<Self> {
+        if workers == 1 {
+            return vec![Queue::Single(rx)];
+        }
+        let shared = Arc::new(Mutex::new(Shared {
+            rx,
+            idle: Vec::new(),
+        }));
+        (0..workers)
+            .map(|_| Queue::Shared(shared.clone()))
+            .collect()
+    }
+
+    pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let shared = match self {
+            Queue::Single(channel) => return channel.rx.poll_recv(cx),
+            Queue::Deque(deque) => return deque.poll_pop(cx),
+            Queue::Shared(shared) => shared,
+        };
+
+        let mut shared = shared.lock().expect("buffer queue poisoned");
+        match shared.rx.rx.poll_recv(cx) {
+            Poll::Ready(Some(msg)) => {
+                shared.idle.retain(|w| !w.will_wake(cx.waker()));
+                if let Some(next) = shared.idle.pop() {
+                    next.wake();
+                }
+                Poll::Ready(Some(msg))
+            }
+            Poll::Ready(None) => {
+                wake_all(&mut shared.idle);
+                Poll::Ready(None)
+            }
+            Poll::Pending => {
+                register(&mut shared.idle, cx);
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Sets whether the newest messages are received first.
+    ///
+    /// This only has an effect on queues created with `adaptive_lifo`.
+    pub(crate) fn set_lifo(&self, lifo: bool) {
+        if let Queue::Deque(deque) = self {
+            deque.lifo.store(lifo, Ordering::Relaxed);
+        }
+    }
+
+    /// Closes the queue, without dropping the queued messages.
+    pub(crate) fn close(&mut self) {
+        match self {
+            Queue::Single(channel) => channel.rx.close(),
+            Queue::Shared(shared) => {
+                let mut shared = shared.lock().expect("buffer queue poisoned");
+                shared.rx.rx.close();
+                // Idle workers must drain the remaining messages.
+                wake_all(&mut shared.idle);
+            }
+            Queue::Deque(deque) => deque.lock().close(deque),
+        }
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        if let Queue::Deque(deque) = self {
+            let items = {
+                let mut state = deque.lock();
+                state.receivers -= 1;
+                if state.receivers > 0 {
+                    return;
+                }
+                // No worker is left to dispatch the queued messages.
+                state.close(deque);
+                std::mem::take(&mut state.items)
+            };
+            deque.metrics.dropped_many(items.len as u64);
+            drop(items);
+        }
+    }
+}
+
+// ===== impl Channel =====
+
+impl<T> Drop for Channel<T> {
+    fn drop(&mut self) {
+        // No worker is left to dispatch the queued messages. Closing the
+        // channel first ensures that they are all received below, unless a
+        // sender is in the middle of sending one.
+        self.rx.close();
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut dropped = 0;
+        while let Poll::Ready(Some(msg)) = self.rx.poll_recv(&mut cx) {
+            dropped += 1;
+            drop(msg);
+        }
+        self.metrics.dropped_many(dropped);
+    }
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+// ===== impl Deque =====
+
+impl<T> Deque<T> {
+    fn lock(&self) -> MutexGuard<'_, DequeState<T>> {
+        self.state.lock().expect("buffer queue poisoned")
+    }
+
+    fn push(&self, item: T, priority: u8) -> Result<Sent<T>, (T, SendError)> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err((item, SendError::Closed));
+        }
+
+        let mut sent = Sent::Queued;
+        // With `Overflow::Wait`, the semaphore already ensures that there is
+        // room in the queue.
+        if self.semaphore.is_none() && self.bound.map_or(false, |bound| state.items.len >= bound) {
+            match self.overflow {
+                Overflow::DropOldest => {
+                    let oldest = state.items.pop_lowest().expect("queue must not be empty");
+                    sent = Sent::Evicted(oldest);
+                }
+                _ => return Err((item, SendError::Full)),
+            }
+        }
+
+        state.items.push(item, priority);
+        if let Some(worker) = state.idle.pop() {
+            worker.wake();
+        }
+        Ok(sent)
+    }
+
+    fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.lock();
+        let item = if self.lifo.load(Ordering::Relaxed) {
+            state.items.pop_newest()
+        } else {
+            state.items.pop()
+        };
+        if let Some(item) = item {
+            if let Some(ref semaphore) = self.semaphore {
+                semaphore.add_permits(1);
+            }
+            state.idle.retain(|w| !w.will_wake(cx.waker()));
+            if state.items.len > 0 {
+                if let Some(next) = state.idle.pop() {
+                    next.wake();
+                }
+            }
+            return Poll::Ready(Some(item));
+        }
+
+        if state.closed || state.senders == 0 {
+            state.wake_idle();
+            return Poll::Ready(None);
+        }
+
+        register(&mut state.idle, cx);
+        Poll::Pending
+    }
+}
+
+impl<T> DequeState<T> {
+    fn wake_idle(&mut self) {
+        wake_all(&mut self.idle);
+    }
+
+    fn close(&mut self, deque: &Deque<T>) {
+        self.closed = true;
+        if let Some(ref semaphore) = deque.semaphore {
+            // Wake senders waiting for room in the queue.
+            semaphore.close();
+        }
+        self.wake_idle();
+    }
+}
+
+// ===== impl Items =====
+
+impl<T> Default for Items<T> {
+    fn default() -> Self {
+        Items {
+            levels: BTreeMap::new(),
+            len: 0,
+            next_seq: 0,
+            passed_over: 0,
+        }
+    }
+}
+
+impl<T> Items<T> {
+    fn push(&mut self, item: T, priority: u8) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.len += 1;
+        self.levels
+            .entry(priority)
+            .or_default()
+            .push_back((seq, item));
+    }
+
+    /// Removes the message with the highest priority, or the oldest message
+    /// if it has been passed over too many times.
+    fn pop(&mut self) -> Option<T> {
+        let highest = *self.levels.keys().next_back()?;
+        let oldest = self.oldest_level().expect("a level must not be empty");
+        let level = if highest == oldest || self.passed_over >= STARVATION_LIMIT {
+            self.passed_over = 0;
+            oldest
+        } else {
+            self.passed_over += 1;
+            highest
+        };
+        self.pop_level(level)
+    }
+
+    /// Removes the newest message with the highest priority.
+    fn pop_newest(&mut self) -> Option<T> {
+        let highest = *self.levels.keys().next_back()?;
+        let items = self.levels.get_mut(&highest)?;
+        let (_, item) = items.pop_back()?;
+        if items.is_empty() {
+            self.levels.remove(&highest);
+        }
+        self.len -= 1;
+        Some(item)
+    }
+
+    /// Removes the oldest message with the lowest priority.
+    fn pop_lowest(&mut self) -> Option<T> {
+        let lowest = *self.levels.keys().next()?;
+        self.pop_level(lowest)
+    }
+
+    /// Returns the priority of the oldest queued message.
+    fn oldest_level(&self) -> Option<u8> {
+        self.levels
+            .iter()
+            .filter_map(|(&priority, items)| Some((items.front()?.0, priority)))
+            .min()
+            .map(|(_, priority)| priority)
+    }
+
+    fn pop_level(&mut self, priority: u8) -> Option<T> {
+        let items = self.levels.get_mut(&priority)?;
+        let (_, item) = items.pop_front()?;
+        if items.is_empty() {
+            self.levels.remove(&priority);
+        }
+        self.len -= 1;
+        Some(item)
+    }
+}
+
+fn register(idle: &mut Vec<Waker>, cx: &mut Context<'_>) {
+    if !idle.iter().any(|w| w.will_wake(cx.waker())) {
+        idle.push(cx.waker().clone());
+    }
+}
+
+fn wake_all(idle: &mut Vec<Waker>) {
+    for waker in idle.drain(..) {
+        waker.wake();
+    }
+}
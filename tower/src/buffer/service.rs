@@ -1,26 +1,39 @@
 use super::{
+    codel::Codel,
+    error::Full,
     future::ResponseFuture,
     message::Message,
+    metrics::Metrics,
+    overflow::Overflow,
+    queue::{self, SendError, Sender, Sent},
+    shutdown::Shutdown,
     worker::{Handle, Worker},
 };
 
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
 use std::{
+    fmt,
     future::Future,
+    sync::Arc,
     task::{Context, Poll},
 };
-use tokio::sync::{mpsc, oneshot};
-use tokio_util::sync::PollSender;
+use tokio::sync::oneshot;
 use tower_service::Service;
 
 /// Adds an mpsc buffer in front of an inner service.
 ///
 /// See the module documentation for more details.
-#[derive(Debug)]
 pub struct Buffer<Req, F> {
-    tx: PollSender<Message<Req, F>>,
+    tx: Sender<Message<Req, F>>,
     handle: Handle,
+    classify: Option<Classify<Req>>,
 }
 
+/// Returns the priority of a request.
+pub(super) type Classify<Req> = Arc<dyn Fn(&Req) -> u8 + Send + Sync>;
+
 impl<Req, F> Buffer<Req, F>
 where
     F: 'static,
@@ -46,15 +59,34 @@ where
     /// [`Poll::Ready`]: std::task::Poll::Ready
     /// [`call`]: crate::Service::call
     /// [`poll_ready`]: crate::Service::poll_ready
+    #[cfg(feature = "tokio-rt")]
     pub fn new<S>(service: S, bound: usize) -> Self
     where
         S: Service<Req, Future = F> + Send + 'static,
         F: Send,
         S::Error: Into<crate::BoxError> + Send + Sync,
         Req: Send + 'static,
+    {
+        Self::with_executor(service, bound, &TokioExecutor::new())
+    }
+
+    /// Creates a new [`Buffer`] wrapping `service`, spawning the background worker with
+    /// `executor`.
+    ///
+    /// This behaves like [`Buffer::new`], except that the worker does not have to run on the
+    /// Tokio runtime. To set other options, such as several workers, use a [`Builder`].
+    ///
+    /// [`Builder`]: crate::buffer::Builder
+    pub fn with_executor<S, E>(service: S, bound: usize, executor: &E) -> Self
+    where
+        S: Service<Req, Future = F> + Send + 'static,
+        F: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+        Req: Send + 'static,
+        E: Executor + ?Sized,
     {
         let (service, worker) = Self::pair(service, bound);
-        tokio::spawn(worker);
+        executor.spawn(Box::pin(worker));
         service
     }
 
@@ -70,13 +102,64 @@ where
         S::Error: Into<crate::BoxError> + Send + Sync,
         Req: Send + 'static,
     {
-        let (tx, rx) = mpsc::channel(bound);
-        let (handle, worker) = Worker::new(service, rx);
+        let (buffer, mut workers) =
+            Self::pair_inner(vec![service], bound, Overflow::Wait, None, None);
+        (buffer, workers.pop().expect("one worker must be created"))
+    }
+
+    pub(super) fn pair_inner<S>(
+        services: Vec<S>,
+        bound: usize,
+        overflow: Overflow,
+        codel: Option<Codel>,
+        classify: Option<Classify<Req>>,
+    ) -> (Self, Vec<Worker<S, Req>>)
+    where
+        S: Service<Req, Future = F> + Send + 'static,
+        F: Send,
+        S::Error: Into<crate::BoxError> + Send + Sync,
+        Req: Send + 'static,
+    {
+        assert!(!services.is_empty(), "a buffer needs at least one worker");
+        let capacity = match overflow {
+            Overflow::Unbounded => usize::MAX,
+            _ => bound,
+        };
+        let handle = Handle::new(capacity, codel.is_some());
+        let (tx, queues) = queue::queue(
+            bound,
+            overflow,
+            classify.is_some(),
+            codel.is_some(),
+            services.len(),
+            handle.shared_metrics(),
+        );
+        let workers = services
+            .into_iter()
+            .zip(queues)
+            .map(|(service, rx)| Worker::new(service, rx, &handle, codel))
+            .collect();
         let buffer = Self {
-            tx: PollSender::new(tx),
+            tx,
             handle,
+            classify,
         };
-        (buffer, worker)
+        (buffer, workers)
+    }
+
+    /// Returns a handle to gracefully shut down the worker of this [`Buffer`].
+    ///
+    /// See [`Shutdown`] for details.
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.handle.shutdown_handle()
+    }
+
+    /// Returns a handle to the metrics of this [`Buffer`], such as the number of queued requests
+    /// and how long they waited in the queue.
+    ///
+    /// The metrics are shared by all clones of this [`Buffer`]. See [`Metrics`] for details.
+    pub fn metrics(&self) -> Metrics {
+        self.handle.metrics()
     }
 
     fn get_worker_error(&self) -> crate::BoxError {
@@ -95,8 +178,10 @@ where
     type Future = ResponseFuture<F>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        // First, check if the worker is still alive.
-        if self.tx.is_closed() {
+        // First, check if the worker is still alive, and still accepts
+        // requests. The worker only closes the channel once it's polled after
+        // a shutdown, so the shutdown is checked here as well.
+        if self.tx.is_closed() || self.handle.is_shutdown() {
             // If the inner service has errored, then we error here.
             return Poll::Ready(Err(self.get_worker_error()));
         }
@@ -115,14 +200,45 @@ where
         // towards that span since the worker would have no way of entering it.
         let span = tracing::Span::current();
 
+        // The buffer may have been shut down since `poll_ready`.
+        if self.handle.is_shutdown() {
+            tracing::trace!("rejecting request to shut down buffer");
+            return ResponseFuture::failed(self.get_worker_error());
+        }
+
         // If we've made it here, then a channel permit has already been
         // acquired, so we can freely allocate a oneshot.
         let (tx, rx) = oneshot::channel();
 
-        match self.tx.send_item(Message { request, span, tx }) {
-            Ok(_) => ResponseFuture::new(rx),
+        let priority = self
+            .classify
+            .as_ref()
+            .map_or(0, |classify| classify(&request));
+        let msg = Message {
+            request,
+            span,
+            tx,
+            enqueued_at: self.handle.enqueued_at(),
+        };
+
+        match self.tx.send(msg, priority) {
+            Ok(sent) => {
+                self.handle.record_enqueued();
+                if let Sent::Evicted(oldest) = sent {
+                    tracing::trace!("dropping oldest request from full buffer");
+                    self.handle.record_dropped();
+                    let _ = oldest.tx.send(Err(Full::new().into()));
+                }
+                ResponseFuture::new(rx)
+            }
+            Err((_, SendError::Full)) => {
+                tracing::trace!("rejecting request to full buffer");
+                self.handle.record_enqueued();
+                self.handle.record_dropped();
+                ResponseFuture::failed(Full::new().into())
+            }
             // If the channel is closed, propagate the error from the worker.
-            Err(_) => {
+            Err((_, SendError::Closed)) => {
                 tracing::trace!("buffer channel closed");
                 ResponseFuture::failed(self.get_worker_error())
             }
@@ -139,6 +255,31 @@ where
         Self {
             handle: self.handle.clone(),
             tx: self.tx.clone(),
+            classify: self.classify.clone(),
         }
     }
 }
+
+impl<Req, F> fmt::Debug for Buffer<Req, F>
+where
+    Req: fmt::Debug,
+    F: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Buffer")
+            .field("tx", &self.tx)
+            .field("handle", &self.handle)
+            .field("prioritized", &self.classify.is_some())
+            .finish()
+    }
+}
+
+/// Returns `n` clones of `service`.
+pub(super) fn clones<S: Clone>(service: S, n: usize) -> Vec<S> {
+    let mut services = Vec::with_capacity(n);
+    if n > 0 {
+        services.extend(std::iter::repeat(service.clone()).take(n - 1));
+        services.push(service);
+    }
+    services
+}
@@ -0,0 +1,192 @@
+use super::future::Drained;
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::task::{Context, Poll, Waker};
+
+/// A handle to gracefully shut down the worker of a [`Buffer`].
+///
+/// Shutting down the worker closes the buffer: [`Buffer`] handles fail with a
+/// [`Closed`] error from then on, while the requests that were already queued
+/// are still dispatched to the inner service. Once every queued request has
+/// been dispatched, the worker completes, and the inner service is dropped.
+///
+/// This is returned by [`Buffer::shutdown_handle`].
+///
+/// [`Buffer`]: crate::buffer::Buffer
+/// [`Buffer::shutdown_handle`]: crate::buffer::Buffer::shutdown_handle
+/// [`Closed`]: crate::buffer::error::Closed
+#[derive(Clone)]
+pub struct Shutdown {
+    shared: Arc<Shared>,
+}
+
+/// Shutdown state shared between the worker and the [`Shutdown`] handles.
+#[derive(Debug)]
+pub(crate) struct Shared {
+    state: Mutex<State>,
+    /// Whether a shutdown has been initiated.
+    ///
+    /// This is checked by every [`Buffer`] handle, so it isn't behind the
+    /// lock.
+    ///
+    /// [`Buffer`]: crate::buffer::Buffer
+    shutdown: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    drained: bool,
+    /// The number of workers that haven't completed.
+    workers: usize,
+    /// The waker of each worker, by the index of its `DrainGuard`.
+    worker_wakers: Vec<Option<Waker>>,
+    drain_waiters: Vec<Waker>,
+}
+
+/// Held by each worker, and marks the buffer as drained once every worker has
+/// dropped its guard.
+#[derive(Debug)]
+pub(crate) struct DrainGuard {
+    shared: Arc<Shared>,
+    index: usize,
+}
+
+// ===== impl Shutdown =====
+
+impl Shutdown {
+    pub(crate) fn new(shared: Arc<Shared>) -> Self {
+        Shutdown { shared }
+    }
+
+    /// Stops the buffer from accepting new requests, and returns a future that
+    /// completes once the worker has dispatched all queued requests.
+    pub fn shutdown(&self) -> Drained {
+        self.shared.shutdown.store(true, Ordering::Release);
+        self.shared.wake_workers();
+        self.drained()
+    }
+
+    /// Returns a future that completes once the worker has completed, without
+    /// initiating a shutdown.
+    ///
+    /// The worker also completes when every [`Buffer`] handle is dropped, or
+    /// when the inner service fails. If the buffer has several workers, the
+    /// future completes once all of them have completed.
+    ///
+    /// [`Buffer`]: crate::buffer::Buffer
+    pub fn drained(&self) -> Drained {
+        Drained::new(self.shared.clone())
+    }
+
+    /// Returns `true` if a shutdown has been initiated.
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.is_shutdown()
+    }
+}
+
+impl fmt::Debug for Shutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.shared.lock();
+        f.debug_struct("Shutdown")
+            .field("shutdown", &self.shared.is_shutdown())
+            .field("drained", &state.drained)
+            .finish()
+    }
+}
+
+// ===== impl Shared =====
+
+impl Shared {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            shutdown: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns `true` if a shutdown has been initiated.
+    pub(crate) fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("buffer shutdown state poisoned")
+    }
+
+    /// Wakes every worker that is waiting, so that it notices a change in
+    /// the state of the buffer.
+    pub(crate) fn wake_workers(&self) {
+        let wakers: Vec<Waker> = self
+            .lock()
+            .worker_wakers
+            .iter_mut()
+            .filter_map(Option::take)
+            .collect();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn poll_drained(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.lock();
+        if state.drained {
+            return Poll::Ready(());
+        }
+        if !state.drain_waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            state.drain_waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+// ===== impl DrainGuard =====
+
+impl DrainGuard {
+    pub(crate) fn new(shared: Arc<Shared>) -> Self {
+        let index = {
+            let mut state = shared.lock();
+            state.workers += 1;
+            state.worker_wakers.push(None);
+            state.worker_wakers.len() - 1
+        };
+        DrainGuard { shared, index }
+    }
+
+    /// Returns `true` if a shutdown has been initiated.
+    ///
+    /// The worker is woken by [`Shared::wake_workers`], when a shutdown is
+    /// initiated or when another worker fails.
+    pub(crate) fn poll_shutdown(&self, cx: &mut Context<'_>) -> bool {
+        let mut state = self.shared.lock();
+        match state.worker_wakers[self.index] {
+            Some(ref waker) if waker.will_wake(cx.waker()) => {}
+            ref mut slot => *slot = Some(cx.waker().clone()),
+        }
+        // The flag is read after registering the waker, so that a concurrent
+        // shutdown either is observed here, or wakes the worker.
+        self.shared.is_shutdown()
+    }
+}
+
+impl Drop for DrainGuard {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = self.shared.lock();
+            state.workers -= 1;
+            if let Some(slot) = state.worker_wakers.get_mut(self.index) {
+                *slot = None;
+            }
+            if state.workers > 0 {
+                return;
+            }
+            state.drained = true;
+            std::mem::take(&mut state.drain_waiters)
+        };
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+}
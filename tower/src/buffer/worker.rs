@@ -1,14 +1,21 @@
 use super::{
-    error::{Closed, ServiceError},
+    codel::{Codel, CodelState},
+    error::{Closed, ServiceError, Shed},
     message::Message,
+    metrics::{self, Metrics},
+    queue::Queue,
+    shutdown::{DrainGuard, Shared, Shutdown},
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
 };
-use std::sync::{Arc, Mutex};
 use std::{
     future::Future,
     pin::Pin,
     task::{ready, Context, Poll},
 };
-use tokio::sync::mpsc;
+use tokio::time::Instant;
 use tower_service::Service;
 
 pin_project_lite::pin_project! {
@@ -25,11 +32,29 @@ pin_project_lite::pin_project! {
         T: Service<Request>,
     {
         current_message: Option<Message<Request, T::Future>>,
-        rx: mpsc::Receiver<Message<Request, T::Future>>,
+        rx: Queue<Message<Request, T::Future>>,
         service: T,
         finish: bool,
         failed: Option<ServiceError>,
         handle: Handle,
+        codel: Option<CodelState>,
+        // Whether a graceful shutdown has closed the channel.
+        shutdown: bool,
+        drain: Option<DrainGuard>,
+    }
+
+    impl<T, Request> PinnedDrop for Worker<T, Request>
+    where
+        T: Service<Request>,
+    {
+        fn drop(this: Pin<&mut Self>) {
+            // A message waiting for the service to become ready is never
+            // dispatched.
+            let this = this.project();
+            if this.current_message.take().is_some() {
+                this.handle.metrics.dropped();
+            }
+        }
     }
 }
 
@@ -37,6 +62,14 @@ pin_project_lite::pin_project! {
 #[derive(Debug)]
 pub(crate) struct Handle {
     inner: Arc<Mutex<Option<ServiceError>>>,
+    // Set once `inner` holds an error, so that workers can check for the
+    // failure of another worker without locking.
+    failed: Arc<AtomicBool>,
+    shutdown: Arc<Shared>,
+    metrics: Arc<metrics::Shared>,
+    // Whether the workers shed requests with CoDel, and so need to know when
+    // each request was sent.
+    codel: bool,
 }
 
 impl<T, Request> Worker<T, Request>
@@ -46,22 +79,21 @@ where
 {
     pub(crate) fn new(
         service: T,
-        rx: mpsc::Receiver<Message<Request, T::Future>>,
-    ) -> (Handle, Worker<T, Request>) {
-        let handle = Handle {
-            inner: Arc::new(Mutex::new(None)),
-        };
-
-        let worker = Worker {
+        rx: Queue<Message<Request, T::Future>>,
+        handle: &Handle,
+        codel: Option<Codel>,
+    ) -> Worker<T, Request> {
+        Worker {
             current_message: None,
             finish: false,
             failed: None,
             rx,
             service,
             handle: handle.clone(),
-        };
-
-        (handle, worker)
+            codel: codel.map(CodelState::new),
+            shutdown: false,
+            drain: Some(DrainGuard::new(handle.shutdown.clone())),
+        }
     }
 
     /// Return the next queued Message that hasn't been canceled.
@@ -82,27 +114,87 @@ where
             // If the oneshot sender is closed, then the receiver is dropped,
             // and nobody cares about the response. If this is the case, we
             // should continue to the next request.
-            if !msg.tx.is_closed() {
+            if msg.tx.is_closed() {
+                tracing::trace!("dropping cancelled buffered request");
+                self.handle.metrics.dropped();
+            } else if self.should_shed(&msg) {
+                // The request kept waiting while the service wasn't ready, so
+                // check its sojourn time again before dispatching it.
+                self.shed(msg);
+            } else {
                 tracing::trace!("resuming buffered request");
                 return Poll::Ready(Some((msg, false)));
             }
-
-            tracing::trace!("dropping cancelled buffered request");
         }
 
         // Get the next request
-        while let Some(msg) = ready!(Pin::new(&mut self.rx).poll_recv(cx)) {
-            if !msg.tx.is_closed() {
-                tracing::trace!("processing new request");
-                return Poll::Ready(Some((msg, true)));
+        while let Some(msg) = ready!(self.rx.poll_recv(cx)) {
+            if msg.tx.is_closed() {
+                // The request is canceled, so pop the next one.
+                tracing::trace!("dropping cancelled request");
+                self.handle.metrics.dropped();
+                continue;
+            }
+
+            if self.should_shed(&msg) {
+                self.shed(msg);
+                continue;
             }
-            // Otherwise, request is canceled, so pop the next one.
-            tracing::trace!("dropping cancelled request");
+
+            tracing::trace!("processing new request");
+            return Poll::Ready(Some((msg, true)));
         }
 
         Poll::Ready(None)
     }
 
+    /// Returns `true` if the given message has waited in the queue for too
+    /// long, according to the worker's CoDel configuration.
+    fn should_shed(&mut self, msg: &Message<Request, T::Future>) -> bool {
+        match self.codel {
+            // Don't shed requests that will be failed anyway.
+            Some(ref mut codel) if self.failed.is_none() => {
+                let shed = msg
+                    .enqueued_at
+                    .map_or(false, |enqueued_at| codel.should_shed(enqueued_at));
+                // While overloaded, dispatch the newest requests first, since
+                // their callers are the most likely to still be waiting.
+                self.rx.set_lifo(codel.is_overloaded());
+                shed
+            }
+            _ => false,
+        }
+    }
+
+    /// Fails the given message with a [`Shed`] error.
+    fn shed(&self, msg: Message<Request, T::Future>) {
+        let _guard = msg.span.enter();
+        tracing::debug!("shedding request that waited too long in the buffer");
+        self.handle.metrics.dropped();
+        let _ = msg.tx.send(Err(Shed::new().into()));
+    }
+
+    /// If the service of another worker has failed, adopts its error, so
+    /// that this worker fails the remaining requests instead of dispatching
+    /// them.
+    fn adopt_failure(&mut self) {
+        if self.failed.is_some() || !self.handle.failed.load(Ordering::Acquire) {
+            return;
+        }
+        let error = self
+            .handle
+            .inner
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(ServiceError::clone);
+        if let Some(error) = error {
+            tracing::debug!("another buffer worker failed");
+            self.rx.close();
+            self.failed = Some(error);
+        }
+    }
+
     fn failed(&mut self, error: crate::BoxError) {
         // The underlying service failed when we called `poll_ready` on it with the given `error`. We
         // need to communicate this to all the `Buffer` handles. To do so, we wrap up the error in
@@ -120,15 +212,24 @@ where
 
         let mut inner = self.handle.inner.lock().unwrap();
 
-        if inner.is_some() {
-            // Future::poll was called after we've already errored out!
+        if let Some(ref existing) = *inner {
+            // Either `Future::poll` was called after we've already errored out, or another
+            // worker's service has failed first. Either way, the first error is reported.
+            let existing = existing.clone();
+            drop(inner);
+            self.rx.close();
+            self.failed = Some(existing);
             return;
         }
 
         *inner = Some(error.clone());
+        self.handle.failed.store(true, Ordering::Release);
         drop(inner);
 
         self.rx.close();
+        // Other workers may be waiting for their own service to become ready.
+        // Wake them up so that they fail their requests with this error too.
+        self.handle.shutdown.wake_workers();
 
         // By closing the mpsc::Receiver, we know that poll_next_msg will soon return Ready(None),
         // which will trigger the `self.finish == true` phase. We just need to make sure that any
@@ -149,13 +250,27 @@ where
             return Poll::Ready(());
         }
 
+        let shutdown = match self.drain {
+            Some(ref drain) => drain.poll_shutdown(cx) && !self.shutdown,
+            None => false,
+        };
+        if shutdown {
+            // Stop accepting new requests, but keep dispatching the queued
+            // ones until the channel is empty.
+            tracing::debug!("shutting down buffer worker");
+            self.shutdown = true;
+            self.rx.close();
+        }
+
         loop {
             match ready!(self.poll_next_msg(cx)) {
                 Some((msg, first)) => {
+                    self.adopt_failure();
                     let _guard = msg.span.enter();
                     if let Some(ref failed) = self.failed {
                         tracing::trace!("notifying caller about worker failure");
-                        let _ = msg.tx.send(Err(failed.clone()));
+                        self.handle.metrics.dropped();
+                        let _ = msg.tx.send(Err(failed.clone().into()));
                         continue;
                     }
 
@@ -167,6 +282,9 @@ where
                     match self.service.poll_ready(cx) {
                         Poll::Ready(Ok(())) => {
                             tracing::debug!(service.ready = true, message = "processing request");
+                            self.handle
+                                .metrics
+                                .dispatched(msg.enqueued_at.map(|at| at.elapsed()));
                             let response = self.service.call(msg.request);
 
                             // Send the response future back to the sender.
@@ -188,17 +306,22 @@ where
                             tracing::debug!({ %error }, "service failed");
                             drop(_guard);
                             self.failed(error);
+                            self.handle.metrics.dropped();
                             let _ = msg.tx.send(Err(self
                                 .failed
                                 .as_ref()
                                 .expect("Worker::failed did not set self.failed?")
-                                .clone()));
+                                .clone()
+                                .into()));
                         }
                     }
                 }
                 None => {
                     // No more more requests _ever_.
                     self.finish = true;
+                    // Resolve `Drained` futures now, even if the worker itself
+                    // is dropped later.
+                    self.drain = None;
                     return Poll::Ready(());
                 }
             }
@@ -207,6 +330,16 @@ where
 }
 
 impl Handle {
+    pub(crate) fn new(capacity: usize, codel: bool) -> Handle {
+        Handle {
+            inner: Arc::new(Mutex::new(None)),
+            failed: Arc::new(AtomicBool::new(false)),
+            shutdown: Shared::new(),
+            metrics: metrics::Shared::new(capacity),
+            codel,
+        }
+    }
+
     pub(crate) fn get_error_on_closed(&self) -> crate::BoxError {
         self.inner
             .lock()
@@ -215,12 +348,51 @@ impl Handle {
             .map(|svc_err| svc_err.clone().into())
             .unwrap_or_else(|| Closed::new().into())
     }
+
+    /// Returns `true` if a shutdown has been initiated, so that no new
+    /// requests are accepted.
+    pub(crate) fn is_shutdown(&self) -> bool {
+        self.shutdown.is_shutdown()
+    }
+
+    pub(crate) fn shutdown_handle(&self) -> Shutdown {
+        Shutdown::new(self.shutdown.clone())
+    }
+
+    pub(crate) fn metrics(&self) -> Metrics {
+        Metrics::new(self.metrics.clone())
+    }
+
+    /// Returns the time at which a message is sent, if the workers need it.
+    pub(crate) fn enqueued_at(&self) -> Option<Instant> {
+        if self.codel || self.metrics.tracks_wait() {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn shared_metrics(&self) -> Arc<metrics::Shared> {
+        self.metrics.clone()
+    }
+
+    pub(crate) fn record_enqueued(&self) {
+        self.metrics.enqueued();
+    }
+
+    pub(crate) fn record_dropped(&self) {
+        self.metrics.dropped();
+    }
 }
 
 impl Clone for Handle {
     fn clone(&self) -> Handle {
         Handle {
             inner: self.inner.clone(),
+            failed: self.failed.clone(),
+            shutdown: self.shutdown.clone(),
+            metrics: self.metrics.clone(),
+            codel: self.codel,
         }
     }
 }
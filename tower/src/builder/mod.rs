@@ -32,7 +32,7 @@ use std::fmt;
 /// # // to say that it should only be run with cfg(feature = "...")
 /// # use tower::Service;
 /// # use tower::builder::ServiceBuilder;
-/// # #[cfg(all(feature = "buffer", feature = "limit"))]
+/// # #[cfg(all(feature = "buffer", feature = "limit", feature = "tokio-rt"))]
 /// # async fn wrap<S>(svc: S) where S: Service<(), Error = &'static str> + 'static + Send, S::Future: Send {
 /// ServiceBuilder::new()
 ///     .buffer(100)
@@ -51,7 +51,7 @@ use std::fmt;
 /// ```
 /// # use tower::Service;
 /// # use tower::builder::ServiceBuilder;
-/// # #[cfg(all(feature = "buffer", feature = "limit"))]
+/// # #[cfg(all(feature = "buffer", feature = "limit", feature = "tokio-rt"))]
 /// # async fn wrap<S>(svc: S) where S: Service<(), Error = &'static str> + 'static + Send, S::Future: Send {
 /// ServiceBuilder::new()
 ///     .concurrency_limit(10)
@@ -90,7 +90,7 @@ use std::fmt;
 /// # use tower::Service;
 /// # use tower::builder::ServiceBuilder;
 /// # use std::time::Duration;
-/// # #[cfg(all(feature = "buffer", feature = "limit"))]
+/// # #[cfg(all(feature = "buffer", feature = "limit", feature = "tokio-rt"))]
 /// # async fn wrap<S>(svc: S) where S: Service<(), Error = &'static str> + 'static + Send, S::Future: Send {
 /// ServiceBuilder::new()
 ///     .buffer(5)
@@ -168,13 +168,28 @@ impl<L> ServiceBuilder<L> {
         self.layer(crate::layer::layer_fn(f))
     }
 
+    /// Collect requests into batches for the next layer.
+    ///
+    /// This wraps the inner service with an instance of the [`Batch`]
+    /// middleware.
+    ///
+    /// [`Batch`]: crate::batch
+    #[cfg(all(feature = "batch", feature = "tokio-rt"))]
+    pub fn batch<Request>(
+        self,
+        max_size: usize,
+        max_latency: std::time::Duration,
+    ) -> ServiceBuilder<Stack<crate::batch::BatchLayer<Request>, L>> {
+        self.layer(crate::batch::BatchLayer::new(max_size, max_latency))
+    }
+
     /// Buffer requests when the next layer is not ready.
     ///
     /// This wraps the inner service with an instance of the [`Buffer`]
     /// middleware.
     ///
     /// [`Buffer`]: crate::buffer
-    #[cfg(feature = "buffer")]
+    #[cfg(all(feature = "buffer", feature = "tokio-rt"))]
     pub fn buffer<Request>(
         self,
         bound: usize,
@@ -182,6 +197,22 @@ impl<L> ServiceBuilder<L> {
         self.layer(crate::buffer::BufferLayer::new(bound))
     }
 
+    /// Serve responses from a cache instead of calling the next layer.
+    ///
+    /// This wraps the inner service with an instance of the [`Cache`]
+    /// middleware.
+    ///
+    /// [`Cache`]: crate::cache
+    #[cfg(all(feature = "cache", feature = "tokio-rt"))]
+    pub fn cache<K, P, St>(
+        self,
+        key: K,
+        policy: P,
+        store: St,
+    ) -> ServiceBuilder<Stack<crate::cache::CacheLayer<K, P, St>, L>> {
+        self.layer(crate::cache::CacheLayer::new(key, policy, store))
+    }
+
     /// Limit the max number of in-flight requests.
     ///
     /// A request is in-flight from the time the request is received until the
@@ -460,6 +491,51 @@ impl<L> ServiceBuilder<L> {
         self.layer(crate::util::AndThenLayer::new(f))
     }
 
+    /// Catches panics in the service's [`call`] and response future, and
+    /// returns them as errors.
+    ///
+    /// This wraps the inner service with an instance of the [`CatchPanic`]
+    /// middleware.
+    ///
+    /// [`call`]: crate::Service::call
+    /// [`CatchPanic`]: crate::util::CatchPanic
+    #[cfg(feature = "util")]
+    pub fn catch_panic(self) -> ServiceBuilder<Stack<crate::util::CatchPanicLayer, L>> {
+        self.layer(crate::util::CatchPanicLayer::new())
+    }
+
+    /// Coalesces concurrent requests with the same key into a single call to
+    /// the service.
+    ///
+    /// This wraps the inner service with an instance of the [`Coalesce`]
+    /// middleware.
+    ///
+    /// [`Coalesce`]: crate::util::Coalesce
+    #[cfg(feature = "util")]
+    pub fn coalesce<K, Request>(
+        self,
+        key: K,
+    ) -> ServiceBuilder<Stack<crate::util::CoalesceLayer<K, Request>, L>> {
+        self.layer(crate::util::CoalesceLayer::new(key))
+    }
+
+    /// Mirrors requests to a `shadow` service, with at most
+    /// `max_concurrency` shadow requests in flight, spawned on the default
+    /// Tokio executor.
+    ///
+    /// This wraps the inner service with an instance of the [`Mirror`]
+    /// middleware.
+    ///
+    /// [`Mirror`]: crate::util::Mirror
+    #[cfg(all(feature = "mirror", feature = "tokio-rt"))]
+    pub fn mirror<M>(
+        self,
+        shadow: M,
+        max_concurrency: usize,
+    ) -> ServiceBuilder<Stack<crate::util::MirrorLayer<M>, L>> {
+        self.layer(crate::util::MirrorLayer::new(shadow, max_concurrency))
+    }
+
     /// Maps this service's result type (`Result<Self::Response, Self::Error>`)
     /// to a different value, regardless of whether the future succeeds or
     /// fails.
@@ -544,6 +620,48 @@ impl<L> ServiceBuilder<L> {
         self.service(crate::util::service_fn(f))
     }
 
+    /// Wrap each service yielded by the [`Discover`] `D` with the middleware
+    /// provided by this [`ServiceBuilder`]'s [`Layer`]s, returning a new
+    /// [`Discover`].
+    ///
+    /// This is useful for applying per-endpoint middleware to the services of
+    /// a [`Balance`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #[cfg(all(feature = "discover-util", feature = "limit", feature = "timeout"))]
+    /// # fn wrapper() {
+    /// use std::time::Duration;
+    /// use tower::discover;
+    /// use tower::ServiceBuilder;
+    /// # use tower::util::BoxService;
+    /// # type Svc = BoxService<(), (), tower::BoxError>;
+    ///
+    /// let (tx, discover) = discover::channel::<&'static str, Svc>();
+    ///
+    /// // Each endpoint handles at most 64 requests at a time, each of which
+    /// // times out after 10 seconds.
+    /// let discover = ServiceBuilder::new()
+    ///     .concurrency_limit(64)
+    ///     .timeout(Duration::from_secs(10))
+    ///     .discover(discover);
+    /// # drop((tx, discover));
+    /// # }
+    /// ```
+    ///
+    /// [`Discover`]: crate::discover::Discover
+    /// [`Layer`]: crate::Layer
+    /// [`Balance`]: crate::balance::p2c::Balance
+    #[cfg(feature = "discover")]
+    pub fn discover<D>(&self, discover: D) -> crate::discover::Layered<D, L>
+    where
+        D: crate::discover::Discover,
+        L: Layer<D::Service> + Clone,
+    {
+        crate::discover::Layered::new(discover, self.layer.clone())
+    }
+
     /// Check that the builder implements `Clone`.
     ///
     /// This can be useful when debugging type errors in `ServiceBuilder`s with lots of layers.
@@ -0,0 +1,135 @@
+//! Future types for the [`Cache`] middleware.
+//!
+//! [`Cache`]: crate::cache::Cache
+
+use pin_project_lite::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tower_service::Service;
+
+/// Stores a response in the cache.
+pub(crate) type Fill<T> = Box<dyn FnOnce(&T) + Send>;
+
+pin_project! {
+    /// Response future for [`Cache`].
+    ///
+    /// [`Cache`]: crate::cache::Cache
+    pub struct ResponseFuture<S, Req>
+    where
+        S: Service<Req>,
+    {
+        #[pin]
+        state: State<S, Req>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<S, Req>
+    where
+        S: Service<Req>,
+    {
+        Hit {
+            response: Option<S::Response>,
+        },
+        // Waiting for a clone of the inner service to become ready.
+        NotReady {
+            service: S,
+            request: Option<Req>,
+            fill: Option<Fill<S::Response>>,
+        },
+        Called {
+            #[pin]
+            future: S::Future,
+            fill: Option<Fill<S::Response>>,
+        },
+    }
+}
+
+impl<S, Req> ResponseFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    pub(crate) fn hit(response: S::Response) -> Self {
+        ResponseFuture {
+            state: State::Hit {
+                response: Some(response),
+            },
+        }
+    }
+
+    /// Calls `service` once it's ready, and caches the response with `fill`.
+    pub(crate) fn call(service: S, request: Req, fill: Option<Fill<S::Response>>) -> Self {
+        ResponseFuture {
+            state: State::NotReady {
+                service,
+                request: Some(request),
+                fill,
+            },
+        }
+    }
+}
+
+impl<S, Req> Future for ResponseFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.project().state;
+        loop {
+            match state.as_mut().project() {
+                StateProj::Hit { response } => {
+                    return Poll::Ready(Ok(response.take().expect("polled after completion")));
+                }
+                StateProj::NotReady {
+                    service,
+                    request,
+                    fill,
+                } => {
+                    ready!(service.poll_ready(cx))?;
+                    let request = request.take().expect("polled after completion");
+                    let future = service.call(request);
+                    let fill = fill.take();
+                    state.set(State::Called { future, fill });
+                }
+                StateProj::Called { future, fill } => {
+                    let result = ready!(future.poll(cx));
+                    if let (Ok(response), Some(fill)) = (&result, fill.take()) {
+                        fill(response);
+                    }
+                    return Poll::Ready(result);
+                }
+            }
+        }
+    }
+}
+
+impl<S, Req> fmt::Debug for ResponseFuture<S, Req>
+where
+    S: Service<Req> + fmt::Debug,
+    S::Response: fmt::Debug,
+    S::Future: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.state {
+            State::Hit { ref response } => f
+                .debug_struct("ResponseFuture::Hit")
+                .field("response", response)
+                .finish(),
+            State::NotReady { ref service, .. } => f
+                .debug_struct("ResponseFuture::NotReady")
+                .field("service", service)
+                .finish(),
+            State::Called { ref future, .. } => f
+                .debug_struct("ResponseFuture::Called")
+                .field("future", future)
+                .finish(),
+        }
+    }
+}
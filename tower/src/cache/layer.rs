@@ -0,0 +1,78 @@
+use super::service::Cache;
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+use std::{fmt, sync::Arc};
+use tower_layer::Layer;
+
+/// Serves responses from a cache, instead of calling the inner service.
+///
+/// The services produced by this layer share the same store of responses.
+///
+/// See the module documentation for more details.
+#[derive(Clone)]
+pub struct CacheLayer<K, P, St> {
+    key: K,
+    policy: P,
+    store: St,
+    executor: Arc<dyn Executor + Send + Sync>,
+}
+
+impl<K, P, St> CacheLayer<K, P, St> {
+    /// Creates a new [`CacheLayer`].
+    ///
+    /// See [`Cache::new`] for details.
+    #[cfg(feature = "tokio-rt")]
+    pub fn new(key: K, policy: P, store: St) -> Self {
+        Self::with_executor(key, policy, store, TokioExecutor::new())
+    }
+
+    /// Creates a new [`CacheLayer`], that refreshes stale responses in the background on
+    /// `executor`.
+    ///
+    /// See [`Cache::with_executor`] for details.
+    pub fn with_executor<E>(key: K, policy: P, store: St, executor: E) -> Self
+    where
+        E: Executor + Send + Sync + 'static,
+    {
+        CacheLayer {
+            key,
+            policy,
+            store,
+            executor: Arc::new(executor),
+        }
+    }
+}
+
+impl<S, K, P, St> Layer<S> for CacheLayer<K, P, St>
+where
+    K: Clone,
+    P: Clone,
+    St: Clone,
+{
+    type Service = Cache<S, K, P, St>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Cache::with_shared_executor(
+            inner,
+            self.key.clone(),
+            self.policy.clone(),
+            self.store.clone(),
+            self.executor.clone(),
+        )
+    }
+}
+
+impl<K, P, St> fmt::Debug for CacheLayer<K, P, St>
+where
+    P: fmt::Debug,
+    St: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheLayer")
+            .field("key", &format_args!("{}", std::any::type_name::<K>()))
+            .field("policy", &self.policy)
+            .field("store", &self.store)
+            .finish()
+    }
+}
@@ -0,0 +1,51 @@
+//! Middleware that serves responses from a cache.
+//!
+//! [`Cache`] extracts a key from each request, and serves the response cached for that key
+//! without calling the inner service. Otherwise, it calls the inner service, and caches its
+//! response for the next requests with the same key.
+//!
+//! Which responses are cached, and for how long, is decided by a cacheability [`Policy`]. A
+//! cached response is fresh for the time-to-live given by its [`Expiry`], after which the next
+//! request calls the inner service again. With stale-while-revalidate, a response that is no
+//! longer fresh is still served for a while, but the inner service is called in the background
+//! to refresh it. Only the successful responses of the inner service are cached.
+//!
+//! Responses are kept in a [`Store`]. [`MemoryStore`] keeps them in memory, and evicts the
+//! least recently used responses once it is full. Clones of a [`Cache`] share its store, and
+//! the same store can be given to several caches.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use tower::cache::{Cache, Expiry, MemoryStore};
+//! # #[cfg(all(feature = "util", feature = "tokio-rt"))]
+//! use tower::{service_fn, Service, ServiceExt};
+//!
+//! # #[cfg(all(feature = "util", feature = "tokio-rt"))]
+//! # async fn example() -> Result<(), tower::BoxError> {
+//! let lookup = service_fn(|user_id: u64| async move {
+//!     Ok::<_, tower::BoxError>(format!("user {}", user_id))
+//! });
+//!
+//! // Cache users for a minute, and keep serving them for ten seconds more while they are
+//! // refreshed in the background.
+//! let expiry = Expiry::new(Duration::from_secs(60)).stale_while_revalidate(Duration::from_secs(10));
+//! let mut lookup = Cache::new(lookup, |user_id: &u64| Some(*user_id), expiry, MemoryStore::new(1024));
+//!
+//! let name = lookup.ready().await?.call(42).await?;
+//! assert_eq!(name, "user 42");
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod future;
+mod layer;
+mod policy;
+mod service;
+mod store;
+
+pub use self::layer::CacheLayer;
+pub use self::policy::{Expiry, Policy};
+pub use self::service::Cache;
+pub use self::store::{Entry, MemoryStore, Store};
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+/// A "cacheability policy" that decides whether, and for how long, a response
+/// may be served from the cache.
+///
+/// [`Expiry`] is itself a policy that caches every response for the same
+/// duration, and any `Fn(&Response) -> Option<Expiry>` closure is a policy.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use tower::cache::{Expiry, Policy};
+///
+/// struct Response {
+///     status: u16,
+///     max_age: Option<Duration>,
+/// }
+///
+/// struct MaxAge;
+///
+/// impl Policy<Response> for MaxAge {
+///     fn expiry(&self, response: &Response) -> Option<Expiry> {
+///         // Only successful responses that have a max age are cached.
+///         if response.status != 200 {
+///             return None;
+///         }
+///         response.max_age.map(Expiry::new)
+///     }
+/// }
+/// ```
+pub trait Policy<Response> {
+    /// Returns for how long `response` may be served from the cache, or
+    /// `None` if it must not be cached.
+    fn expiry(&self, response: &Response) -> Option<Expiry>;
+}
+
+/// How long a response may be served from the cache.
+///
+/// A cached response is *fresh* for its time-to-live, and is then served
+/// without calling the inner service. If stale-while-revalidate is enabled,
+/// the response is then *stale* for a while: it is still served, but a
+/// background request to the inner service refreshes it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Expiry {
+    ttl: Duration,
+    stale_while_revalidate: Duration,
+}
+
+impl Expiry {
+    /// Creates a new [`Expiry`] for responses that are fresh for `ttl`.
+    pub const fn new(ttl: Duration) -> Self {
+        Expiry {
+            ttl,
+            stale_while_revalidate: Duration::ZERO,
+        }
+    }
+
+    /// Keeps serving responses for `duration` after they become stale, while
+    /// they are refreshed in the background.
+    pub const fn stale_while_revalidate(mut self, duration: Duration) -> Self {
+        self.stale_while_revalidate = duration;
+        self
+    }
+
+    /// Returns how long responses are fresh.
+    pub const fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns how long responses are served while stale.
+    pub const fn stale(&self) -> Duration {
+        self.stale_while_revalidate
+    }
+}
+
+impl<Response> Policy<Response> for Expiry {
+    fn expiry(&self, _: &Response) -> Option<Expiry> {
+        Some(*self)
+    }
+}
+
+impl<F, Response> Policy<Response> for F
+where
+    F: Fn(&Response) -> Option<Expiry>,
+{
+    fn expiry(&self, response: &Response) -> Option<Expiry> {
+        self(response)
+    }
+}
@@ -0,0 +1,214 @@
+use super::{
+    future::{Fill, ResponseFuture},
+    policy::Policy,
+    store::{Entry, Store},
+};
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time::Instant;
+use tower_service::Service;
+
+/// Serves responses from a cache, instead of calling the inner service.
+///
+/// A [`Cache`] is always ready, so that cached responses are served even while the inner service
+/// isn't ready. Instead, requests that call the inner service wait for a clone of it to become
+/// ready, so the inner service must be [`Clone`]. A service that can't be cloned cheaply can be
+/// wrapped in a [`Buffer`] first.
+///
+/// See the module documentation for more details.
+///
+/// [`Buffer`]: crate::buffer::Buffer
+#[derive(Clone)]
+pub struct Cache<S, K, P, St> {
+    inner: S,
+    key: K,
+    policy: P,
+    store: St,
+    executor: Arc<dyn Executor + Send + Sync>,
+}
+
+impl<S, K, P, St> Cache<S, K, P, St> {
+    /// Creates a new [`Cache`] wrapping `inner`.
+    ///
+    /// Requests are cached by the key that `key` returns for them. Requests for which it returns
+    /// `None` aren't cached. Responses are cached in `store`, if and for as long as `policy`
+    /// allows.
+    ///
+    /// Stale responses are refreshed on the default Tokio executor.
+    #[cfg(feature = "tokio-rt")]
+    pub fn new(inner: S, key: K, policy: P, store: St) -> Self {
+        Self::with_executor(inner, key, policy, store, TokioExecutor::new())
+    }
+
+    /// Creates a new [`Cache`] wrapping `inner`, that refreshes stale responses in the
+    /// background on `executor`.
+    ///
+    /// See [`Cache::new`] for details.
+    pub fn with_executor<E>(inner: S, key: K, policy: P, store: St, executor: E) -> Self
+    where
+        E: Executor + Send + Sync + 'static,
+    {
+        Self::with_shared_executor(inner, key, policy, store, Arc::new(executor))
+    }
+
+    pub(super) fn with_shared_executor(
+        inner: S,
+        key: K,
+        policy: P,
+        store: St,
+        executor: Arc<dyn Executor + Send + Sync>,
+    ) -> Self {
+        Cache {
+            inner,
+            key,
+            policy,
+            store,
+            executor,
+        }
+    }
+
+    /// Returns the store of cached responses.
+    pub fn store(&self) -> &St {
+        &self.store
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, K, P, St, Request, Key> Service<Request> for Cache<S, K, P, St>
+where
+    S: Service<Request> + Clone + Send + 'static,
+    S::Response: Clone + Send + 'static,
+    S::Future: Send + 'static,
+    Request: Send + 'static,
+    K: Fn(&Request) -> Option<Key>,
+    Key: Clone + Send + 'static,
+    P: Policy<S::Response> + Clone + Send + 'static,
+    St: Store<Key, S::Response> + Clone + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S, Request>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The inner service is only driven to readiness by the requests that call it.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let key = match (self.key)(&request) {
+            Some(key) => key,
+            None => return ResponseFuture::call(self.inner.clone(), request, None),
+        };
+
+        let now = Instant::now();
+        match self.store.get(&key) {
+            Some(entry) if entry.is_fresh(now) => {
+                tracing::trace!("serving fresh response from cache");
+                ResponseFuture::hit(entry.into_value())
+            }
+            Some(entry) if !entry.is_expired(now) => {
+                tracing::trace!("serving stale response from cache");
+                let response = entry.value().clone();
+                if !entry.is_revalidating() {
+                    self.revalidate(key, request, entry);
+                }
+                ResponseFuture::hit(response)
+            }
+            _ => {
+                tracing::trace!("cache miss");
+                let fill = fill(key, self.policy.clone(), self.store.clone());
+                ResponseFuture::call(self.inner.clone(), request, Some(fill))
+            }
+        }
+    }
+}
+
+impl<S, K, P, St> Cache<S, K, P, St> {
+    /// Refreshes the stale `entry` for `key` in the background.
+    fn revalidate<Request, Key>(&mut self, key: Key, request: Request, entry: Entry<S::Response>)
+    where
+        S: Service<Request> + Clone + Send + 'static,
+        S::Response: Clone + Send + 'static,
+        S::Future: Send + 'static,
+        Request: Send + 'static,
+        Key: Clone + Send + 'static,
+        P: Policy<S::Response> + Clone + Send + 'static,
+        St: Store<Key, S::Response> + Clone + Send + 'static,
+    {
+        // Other callers keep being served the stale response meanwhile, without refreshing it
+        // again. If another clone has marked the entry first, or a newer response has replaced
+        // it, there is nothing left to refresh.
+        let mut revalidating = entry.clone();
+        revalidating.set_revalidating(true);
+        if !self
+            .store
+            .compare_and_swap(&key, &entry, revalidating.clone())
+        {
+            return;
+        }
+        tracing::debug!("refreshing stale response in the background");
+
+        let fill = fill(key.clone(), self.policy.clone(), self.store.clone());
+        let future = ResponseFuture::call(self.inner.clone(), request, Some(fill));
+        let store = self.store.clone();
+        self.executor.spawn(Box::pin(async move {
+            // The response is cached by the future itself.
+            if future.await.is_err() {
+                tracing::debug!("failed to refresh stale response");
+                // Let the next caller try again, unless the entry was replaced meanwhile.
+                store.compare_and_swap(&key, &revalidating, entry);
+            }
+        }));
+    }
+}
+
+/// Returns a function that caches a response for `key`, if `policy` allows it.
+fn fill<Key, V, P, St>(key: Key, policy: P, store: St) -> Fill<V>
+where
+    Key: Send + 'static,
+    P: Policy<V> + Send + 'static,
+    St: Store<Key, V> + Send + 'static,
+    V: Clone,
+{
+    Box::new(move |response: &V| match policy.expiry(response) {
+        Some(expiry) => store.insert(key, Entry::new(response.clone(), expiry)),
+        // The response may replace a response that could be cached.
+        None => store.remove(&key),
+    })
+}
+
+impl<S, K, P, St> fmt::Debug for Cache<S, K, P, St>
+where
+    S: fmt::Debug,
+    P: fmt::Debug,
+    St: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("inner", &self.inner)
+            .field("key", &format_args!("{}", std::any::type_name::<K>()))
+            .field("policy", &self.policy)
+            .field("store", &self.store)
+            .finish()
+    }
+}
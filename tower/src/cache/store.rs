@@ -0,0 +1,327 @@
+use super::policy::Expiry;
+use crate::time::saturating_add;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+use tokio::time::Instant;
+
+/// Storage for the responses of a [`Cache`].
+///
+/// Methods take `&self`, so that a store can be shared by clones of a
+/// [`Cache`], or by several caches. [`MemoryStore`] is a bounded in-memory
+/// store, but responses can be stored elsewhere by implementing this trait.
+///
+/// Stores may drop entries at any time, for example to stay within a size
+/// bound. The cache ignores entries returned by [`Store::get`] that have
+/// expired.
+///
+/// [`Cache`]: crate::cache::Cache
+pub trait Store<K, V> {
+    /// Returns the entry for `key`, if any.
+    fn get(&self, key: &K) -> Option<Entry<V>>;
+
+    /// Stores `entry` for `key`, replacing any previous entry.
+    fn insert(&self, key: K, entry: Entry<V>);
+
+    /// Removes the entry for `key`, if any.
+    fn remove(&self, key: &K);
+
+    /// Replaces the entry for `key` with `new`, but only if the stored entry is still `current`,
+    /// as determined by [`Entry::is_same`]. Returns `true` if the entry was replaced.
+    ///
+    /// The cache uses this to mark a stale entry as being refreshed without racing with other
+    /// clones of the cache, or with a newer response. The default implementation calls
+    /// [`Store::get`] and [`Store::insert`], which isn't atomic, so stores that may be used
+    /// concurrently should override it.
+    fn compare_and_swap(&self, key: &K, current: &Entry<V>, new: Entry<V>) -> bool
+    where
+        K: Clone,
+    {
+        match self.get(key) {
+            Some(ref stored) if stored.is_same(current) => {
+                self.insert(key.clone(), new);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A cached response.
+#[derive(Clone, Debug)]
+pub struct Entry<V> {
+    /// Identifies the response, so that copies of the entry can be told apart
+    /// from other entries.
+    id: u64,
+    value: V,
+    fresh_until: Instant,
+    expires_at: Instant,
+    revalidating: bool,
+}
+
+/// A bounded in-memory [`Store`], that evicts the least recently used entries.
+///
+/// Clones share the same entries.
+pub struct MemoryStore<K, V> {
+    lru: Arc<Mutex<Lru<K, V>>>,
+}
+
+struct Lru<K, V> {
+    entries: HashMap<K, (Entry<V>, u64)>,
+    /// Keys by the tick at which they were last used.
+    recency: BTreeMap<u64, K>,
+    tick: u64,
+    capacity: usize,
+}
+
+// ===== impl Store =====
+
+impl<S, K, V> Store<K, V> for Arc<S>
+where
+    S: Store<K, V> + ?Sized,
+{
+    fn get(&self, key: &K) -> Option<Entry<V>> {
+        (**self).get(key)
+    }
+
+    fn insert(&self, key: K, entry: Entry<V>) {
+        (**self).insert(key, entry)
+    }
+
+    fn remove(&self, key: &K) {
+        (**self).remove(key)
+    }
+
+    fn compare_and_swap(&self, key: &K, current: &Entry<V>, new: Entry<V>) -> bool
+    where
+        K: Clone,
+    {
+        (**self).compare_and_swap(key, current, new)
+    }
+}
+
+// ===== impl Entry =====
+
+impl<V> Entry<V> {
+    pub(crate) fn new(value: V, expiry: Expiry) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let fresh_until = saturating_add(Instant::now(), expiry.ttl());
+        Entry {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            value,
+            fresh_until,
+            expires_at: saturating_add(fresh_until, expiry.stale()),
+            revalidating: false,
+        }
+    }
+
+    /// Returns the cached response.
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+
+    /// Consumes the entry, returning the cached response.
+    pub fn into_value(self) -> V {
+        self.value
+    }
+
+    /// Returns when the response becomes stale.
+    pub fn fresh_until(&self) -> Instant {
+        self.fresh_until
+    }
+
+    /// Returns when the response can no longer be served.
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+
+    /// Returns `true` if `other` is a copy of this entry, in the same revalidation state.
+    ///
+    /// Entries created for different responses are never the same, even if the responses are
+    /// equal.
+    pub fn is_same(&self, other: &Entry<V>) -> bool {
+        self.id == other.id && self.revalidating == other.revalidating
+    }
+
+    pub(crate) fn is_fresh(&self, now: Instant) -> bool {
+        now < self.fresh_until
+    }
+
+    pub(crate) fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Returns `true` if a background request is refreshing the response.
+    pub(crate) fn is_revalidating(&self) -> bool {
+        self.revalidating
+    }
+
+    pub(crate) fn set_revalidating(&mut self, revalidating: bool) {
+        self.revalidating = revalidating;
+    }
+}
+
+// ===== impl MemoryStore =====
+
+impl<K, V> MemoryStore<K, V> {
+    /// Creates a new [`MemoryStore`] holding at most `capacity` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "cache capacity must be greater than zero");
+        MemoryStore {
+            lru: Arc::new(Mutex::new(Lru {
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+                tick: 0,
+                capacity,
+            })),
+        }
+    }
+
+    /// Returns the number of stored entries, including expired entries that
+    /// haven't been evicted yet.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns `true` if there are no stored entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximal number of stored entries.
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lru<K, V>> {
+        self.lru.lock().expect("cache store poisoned")
+    }
+}
+
+impl<K, V> Store<K, V> for MemoryStore<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    fn get(&self, key: &K) -> Option<Entry<V>> {
+        let mut lru = self.lock();
+        let lru = &mut *lru;
+        let (entry, used) = lru.entries.get_mut(key)?;
+        if entry.is_expired(Instant::now()) {
+            lru.recency.remove(used);
+            lru.entries.remove(key);
+            return None;
+        }
+
+        lru.tick += 1;
+        let key = lru.recency.remove(used).expect("entry must have a recency");
+        *used = lru.tick;
+        lru.recency.insert(lru.tick, key);
+        Some(entry.clone())
+    }
+
+    fn insert(&self, key: K, entry: Entry<V>) {
+        let mut lru = self.lock();
+        let lru = &mut *lru;
+        lru.tick += 1;
+        let tick = lru.tick;
+        if let Some((_, used)) = lru.entries.insert(key.clone(), (entry, tick)) {
+            lru.recency.remove(&used);
+        } else if lru.entries.len() > lru.capacity {
+            let oldest = *lru
+                .recency
+                .keys()
+                .next()
+                .expect("a full store must not be empty");
+            let evicted = lru
+                .recency
+                .remove(&oldest)
+                .expect("oldest key must be stored");
+            lru.entries.remove(&evicted);
+        }
+        lru.recency.insert(tick, key);
+    }
+
+    fn remove(&self, key: &K) {
+        let mut lru = self.lock();
+        if let Some((_, used)) = lru.entries.remove(key) {
+            lru.recency.remove(&used);
+        }
+    }
+
+    fn compare_and_swap(&self, key: &K, current: &Entry<V>, new: Entry<V>) -> bool {
+        let mut lru = self.lock();
+        match lru.entries.get_mut(key) {
+            Some((stored, _)) if stored.is_same(current) => {
+                *stored = new;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<K, V> Clone for MemoryStore<K, V> {
+    fn clone(&self) -> Self {
+        MemoryStore {
+            lru: self.lru.clone(),
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for MemoryStore<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lru = self.lock();
+        f.debug_struct("MemoryStore")
+            .field("len", &lru.entries.len())
+            .field("capacity", &lru.capacity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn compare_and_swap_only_replaces_the_same_entry() {
+        let expiry = Expiry::new(Duration::from_secs(1));
+        let store = MemoryStore::new(10);
+        let old = Entry::new("old", expiry);
+        store.insert("a", old.clone());
+
+        let mut revalidating = old.clone();
+        revalidating.set_revalidating(true);
+        assert!(store.compare_and_swap(&"a", &old, revalidating.clone()));
+        // The entry is already being revalidated.
+        assert!(!store.compare_and_swap(&"a", &old, revalidating.clone()));
+
+        // A newer response isn't replaced by the old one.
+        store.insert("a", Entry::new("new", expiry));
+        assert!(!store.compare_and_swap(&"a", &revalidating, old));
+        assert_eq!(*store.get(&"a").unwrap().value(), "new");
+    }
+
+    #[test]
+    fn entry_saturates_long_expiries() {
+        let entry = Entry::new(
+            (),
+            Expiry::new(Duration::MAX).stale_while_revalidate(Duration::MAX),
+        );
+        let now = Instant::now();
+        assert!(entry.is_fresh(now));
+        assert!(!entry.is_expired(now));
+    }
+}
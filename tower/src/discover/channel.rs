@@ -0,0 +1,141 @@
+use super::Change;
+use futures_core::Stream;
+use std::convert::Infallible;
+use std::fmt;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+
+/// Creates a [`Discover`] that is fed by sending [`Change`]s through a
+/// channel.
+///
+/// This is useful when changes to the service set are learned about outside
+/// of the task polling the discovery, such as from a control plane
+/// subscription. The returned [`Receiver`] yields the changes in the order in
+/// which they were sent, and ends once every [`Sender`] has been dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower::discover::{self, Change};
+/// # use tower::BoxError;
+///
+/// # fn main() -> Result<(), BoxError> {
+/// let (tx, discover) = discover::channel::<&'static str, ()>();
+/// tx.insert("a", ())?;
+/// tx.remove("a")?;
+/// # drop(discover);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Discover`]: crate::discover::Discover
+pub fn channel<K, S>() -> (Sender<K, S>, Receiver<K, S>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (Sender { tx }, Receiver { rx })
+}
+
+/// Sends [`Change`]s to the [`Receiver`] created by [`channel`].
+pub struct Sender<K, S> {
+    tx: mpsc::UnboundedSender<Change<K, S>>,
+}
+
+/// A [`Discover`] yielding the [`Change`]s sent by the [`Sender`]s created by
+/// [`channel`].
+///
+/// [`Discover`]: crate::discover::Discover
+pub struct Receiver<K, S> {
+    rx: mpsc::UnboundedReceiver<Change<K, S>>,
+}
+
+/// An error produced when a [`Change`] is sent after the [`Receiver`] has
+/// been dropped.
+///
+/// The change that could not be sent is returned by
+/// [`SendError::into_change`].
+pub struct SendError<K, S> {
+    change: Change<K, S>,
+}
+
+// ===== impl Sender =====
+
+impl<K, S> Sender<K, S> {
+    /// Sends a [`Change`] to the [`Receiver`].
+    ///
+    /// Fails, returning the change, if the [`Receiver`] has been dropped.
+    pub fn send(&self, change: Change<K, S>) -> Result<(), SendError<K, S>> {
+        self.tx
+            .send(change)
+            .map_err(|mpsc::error::SendError(change)| SendError { change })
+    }
+
+    /// Inserts the service `svc` with the identifier `key`.
+    pub fn insert(&self, key: K, svc: S) -> Result<(), SendError<K, S>> {
+        self.send(Change::Insert(key, svc))
+    }
+
+    /// Removes the service with the identifier `key`.
+    pub fn remove(&self, key: K) -> Result<(), SendError<K, S>> {
+        self.send(Change::Remove(key))
+    }
+
+    /// Returns whether the [`Receiver`] has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+impl<K, S> Clone for Sender<K, S> {
+    fn clone(&self) -> Self {
+        Sender {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<K, S> fmt::Debug for Sender<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").field("tx", &self.tx).finish()
+    }
+}
+
+// ===== impl Receiver =====
+
+impl<K, S> Stream for Receiver<K, S> {
+    type Item = Result<Change<K, S>, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx).map(|change| change.map(Ok))
+    }
+}
+
+impl<K, S> fmt::Debug for Receiver<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").field("rx", &self.rx).finish()
+    }
+}
+
+// ===== impl SendError =====
+
+impl<K, S> SendError<K, S> {
+    /// Returns the [`Change`] that could not be sent.
+    pub fn into_change(self) -> Change<K, S> {
+        self.change
+    }
+}
+
+impl<K, S> fmt::Debug for SendError<K, S> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<K, S> fmt::Display for SendError<K, S> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "discovery receiver dropped")
+    }
+}
+
+impl<K, S> std::error::Error for SendError<K, S> {}
@@ -0,0 +1,153 @@
+use super::{Change, Discover};
+use crate::time::saturating_add;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+use std::time::Duration;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::time::{Instant, Sleep};
+
+pin_project! {
+    /// Coalesces rapid changes to the services of a [`Discover`].
+    ///
+    /// When a change for a key is discovered, it is held back for the debounce
+    /// delay. Later changes for the same key within that window replace the
+    /// held change, so only the last change for a key is yielded once the
+    /// window elapses. A service that is inserted and removed again within the
+    /// window is never yielded, and a service that is removed and inserted
+    /// again is yielded as a single [`Change::Insert`] replacing the old
+    /// service.
+    ///
+    /// Changes held back when the underlying [`Discover`] ends are yielded
+    /// immediately.
+    ///
+    /// This is created by [`DiscoverExt::debounce`].
+    ///
+    /// [`DiscoverExt::debounce`]: crate::discover::DiscoverExt::debounce
+    pub struct Debounce<D>
+    where
+        D: Discover,
+    {
+        #[pin]
+        discover: D,
+        delay: Duration,
+        done: bool,
+        // The change currently held back for each key.
+        pending: HashMap<D::Key, Change<D::Key, D::Service>>,
+        // When the window of each key with a held change ends, in order.
+        deadlines: VecDeque<(Instant, D::Key)>,
+        // Keys whose services have been yielded, and not removed since.
+        inserted: HashSet<D::Key>,
+        sleep: Option<Pin<Box<Sleep>>>,
+    }
+}
+
+impl<D> Debounce<D>
+where
+    D: Discover,
+{
+    /// Creates a new [`Debounce`], holding back changes for `delay`.
+    pub fn new(discover: D, delay: Duration) -> Self {
+        Debounce {
+            discover,
+            delay,
+            done: false,
+            pending: HashMap::new(),
+            deadlines: VecDeque::new(),
+            inserted: HashSet::new(),
+            sleep: None,
+        }
+    }
+}
+
+impl<D> Stream for Debounce<D>
+where
+    D: Discover,
+    D::Key: Hash + Clone,
+{
+    type Item = Result<Change<D::Key, D::Service>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        while !*this.done {
+            let change = match this.discover.as_mut().poll_discover(cx) {
+                Poll::Ready(Some(Ok(change))) => change,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    break;
+                }
+                Poll::Pending => break,
+            };
+            let key = match change {
+                Change::Insert(ref key, _) | Change::Remove(ref key) => key.clone(),
+            };
+            if this.pending.insert(key.clone(), change).is_none() {
+                this.deadlines
+                    .push_back((saturating_add(Instant::now(), *this.delay), key));
+            }
+        }
+
+        loop {
+            let deadline = match this.deadlines.front() {
+                Some((deadline, _)) => *deadline,
+                None if *this.done => return Poll::Ready(None),
+                None => return Poll::Pending,
+            };
+
+            if !*this.done && deadline > Instant::now() {
+                let sleep = match this.sleep {
+                    Some(sleep) => {
+                        sleep.as_mut().reset(deadline);
+                        sleep
+                    }
+                    None => this
+                        .sleep
+                        .insert(Box::pin(tokio::time::sleep_until(deadline))),
+                };
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+
+            let (_, key) = this.deadlines.pop_front().expect("deadline must be queued");
+            let change = this
+                .pending
+                .remove(&key)
+                .expect("a change must be held for each deadline");
+            match change {
+                Change::Insert(key, svc) => {
+                    this.inserted.insert(key.clone());
+                    return Poll::Ready(Some(Ok(Change::Insert(key, svc))));
+                }
+                // Services that were never yielded don't need to be removed.
+                Change::Remove(key) if this.inserted.remove(&key) => {
+                    return Poll::Ready(Some(Ok(Change::Remove(key))));
+                }
+                Change::Remove(_) => {}
+            }
+        }
+    }
+}
+
+impl<D> fmt::Debug for Debounce<D>
+where
+    D: Discover + fmt::Debug,
+    D::Key: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debounce")
+            .field("discover", &self.discover)
+            .field("delay", &self.delay)
+            .field("pending", &self.pending.keys())
+            .field("inserted", &self.inserted)
+            .finish()
+    }
+}
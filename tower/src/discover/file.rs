@@ -0,0 +1,232 @@
+use super::{polling::PollingDiscover, Change, Resolve};
+use crate::make::MakeService;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::hash::Hash;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+type Parse<T> = Box<dyn Fn(&str) -> Result<Vec<T>, crate::BoxError> + Send + Sync>;
+
+pin_project! {
+    /// A [`Discover`] that reads its targets from a file, and reloads them when
+    /// the file changes.
+    ///
+    /// Every `interval`, the file's modification time and length are checked.
+    /// When either has changed, the file is read and parsed again, and the
+    /// targets that were added or removed are yielded as [`Change`]s, just
+    /// like [`PollingDiscover`]. Services for new targets are built with a
+    /// [`MakeService`], and each target is used as the key of its service.
+    ///
+    /// By default, the file lists one target per line; see [`FileDiscover::new`].
+    /// Other formats, such as JSON or TOML, are supported by providing a parser
+    /// to [`FileDiscover::with_parser`].
+    ///
+    /// The file is read on Tokio's blocking thread pool, and checked for
+    /// changes with a Tokio timer, so the discovery must be polled within a
+    /// Tokio runtime with the time driver enabled. It may be created outside
+    /// of a runtime, however. If the file can't be read or parsed, for example because it
+    /// is missing or only partially written, the failure is logged and the
+    /// previous targets are kept until the file changes again.
+    ///
+    /// [`Discover`]: crate::discover::Discover
+    /// [`MakeService`]: crate::make::MakeService
+    pub struct FileDiscover<T, M, Req>
+    where
+        T: Clone,
+        T: Send,
+        T: 'static,
+        M: MakeService<T, Req>,
+    {
+        #[pin]
+        inner: PollingDiscover<FileResolve<T>, M, Req>,
+    }
+}
+
+/// Reads the targets of a [`FileDiscover`].
+struct FileResolve<T> {
+    // Shared with the blocking task reading the file.
+    file: Arc<File<T>>,
+}
+
+struct File<T> {
+    path: PathBuf,
+    parse: Parse<T>,
+    loaded: Mutex<Loaded<T>>,
+}
+
+/// The targets that were last read successfully.
+struct Loaded<T> {
+    /// The modification time and length of the file when it was read.
+    version: Option<(SystemTime, u64)>,
+    targets: Vec<T>,
+}
+
+// ===== impl FileDiscover =====
+
+impl<T, M, Req> FileDiscover<T, M, Req>
+where
+    T: Clone + Send + 'static,
+    M: MakeService<T, Req>,
+{
+    /// Creates a [`FileDiscover`] that reads one target per line from the file
+    /// at `path`, checking the file for changes every `interval`.
+    ///
+    /// Each line is parsed with [`FromStr`], after trimming surrounding
+    /// whitespace. Empty lines and lines starting with `#` are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(path: impl Into<PathBuf>, make_service: M, interval: Duration) -> Self
+    where
+        T: FromStr + 'static,
+        T::Err: Into<crate::BoxError>,
+    {
+        Self::with_parser(path, parse_lines, make_service, interval)
+    }
+
+    /// Creates a [`FileDiscover`] that parses the targets from the contents of
+    /// the file at `path` with `parse`, checking the file for changes every
+    /// `interval`.
+    ///
+    /// For example, a JSON array of addresses could be parsed with
+    /// `serde_json::from_str::<Vec<SocketAddr>>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn with_parser<P, E>(
+        path: impl Into<PathBuf>,
+        parse: P,
+        make_service: M,
+        interval: Duration,
+    ) -> Self
+    where
+        P: Fn(&str) -> Result<Vec<T>, E> + Send + Sync + 'static,
+        E: Into<crate::BoxError>,
+    {
+        let file = File {
+            path: path.into(),
+            parse: Box::new(move |contents| parse(contents).map_err(Into::into)),
+            loaded: Mutex::new(Loaded {
+                version: None,
+                targets: Vec::new(),
+            }),
+        };
+        let resolve = FileResolve {
+            file: Arc::new(file),
+        };
+        FileDiscover {
+            inner: PollingDiscover::new(resolve, make_service, interval),
+        }
+    }
+}
+
+impl<T, M, Req> Stream for FileDiscover<T, M, Req>
+where
+    T: Hash + Eq + Clone + Send + 'static,
+    M: MakeService<T, Req>,
+    M::MakeError: Into<crate::BoxError>,
+{
+    type Item = Result<Change<T, M::Service>, crate::BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().inner.poll_next(cx)
+    }
+}
+
+impl<T, M, Req> fmt::Debug for FileDiscover<T, M, Req>
+where
+    T: Clone + Send + fmt::Debug + 'static,
+    M: MakeService<T, Req> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileDiscover")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+// ===== impl FileResolve =====
+
+impl<T> Resolve for FileResolve<T>
+where
+    T: Clone + Send + 'static,
+{
+    type Target = T;
+    type Targets = Vec<T>;
+    type Error = crate::BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Vec<T>, crate::BoxError>> + Send>>;
+
+    fn resolve(&mut self) -> Self::Future {
+        let file = self.file.clone();
+        let read = tokio::task::spawn_blocking(move || file.read());
+        Box::pin(async move { read.await.map_err(Into::into) })
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for FileResolve<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let loaded = self.file.loaded.lock().expect("file targets poisoned");
+        f.debug_struct("FileResolve")
+            .field("path", &self.file.path)
+            .field("targets", &loaded.targets)
+            .finish()
+    }
+}
+
+// ===== impl File =====
+
+impl<T: Clone> File<T> {
+    /// Returns the targets listed in the file, or the previous targets if the
+    /// file can't be read or parsed.
+    fn read(&self) -> Vec<T> {
+        let mut loaded = self.loaded.lock().expect("file targets poisoned");
+        if let Err(error) = self.reload(&mut loaded) {
+            debug!(
+                path = %self.path.display(),
+                %error,
+                "failed to reload targets; keeping the previous targets"
+            );
+        }
+        loaded.targets.clone()
+    }
+
+    /// Reads and parses the file again if it has changed since it was last
+    /// read successfully.
+    fn reload(&self, loaded: &mut Loaded<T>) -> Result<(), crate::BoxError> {
+        let metadata = fs::metadata(&self.path)?;
+        let version = (metadata.modified()?, metadata.len());
+        if loaded.version != Some(version) {
+            let contents = fs::read_to_string(&self.path)?;
+            loaded.targets = (self.parse)(&contents)?;
+            loaded.version = Some(version);
+            debug!(path = %self.path.display(), targets = loaded.targets.len(), "reloaded targets");
+        }
+        Ok(())
+    }
+}
+
+fn parse_lines<T>(contents: &str) -> Result<Vec<T>, crate::BoxError>
+where
+    T: FromStr,
+    T::Err: Into<crate::BoxError>,
+{
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.parse().map_err(Into::into))
+        .collect()
+}
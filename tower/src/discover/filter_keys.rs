@@ -0,0 +1,67 @@
+use super::{Change, Discover};
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+use std::fmt;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pin_project! {
+    /// Only yields the services of a [`Discover`] whose keys match a predicate.
+    ///
+    /// This is created by [`DiscoverExt::filter_keys`].
+    ///
+    /// [`DiscoverExt::filter_keys`]: crate::discover::DiscoverExt::filter_keys
+    #[derive(Clone)]
+    pub struct FilterKeys<D, F> {
+        #[pin]
+        discover: D,
+        predicate: F,
+    }
+}
+
+impl<D, F> FilterKeys<D, F> {
+    /// Creates a new [`FilterKeys`].
+    pub const fn new(discover: D, predicate: F) -> Self {
+        FilterKeys {
+            discover,
+            predicate,
+        }
+    }
+}
+
+impl<D, F> Stream for FilterKeys<D, F>
+where
+    D: Discover,
+    F: FnMut(&D::Key) -> bool,
+{
+    type Item = Result<Change<D::Key, D::Service>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            let change = match ready!(this.discover.as_mut().poll_discover(cx)) {
+                Some(Ok(change)) => change,
+                other => return Poll::Ready(other),
+            };
+            let key = match change {
+                Change::Insert(ref key, _) | Change::Remove(ref key) => key,
+            };
+            // Removals are filtered like insertions, so a removal is only
+            // yielded for keys whose services were yielded.
+            if (this.predicate)(key) {
+                return Poll::Ready(Some(Ok(change)));
+            }
+        }
+    }
+}
+
+impl<D: fmt::Debug, F> fmt::Debug for FilterKeys<D, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterKeys")
+            .field("discover", &self.discover)
+            .field("predicate", &format_args!("{}", std::any::type_name::<F>()))
+            .finish()
+    }
+}
@@ -0,0 +1,82 @@
+use super::{Change, Discover};
+use futures_core::{ready, Stream};
+use pin_project_lite::pin_project;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+
+pin_project! {
+    /// Wraps each service yielded by a [`Discover`] with a [`Layer`].
+    ///
+    /// Every service inserted by the underlying [`Discover`] is passed through
+    /// [`Layer::layer`] before being yielded, so that per-endpoint middleware,
+    /// such as a [`Timeout`], a [`ConcurrencyLimit`] or a load metric like
+    /// [`PeakEwma`], can be applied to the endpoints of a [`Balance`].
+    ///
+    /// This may also be created with [`DiscoverExt::map_service`], or with
+    /// [`ServiceBuilder::discover`] to apply the builder's layers.
+    ///
+    /// [`Timeout`]: crate::timeout::Timeout
+    /// [`ConcurrencyLimit`]: crate::limit::ConcurrencyLimit
+    /// [`PeakEwma`]: crate::load::PeakEwma
+    /// [`Balance`]: crate::balance::p2c::Balance
+    /// [`DiscoverExt::map_service`]: crate::discover::DiscoverExt::map_service
+    /// [`ServiceBuilder::discover`]: crate::ServiceBuilder::discover
+    #[derive(Clone, Debug)]
+    pub struct Layered<D, L> {
+        #[pin]
+        discover: D,
+        layer: L,
+    }
+}
+
+impl<D, L> Layered<D, L> {
+    /// Creates a new [`Layered`], wrapping the services of `discover` with
+    /// `layer`.
+    pub const fn new(discover: D, layer: L) -> Self {
+        Layered { discover, layer }
+    }
+
+    /// Get a reference to the inner [`Discover`]
+    pub fn get_ref(&self) -> &D {
+        &self.discover
+    }
+
+    /// Get a mutable reference to the inner [`Discover`]
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.discover
+    }
+
+    /// Get a reference to the [`Layer`] applied to each service
+    pub fn layer(&self) -> &L {
+        &self.layer
+    }
+
+    /// Consume `self`, returning the inner [`Discover`]
+    pub fn into_inner(self) -> D {
+        self.discover
+    }
+}
+
+impl<D, L> Stream for Layered<D, L>
+where
+    D: Discover,
+    L: Layer<D::Service>,
+{
+    type Item = Result<Change<D::Key, L::Service>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let change = match ready!(this.discover.poll_discover(cx)) {
+            Some(Ok(change)) => change,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+        Poll::Ready(Some(Ok(match change {
+            Change::Insert(key, svc) => Change::Insert(key, this.layer.layer(svc)),
+            Change::Remove(key) => Change::Remove(key),
+        })))
+    }
+}
@@ -0,0 +1,104 @@
+use super::{Change, Discover};
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pin_project! {
+    /// Yields the services of two [`Discover`]s as a single set of services.
+    ///
+    /// Keys are namespaced with [`MergeKey`], so that services from both
+    /// sources may use the same keys without replacing each other.
+    ///
+    /// This is created by [`DiscoverExt::merge`].
+    ///
+    /// [`DiscoverExt::merge`]: crate::discover::DiscoverExt::merge
+    #[derive(Clone, Debug)]
+    pub struct Merge<A, B> {
+        #[pin]
+        first: A,
+        #[pin]
+        second: B,
+        first_done: bool,
+        second_done: bool,
+        // Which source is polled first, alternated so that a busy source
+        // can't starve the other.
+        second_first: bool,
+    }
+}
+
+/// The key of a service yielded by [`Merge`], identifying which source it came
+/// from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MergeKey<A, B> {
+    /// A key from the first source.
+    First(A),
+    /// A key from the second source.
+    Second(B),
+}
+
+impl<A, B> Merge<A, B> {
+    /// Creates a new [`Merge`].
+    pub const fn new(first: A, second: B) -> Self {
+        Merge {
+            first,
+            second,
+            first_done: false,
+            second_done: false,
+            second_first: false,
+        }
+    }
+}
+
+impl<A, B> Stream for Merge<A, B>
+where
+    A: Discover,
+    B: Discover<Service = A::Service, Error = A::Error>,
+{
+    type Item = Result<Change<MergeKey<A::Key, B::Key>, A::Service>, A::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        *this.second_first = !*this.second_first;
+        for second in [*this.second_first, !*this.second_first] {
+            let polled = if second {
+                if *this.second_done {
+                    continue;
+                }
+                this.second
+                    .as_mut()
+                    .poll_discover(cx)
+                    .map(|change| change.map(|c| c.map(|c| namespace(c, MergeKey::Second))))
+            } else {
+                if *this.first_done {
+                    continue;
+                }
+                this.first
+                    .as_mut()
+                    .poll_discover(cx)
+                    .map(|change| change.map(|c| c.map(|c| namespace(c, MergeKey::First))))
+            };
+            match polled {
+                Poll::Ready(None) if second => *this.second_done = true,
+                Poll::Ready(None) => *this.first_done = true,
+                Poll::Ready(Some(change)) => return Poll::Ready(Some(change)),
+                Poll::Pending => {}
+            }
+        }
+
+        if *this.first_done && *this.second_done {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+fn namespace<K, S, N>(change: Change<K, S>, f: impl FnOnce(K) -> N) -> Change<N, S> {
+    match change {
+        Change::Insert(key, svc) => Change::Insert(f(key), svc),
+        Change::Remove(key) => Change::Remove(f(key)),
+    }
+}
@@ -7,7 +7,9 @@
 //!
 //! Every discovered service is assigned an identifier that is distinct among the currently active
 //! services. If that service later goes away, a [`Change::Remove`] is yielded with that service's
-//! identifier. From that point forward, the identifier may be re-used.
+//! identifier. From that point forward, the identifier may be re-used. A service may also be
+//! replaced, for example when its endpoint's configuration changes, by yielding a
+//! [`Change::Insert`] with the identifier of the service it replaces.
 //!
 //! # Examples
 //!
@@ -32,14 +34,55 @@
 //! }
 //! ```
 //!
+//! # Implementations
+//!
+//! Besides the static [`ServiceList`], this module provides [`channel`], which yields the
+//! [`Change`]s sent to it, and [`PollingDiscover`], which periodically looks up a set of targets
+//! with a [`Resolve`]r and builds a service for each new target. [`FileDiscover`] does the same
+//! for targets listed in a file, reloading them when the file changes. These implementations,
+//! and [`DiscoverExt::debounce`], require the `discover-util` feature.
+//!
+//! The [`DiscoverExt`] trait provides combinators for transforming and combining the services
+//! yielded by a [`Discover`].
+//!
 //! [`TryStream`]: https://docs.rs/futures/latest/futures/stream/trait.TryStream.html
 
+#[cfg(feature = "discover-util")]
+mod channel;
+#[cfg(feature = "discover-util")]
+mod debounce;
+#[cfg(feature = "discover-util")]
+mod file;
+mod filter_keys;
+mod layered;
 mod list;
+mod merge;
+#[cfg(feature = "discover-util")]
+mod polling;
 
+#[cfg(feature = "discover-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "discover-util")))]
+pub use self::channel::{channel, Receiver, SendError, Sender};
+#[cfg(feature = "discover-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "discover-util")))]
+pub use self::debounce::Debounce;
+#[cfg(feature = "discover-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "discover-util")))]
+pub use self::file::FileDiscover;
+pub use self::filter_keys::FilterKeys;
+pub use self::layered::Layered;
 pub use self::list::ServiceList;
+pub use self::merge::{Merge, MergeKey};
+#[cfg(feature = "discover-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "discover-util")))]
+pub use self::polling::{PollingDiscover, Resolve};
 
 use crate::sealed::Sealed;
 use futures_core::TryStream;
+#[cfg(feature = "discover-util")]
+use std::hash::Hash;
+#[cfg(feature = "discover-util")]
+use std::time::Duration;
 use std::{
     pin::Pin,
     task::{Context, Poll},
@@ -97,10 +140,87 @@ where
     }
 }
 
+/// An extension trait for [`Discover`]s that provides a variety of convenient
+/// adapters.
+pub trait DiscoverExt: Discover {
+    /// Wraps each discovered service with `layer`.
+    ///
+    /// See [`Layered`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #[cfg(all(feature = "discover-util", feature = "timeout"))]
+    /// # fn wrapper() {
+    /// use std::time::Duration;
+    /// use tower::discover::{self, DiscoverExt};
+    /// use tower::timeout::TimeoutLayer;
+    /// # use tower::util::BoxService;
+    /// # type Svc = BoxService<(), (), tower::BoxError>;
+    ///
+    /// let (tx, discover) = discover::channel::<&'static str, Svc>();
+    /// // Every discovered service times out after one second.
+    /// let discover = discover.map_service(TimeoutLayer::new(Duration::from_secs(1)));
+    /// # drop((tx, discover));
+    /// # }
+    /// ```
+    fn map_service<L>(self, layer: L) -> Layered<Self, L>
+    where
+        Self: Sized,
+        L: crate::Layer<Self::Service>,
+    {
+        Layered::new(self, layer)
+    }
+
+    /// Only yields the services whose keys match `predicate`.
+    fn filter_keys<F>(self, predicate: F) -> FilterKeys<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Key) -> bool,
+    {
+        FilterKeys::new(self, predicate)
+    }
+
+    /// Yields the services of both `self` and `other`.
+    ///
+    /// The keys of the merged services are namespaced with [`MergeKey`], so
+    /// that both sources may use the same keys.
+    fn merge<D>(self, other: D) -> Merge<Self, D>
+    where
+        Self: Sized,
+        D: Discover<Service = Self::Service, Error = Self::Error>,
+    {
+        Merge::new(self, other)
+    }
+
+    /// Coalesces changes to the same key that happen within `delay` of each
+    /// other.
+    ///
+    /// See [`Debounce`] for details.
+    #[cfg(feature = "discover-util")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "discover-util")))]
+    fn debounce(self, delay: Duration) -> Debounce<Self>
+    where
+        Self: Sized,
+        Self::Key: Hash + Clone,
+    {
+        Debounce::new(self, delay)
+    }
+}
+
+impl<D: Discover + ?Sized> DiscoverExt for D {}
+
 /// A change in the service set.
 #[derive(Debug, Clone)]
 pub enum Change<K, V> {
     /// A new service identified by key `K` was identified.
+    ///
+    /// If a service with the same key is already present, the new service
+    /// replaces it, for example to update the endpoint's configuration.
+    /// Consumers such as [`Balance`] apply the replacement atomically: the
+    /// existing service keeps handling requests until the new one is ready.
+    ///
+    /// [`Balance`]: crate::balance::p2c::Balance
     Insert(K, V),
     /// The service identified by key `K` disappeared.
     Remove(K),
@@ -0,0 +1,245 @@
+use super::Change;
+use crate::make::MakeService;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::time::Duration;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::{debug, trace};
+
+/// Looks up the current set of targets for a [`PollingDiscover`].
+///
+/// This is the extension point for sources of targets such as DNS lookups or
+/// files listing addresses. It is implemented for all `FnMut() -> Future`
+/// closures whose future resolves to an iterator of targets, so a closure may
+/// be used directly:
+///
+/// ```rust
+/// use std::convert::Infallible;
+/// use tower::discover::Resolve;
+///
+/// fn resolver() -> impl Resolve<Target = &'static str, Error = Infallible> {
+///     || async { Ok(vec!["10.0.0.1:80", "10.0.0.2:80"]) }
+/// }
+/// ```
+pub trait Resolve {
+    /// The target of a single endpoint, such as a socket address.
+    type Target;
+
+    /// The collection of targets returned by a lookup.
+    type Targets: IntoIterator<Item = Self::Target>;
+
+    /// Errors produced while looking up targets.
+    type Error;
+
+    /// The future returned by [`Resolve::resolve`].
+    type Future: Future<Output = Result<Self::Targets, Self::Error>>;
+
+    /// Looks up the current set of targets.
+    fn resolve(&mut self) -> Self::Future;
+}
+
+impl<F, Fut, I, E> Resolve for F
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<I, E>>,
+    I: IntoIterator,
+{
+    type Target = I::Item;
+    type Targets = I;
+    type Error = E;
+    type Future = Fut;
+
+    fn resolve(&mut self) -> Self::Future {
+        self()
+    }
+}
+
+pin_project! {
+    /// A [`Discover`] that periodically looks up the set of targets with a
+    /// [`Resolve`]r.
+    ///
+    /// Every `interval`, the current targets are looked up, and compared to the
+    /// targets found by the previous lookup. Targets that have disappeared are
+    /// removed with [`Change::Remove`], and services for new targets are built
+    /// with a [`MakeService`] and inserted with [`Change::Insert`]. Each
+    /// target is used as the key of its service.
+    ///
+    /// Errors are never yielded, since a consumer such as [`Balance`] fails
+    /// once its discovery does. Instead, if a lookup fails, the failure is
+    /// logged and the current targets are kept until the next lookup. If
+    /// building a service fails, the failure is logged and the target is
+    /// retried on the next lookup.
+    ///
+    /// [`Balance`]: crate::balance::p2c::Balance
+    ///
+    /// [`Discover`]: crate::discover::Discover
+    /// [`MakeService`]: crate::make::MakeService
+    pub struct PollingDiscover<R, M, Req>
+    where
+        R: Resolve,
+        M: MakeService<R::Target, Req>,
+    {
+        resolve: R,
+        make_service: M,
+        period: Duration,
+        // Created on the first poll, so that the discovery can be built outside
+        // of a Tokio runtime.
+        interval: Option<Interval>,
+        resolving: Option<Pin<Box<R::Future>>>,
+        making: Option<(R::Target, Pin<Box<M::Future>>)>,
+        // Targets for which a service has been inserted.
+        current: HashSet<R::Target>,
+        // Targets found by the last lookup that still need a service.
+        added: VecDeque<R::Target>,
+        // Targets that disappeared in the last lookup.
+        removed: VecDeque<R::Target>,
+        _req: PhantomData<fn(Req)>,
+    }
+}
+
+impl<R, M, Req> PollingDiscover<R, M, Req>
+where
+    R: Resolve,
+    M: MakeService<R::Target, Req>,
+{
+    /// Creates a [`PollingDiscover`] that looks up targets with `resolve`
+    /// every `interval`, and builds their services with `make_service`.
+    ///
+    /// The first lookup happens immediately when the discovery is first
+    /// polled. Lookups are scheduled with a Tokio timer, so the discovery must
+    /// be polled within a Tokio runtime with the time driver enabled, but it
+    /// may be created anywhere.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(resolve: R, make_service: M, interval: Duration) -> Self {
+        assert!(
+            interval > Duration::ZERO,
+            "polling interval must be greater than zero"
+        );
+        PollingDiscover {
+            resolve,
+            make_service,
+            period: interval,
+            interval: None,
+            resolving: None,
+            making: None,
+            current: HashSet::new(),
+            added: VecDeque::new(),
+            removed: VecDeque::new(),
+            _req: PhantomData,
+        }
+    }
+}
+
+impl<R, M, Req> Stream for PollingDiscover<R, M, Req>
+where
+    R: Resolve,
+    R::Target: Hash + Eq + Clone,
+    M: MakeService<R::Target, Req>,
+    R::Error: Into<crate::BoxError>,
+    M::MakeError: Into<crate::BoxError>,
+{
+    type Item = Result<Change<R::Target, M::Service>, crate::BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        loop {
+            if let Some(target) = this.removed.pop_front() {
+                this.current.remove(&target);
+                return Poll::Ready(Some(Ok(Change::Remove(target))));
+            }
+
+            if let Some((_, fut)) = this.making.as_mut() {
+                let result = futures_core::ready!(fut.as_mut().poll(cx));
+                let (target, _) = this.making.take().expect("service must be in progress");
+                match result {
+                    Ok(svc) => {
+                        this.current.insert(target.clone());
+                        return Poll::Ready(Some(Ok(Change::Insert(target, svc))));
+                    }
+                    Err(e) => {
+                        let error: crate::BoxError = e.into();
+                        debug!(%error, "failed to build service; retrying on the next lookup");
+                        continue;
+                    }
+                }
+            }
+
+            if !this.added.is_empty() {
+                match futures_core::ready!(this.make_service.poll_ready(cx)) {
+                    Ok(()) => {
+                        let target = this.added.pop_front().expect("target must be queued");
+                        let fut = this.make_service.make_service(target.clone());
+                        *this.making = Some((target, Box::pin(fut)));
+                        continue;
+                    }
+                    Err(e) => {
+                        let error: crate::BoxError = e.into();
+                        debug!(%error, "failed to build services; retrying on the next lookup");
+                        // The targets are added again by the next lookup.
+                        this.added.clear();
+                    }
+                }
+            }
+
+            if let Some(fut) = this.resolving.as_mut() {
+                let result = futures_core::ready!(fut.as_mut().poll(cx));
+                *this.resolving = None;
+                let targets = match result {
+                    Ok(targets) => targets.into_iter().collect::<HashSet<_>>(),
+                    Err(e) => {
+                        let error: crate::BoxError = e.into();
+                        debug!(%error, "failed to look up targets; keeping the current targets");
+                        continue;
+                    }
+                };
+                let current = &*this.current;
+                this.removed.extend(current.difference(&targets).cloned());
+                this.added
+                    .extend(targets.into_iter().filter(|t| !current.contains(t)));
+                trace!(
+                    added = this.added.len(),
+                    removed = this.removed.len(),
+                    "resolved targets"
+                );
+                continue;
+            }
+
+            let period = *this.period;
+            let interval = this.interval.get_or_insert_with(|| {
+                let mut interval = tokio::time::interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                interval
+            });
+            futures_core::ready!(interval.poll_tick(cx));
+            *this.resolving = Some(Box::pin(this.resolve.resolve()));
+        }
+    }
+}
+
+impl<R, M, Req> fmt::Debug for PollingDiscover<R, M, Req>
+where
+    R: Resolve + fmt::Debug,
+    R::Target: fmt::Debug,
+    M: MakeService<R::Target, Req> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollingDiscover")
+            .field("resolve", &self.resolve)
+            .field("make_service", &self.make_service)
+            .field("interval", &self.period)
+            .field("current", &self.current)
+            .finish()
+    }
+}
@@ -0,0 +1,138 @@
+//! Executors used to spawn background tasks.
+//!
+//! Some middleware, such as [`Batch`], [`Buffer`] and [`SpawnReady`], drive
+//! their inner service on a background task. These tasks are spawned with an
+//! [`Executor`]. With the `tokio-rt` feature, `TokioExecutor` spawns them on the
+//! current Tokio runtime, and is used by default; without it, an [`Executor`]
+//! must be provided, so that Tower doesn't depend on the Tokio runtime.
+//!
+//! With the `test-util` feature, [`TestExecutor`] only runs the spawned tasks
+//! when it is polled, so that tests can control when they make progress.
+//!
+//! [`Batch`]: crate::batch::Batch
+//! [`Buffer`]: crate::buffer::Buffer
+//! [`SpawnReady`]: crate::spawn_ready::SpawnReady
+
+#[cfg(feature = "test-util")]
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+#[cfg(feature = "test-util")]
+use std::{
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+/// A boxed future spawned by an [`Executor`].
+pub type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Spawns futures as background tasks.
+pub trait Executor {
+    /// Spawns `future`, running it to completion in the background.
+    fn spawn(&self, future: BoxFuture);
+}
+
+impl<E: Executor + ?Sized> Executor for &E {
+    fn spawn(&self, future: BoxFuture) {
+        (**self).spawn(future)
+    }
+}
+
+impl<E: Executor + ?Sized> Executor for Arc<E> {
+    fn spawn(&self, future: BoxFuture) {
+        (**self).spawn(future)
+    }
+}
+
+/// An [`Executor`] that spawns futures on the current Tokio runtime.
+///
+/// Spawning panics if called from outside of a Tokio runtime.
+#[cfg(feature = "tokio-rt")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioExecutor {
+    _p: (),
+}
+
+#[cfg(feature = "tokio-rt")]
+impl TokioExecutor {
+    /// Creates a new [`TokioExecutor`].
+    pub const fn new() -> Self {
+        TokioExecutor { _p: () }
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+impl Executor for TokioExecutor {
+    fn spawn(&self, future: BoxFuture) {
+        tokio::spawn(future);
+    }
+}
+
+/// An [`Executor`] that runs spawned futures only when it is polled.
+///
+/// This is intended for tests: it lets a test control exactly when background
+/// tasks make progress, and check how many tasks were spawned. Clones share
+/// the same set of tasks.
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+#[derive(Clone, Default)]
+pub struct TestExecutor {
+    tasks: Arc<Mutex<Vec<BoxFuture>>>,
+}
+
+#[cfg(feature = "test-util")]
+impl TestExecutor {
+    /// Creates a new [`TestExecutor`] without any tasks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of spawned tasks that have not completed.
+    pub fn len(&self) -> usize {
+        self.tasks.lock().expect("test executor poisoned").len()
+    }
+
+    /// Returns whether all spawned tasks have completed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Polls each spawned task once, dropping the tasks that complete.
+    ///
+    /// Returns [`Poll::Ready`] once all tasks have completed. Otherwise, the
+    /// pending tasks are woken with `cx`'s waker.
+    pub fn poll(&self, cx: &mut Context<'_>) -> Poll<()> {
+        // Tasks may spawn more tasks, so the lock isn't held while polling.
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().expect("test executor poisoned"));
+        tasks.retain_mut(|task| task.as_mut().poll(cx).is_pending());
+
+        let mut shared = self.tasks.lock().expect("test executor poisoned");
+        tasks.append(&mut shared);
+        *shared = tasks;
+        if shared.is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Executor for TestExecutor {
+    fn spawn(&self, future: BoxFuture) {
+        self.tasks
+            .lock()
+            .expect("test executor poisoned")
+            .push(future);
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl fmt::Debug for TestExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestExecutor")
+            .field("tasks", &self.len())
+            .finish()
+    }
+}
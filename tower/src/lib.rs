@@ -164,10 +164,24 @@
 pub(crate) mod macros;
 #[cfg(feature = "balance")]
 pub mod balance;
+#[cfg(feature = "batch")]
+pub mod batch;
 #[cfg(feature = "buffer")]
 pub mod buffer;
+#[cfg(feature = "cache")]
+pub mod cache;
 #[cfg(feature = "discover")]
 pub mod discover;
+#[cfg(any(
+    feature = "batch",
+    feature = "buffer",
+    feature = "cache",
+    feature = "spawn-ready",
+    feature = "test-util",
+    feature = "tokio-rt",
+    feature = "util"
+))]
+pub mod executor;
 #[cfg(feature = "filter")]
 pub mod filter;
 #[cfg(feature = "hedge")]
@@ -181,6 +195,8 @@ pub mod load_shed;
 
 #[cfg(feature = "make")]
 pub mod make;
+#[cfg(feature = "pool")]
+pub mod pool;
 #[cfg(feature = "ready-cache")]
 pub mod ready_cache;
 #[cfg(feature = "reconnect")]
@@ -196,6 +212,14 @@ pub mod timeout;
 #[cfg(feature = "util")]
 pub mod util;
 
+#[cfg(any(
+    feature = "batch",
+    feature = "buffer",
+    feature = "cache",
+    feature = "discover-util"
+))]
+mod time;
+
 pub mod builder;
 pub mod layer;
 
@@ -7,7 +7,7 @@ use futures_core::Stream;
 #[cfg(feature = "discover")]
 use std::{pin::Pin, task::ready};
 
-use super::Load;
+use super::{Load, Outstanding};
 use pin_project_lite::pin_project;
 use std::task::{Context, Poll};
 use tower_service::Service;
@@ -40,6 +40,16 @@ impl<T, M: Copy + PartialOrd> Load for Constant<T, M> {
     }
 }
 
+impl<T: Outstanding, M> Outstanding for Constant<T, M> {
+    fn outstanding(&self) -> usize {
+        self.inner.outstanding()
+    }
+
+    fn poll_drained(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.inner.poll_drained(cx)
+    }
+}
+
 impl<S, M, Request> Service<Request> for Constant<S, M>
 where
     S: Service<Request>,
@@ -11,8 +11,9 @@
 //! balance services depending on their load. Which load metric to use depends on your exact
 //! use-case, but the ones above should get you quite far!
 //!
-//! When the `discover` feature is enabled, wrapper types for [`Discover`] that
-//! wrap the discovered services with the given load estimator are also provided.
+//! Each load estimator also has a [`Layer`], such as [`PeakEwmaLayer`], which can be used to apply
+//! it along with other middleware. When the `discover` feature is enabled, wrapper types for
+//! [`Discover`] that wrap the discovered services with the given load estimator are also provided.
 //!
 //! # When does a request complete?
 //!
@@ -56,6 +57,7 @@
 //!
 //! [`tower::balance`]: crate::balance
 //! [`Discover`]: crate::discover::Discover
+//! [`Layer`]: crate::Layer
 //! [`CompleteOnResponse`]: crate::load::completion::CompleteOnResponse
 // TODO: a custom completion example would be good here
 
@@ -63,17 +65,20 @@ pub mod completion;
 mod constant;
 pub mod peak_ewma;
 pub mod pending_requests;
+mod tracker;
 
 pub use self::{
     completion::{CompleteOnResponse, TrackCompletion},
     constant::Constant,
-    peak_ewma::PeakEwma,
-    pending_requests::PendingRequests,
+    peak_ewma::{PeakEwma, PeakEwmaLayer},
+    pending_requests::{PendingRequests, PendingRequestsLayer},
 };
 
 #[cfg(feature = "discover")]
 pub use self::{peak_ewma::PeakEwmaDiscover, pending_requests::PendingRequestsDiscover};
 
+use std::task::{Context, Poll};
+
 /// Types that implement this trait can give an estimate of how loaded they are.
 ///
 /// See the module documentation for more details.
@@ -87,3 +92,21 @@ pub trait Load {
     /// Estimate the service's current load.
     fn load(&self) -> Self::Metric;
 }
+
+/// Load metrics that track each of their requests until it completes.
+///
+/// This allows users of a service, such as a draining [`Balance`], to tell
+/// when all of the requests dispatched to it have completed.
+///
+/// [`Balance`]: crate::balance::p2c::Balance
+pub trait Outstanding {
+    /// Returns the number of requests that have been dispatched to the service
+    /// and have not completed yet.
+    fn outstanding(&self) -> usize;
+
+    /// Returns `Poll::Ready` once no requests are outstanding.
+    ///
+    /// Otherwise, the current task is woken once the last outstanding request
+    /// completes.
+    fn poll_drained(&self, cx: &mut Context<'_>) -> Poll<()>;
+}
@@ -1,22 +1,24 @@
 //! A `Load` implementation that measures load using the PeakEWMA response latency.
 
 #[cfg(feature = "discover")]
-use crate::discover::{Change, Discover};
+use crate::discover::{Change, Discover, Layered};
 #[cfg(feature = "discover")]
 use futures_core::Stream;
 #[cfg(feature = "discover")]
 use pin_project_lite::pin_project;
 #[cfg(feature = "discover")]
-use std::{pin::Pin, task::ready};
+use std::pin::Pin;
 
 use super::completion::{CompleteOnResponse, TrackCompletion, TrackCompletionFuture};
-use super::Load;
+use super::tracker::{Tracked, Tracker};
+use super::{Load, Outstanding};
 use std::task::{Context, Poll};
 use std::{
     sync::{Arc, Mutex},
     time::Duration,
 };
 use tokio::time::Instant;
+use tower_layer::Layer;
 use tower_service::Service;
 use tracing::trace;
 
@@ -45,6 +47,7 @@ pub struct PeakEwma<S, C = CompleteOnResponse> {
     service: S,
     decay_ns: f64,
     rtt_estimate: Arc<Mutex<RttEstimate>>,
+    tracker: Tracker,
     completion: C,
 }
 
@@ -55,13 +58,23 @@ pin_project! {
     #[derive(Debug)]
     pub struct PeakEwmaDiscover<D, C = CompleteOnResponse> {
         #[pin]
-        discover: D,
-        decay_ns: f64,
-        default_rtt: Duration,
-        completion: C,
+        inner: Layered<D, PeakEwmaLayer<C>>,
     }
 }
 
+/// Wraps services with [`PeakEwma`].
+///
+/// This can be used with [`ServiceBuilder::discover`] to apply
+/// [`PeakEwma`] to discovered services along with other middleware.
+///
+/// [`ServiceBuilder::discover`]: crate::ServiceBuilder::discover
+#[derive(Clone, Debug)]
+pub struct PeakEwmaLayer<C = CompleteOnResponse> {
+    decay_ns: f64,
+    default_rtt: Duration,
+    completion: C,
+}
+
 /// Represents the relative cost of communicating with a service.
 ///
 /// The underlying value estimates the amount of pending work to a service: the Peak-EWMA
@@ -75,6 +88,8 @@ pub struct Handle {
     sent_at: Instant,
     decay_ns: f64,
     rtt_estimate: Arc<Mutex<RttEstimate>>,
+    // Dropped after the RTT estimate is updated.
+    _tracked: Tracked,
 }
 
 /// Holds the current RTT estimate and the last time this value was updated.
@@ -96,6 +111,7 @@ impl<S, C> PeakEwma<S, C> {
             service,
             decay_ns,
             rtt_estimate: Arc::new(Mutex::new(RttEstimate::new(nanos(default_rtt)))),
+            tracker: Tracker::default(),
             completion,
         }
     }
@@ -105,6 +121,7 @@ impl<S, C> PeakEwma<S, C> {
             decay_ns: self.decay_ns,
             sent_at: Instant::now(),
             rtt_estimate: self.rtt_estimate.clone(),
+            _tracked: self.tracker.track(),
         }
     }
 }
@@ -152,6 +169,16 @@ impl<S, C> Load for PeakEwma<S, C> {
     }
 }
 
+impl<S, C> Outstanding for PeakEwma<S, C> {
+    fn outstanding(&self) -> usize {
+        self.tracker.outstanding()
+    }
+
+    fn poll_drained(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.tracker.poll_idle(cx)
+    }
+}
+
 impl<S, C> PeakEwma<S, C> {
     fn update_estimate(&self) -> f64 {
         let mut rtt = self.rtt_estimate.lock().expect("peak ewma prior_estimate");
@@ -159,6 +186,37 @@ impl<S, C> PeakEwma<S, C> {
     }
 }
 
+// ===== impl PeakEwmaLayer =====
+
+impl<C> PeakEwmaLayer<C> {
+    /// Creates a layer that wraps services so that they have a [`PeakEwma`]
+    /// load metric.
+    ///
+    /// The provided `default_rtt` is used as the default RTT estimate for
+    /// wrapped services, and the `decay` value determines over what time period
+    /// a RTT estimate should decay.
+    pub fn new(default_rtt: Duration, decay: Duration, completion: C) -> Self {
+        PeakEwmaLayer {
+            decay_ns: nanos(decay),
+            default_rtt,
+            completion,
+        }
+    }
+}
+
+impl<S, C: Clone> Layer<S> for PeakEwmaLayer<C> {
+    type Service = PeakEwma<S, C>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        PeakEwma::new(
+            service,
+            self.default_rtt,
+            self.decay_ns,
+            self.completion.clone(),
+        )
+    }
+}
+
 // ===== impl PeakEwmaDiscover =====
 
 #[cfg(feature = "discover")]
@@ -177,10 +235,7 @@ impl<D, C> PeakEwmaDiscover<D, C> {
         C: TrackCompletion<Handle, <D::Service as Service<Request>>::Response>,
     {
         PeakEwmaDiscover {
-            discover,
-            decay_ns: nanos(decay),
-            default_rtt,
-            completion,
+            inner: Layered::new(discover, PeakEwmaLayer::new(default_rtt, decay, completion)),
         }
     }
 }
@@ -194,22 +249,7 @@ where
     type Item = Result<Change<D::Key, PeakEwma<D::Service, C>>, D::Error>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let this = self.project();
-        let change = match ready!(this.discover.poll_discover(cx)).transpose()? {
-            None => return Poll::Ready(None),
-            Some(Change::Remove(k)) => Change::Remove(k),
-            Some(Change::Insert(k, svc)) => {
-                let peak_ewma = PeakEwma::new(
-                    svc,
-                    *this.default_rtt,
-                    *this.decay_ns,
-                    this.completion.clone(),
-                );
-                Change::Insert(k, peak_ewma)
-            }
-        };
-
-        Poll::Ready(Some(Ok(change)))
+        self.project().inner.poll_next(cx)
     }
 }
 
@@ -1,32 +1,29 @@
 //! A [`Load`] implementation that measures load using the number of in-flight requests.
 
 #[cfg(feature = "discover")]
-use crate::discover::{Change, Discover};
+use crate::discover::{Change, Discover, Layered};
 #[cfg(feature = "discover")]
 use futures_core::Stream;
 #[cfg(feature = "discover")]
 use pin_project_lite::pin_project;
 #[cfg(feature = "discover")]
-use std::{pin::Pin, task::ready};
+use std::pin::Pin;
 
 use super::completion::{CompleteOnResponse, TrackCompletion, TrackCompletionFuture};
-use super::Load;
-use std::sync::Arc;
+use super::tracker::{Tracked, Tracker};
+use super::{Load, Outstanding};
 use std::task::{Context, Poll};
+use tower_layer::Layer;
 use tower_service::Service;
 
 /// Measures the load of the underlying service using the number of currently-pending requests.
 #[derive(Debug)]
 pub struct PendingRequests<S, C = CompleteOnResponse> {
     service: S,
-    ref_count: RefCount,
+    tracker: Tracker,
     completion: C,
 }
 
-/// Shared between instances of [`PendingRequests`] and [`Handle`] to track active references.
-#[derive(Clone, Debug, Default)]
-struct RefCount(Arc<()>);
-
 #[cfg(feature = "discover")]
 pin_project! {
     /// Wraps a `D`-typed stream of discovered services with [`PendingRequests`].
@@ -34,19 +31,29 @@ pin_project! {
     #[derive(Debug)]
     pub struct PendingRequestsDiscover<D, C = CompleteOnResponse> {
         #[pin]
-        discover: D,
-        completion: C,
+        inner: Layered<D, PendingRequestsLayer<C>>,
     }
 }
 
+/// Wraps services with [`PendingRequests`].
+///
+/// This can be used with [`ServiceBuilder::discover`] to apply
+/// [`PendingRequests`] to discovered services along with other middleware.
+///
+/// [`ServiceBuilder::discover`]: crate::ServiceBuilder::discover
+#[derive(Clone, Debug)]
+pub struct PendingRequestsLayer<C = CompleteOnResponse> {
+    completion: C,
+}
+
 /// Represents the number of currently-pending requests to a given service.
 #[derive(Clone, Copy, Debug, Default, PartialOrd, PartialEq, Ord, Eq)]
 pub struct Count(usize);
 
-/// Tracks an in-flight request by reference count.
+/// Tracks an in-flight request until it is dropped.
 #[derive(Debug)]
 #[allow(dead_code)]
-pub struct Handle(RefCount);
+pub struct Handle(Tracked);
 
 // ===== impl PendingRequests =====
 
@@ -56,12 +63,12 @@ impl<S, C> PendingRequests<S, C> {
         Self {
             service,
             completion,
-            ref_count: RefCount::default(),
+            tracker: Tracker::default(),
         }
     }
 
     fn handle(&self) -> Handle {
-        Handle(self.ref_count.clone())
+        Handle(self.tracker.track())
     }
 }
 
@@ -69,8 +76,17 @@ impl<S, C> Load for PendingRequests<S, C> {
     type Metric = Count;
 
     fn load(&self) -> Count {
-        // Count the number of references that aren't `self`.
-        Count(self.ref_count.ref_count() - 1)
+        Count(self.tracker.outstanding())
+    }
+}
+
+impl<S, C> Outstanding for PendingRequests<S, C> {
+    fn outstanding(&self) -> usize {
+        self.tracker.outstanding()
+    }
+
+    fn poll_drained(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.tracker.poll_idle(cx)
     }
 }
 
@@ -96,6 +112,24 @@ where
     }
 }
 
+// ===== impl PendingRequestsLayer =====
+
+impl<C> PendingRequestsLayer<C> {
+    /// Creates a layer that wraps services so that their load is tracked by
+    /// the number of pending requests.
+    pub const fn new(completion: C) -> Self {
+        Self { completion }
+    }
+}
+
+impl<S, C: Clone> Layer<S> for PendingRequestsLayer<C> {
+    type Service = PendingRequests<S, C>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        PendingRequests::new(service, self.completion.clone())
+    }
+}
+
 // ===== impl PendingRequestsDiscover =====
 
 #[cfg(feature = "discover")]
@@ -108,8 +142,7 @@ impl<D, C> PendingRequestsDiscover<D, C> {
         C: TrackCompletion<Handle, <D::Service as Service<Request>>::Response>,
     {
         Self {
-            discover,
-            completion,
+            inner: Layered::new(discover, PendingRequestsLayer::new(completion)),
         }
     }
 }
@@ -124,24 +157,7 @@ where
 
     /// Yields the next discovery change set.
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        use self::Change::*;
-
-        let this = self.project();
-        let change = match ready!(this.discover.poll_discover(cx)).transpose()? {
-            None => return Poll::Ready(None),
-            Some(Insert(k, svc)) => Insert(k, PendingRequests::new(svc, this.completion.clone())),
-            Some(Remove(k)) => Remove(k),
-        };
-
-        Poll::Ready(Some(Ok(change)))
-    }
-}
-
-// ==== RefCount ====
-
-impl RefCount {
-    pub(crate) fn ref_count(&self) -> usize {
-        Arc::strong_count(&self.0)
+        self.project().inner.poll_next(cx)
     }
 }
 
@@ -0,0 +1,76 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::task::{Context, Poll, Waker};
+
+/// Counts the outstanding requests of a service, and wakes a task once the
+/// last one completes.
+#[derive(Debug, Default)]
+pub(crate) struct Tracker {
+    shared: Arc<Shared>,
+}
+
+/// Tracks an outstanding request until it is dropped.
+#[derive(Debug)]
+pub(crate) struct Tracked {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    outstanding: AtomicUsize,
+    idle: Mutex<Option<Waker>>,
+}
+
+// ===== impl Tracker =====
+
+impl Tracker {
+    /// Tracks a new outstanding request.
+    pub(crate) fn track(&self) -> Tracked {
+        self.shared.outstanding.fetch_add(1, Ordering::AcqRel);
+        Tracked {
+            shared: self.shared.clone(),
+        }
+    }
+
+    pub(crate) fn outstanding(&self) -> usize {
+        self.shared.outstanding.load(Ordering::Acquire)
+    }
+
+    /// Returns `Poll::Ready` once no requests are outstanding.
+    pub(crate) fn poll_idle(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.outstanding() == 0 {
+            return Poll::Ready(());
+        }
+        *self.shared.lock() = Some(cx.waker().clone());
+        // The last request may have completed before the waker was stored.
+        if self.outstanding() == 0 {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+// ===== impl Tracked =====
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        if self.shared.outstanding.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let waker = self.shared.lock().take();
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// ===== impl Shared =====
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Waker>> {
+        self.idle
+            .lock()
+            .expect("outstanding request tracker poisoned")
+    }
+}
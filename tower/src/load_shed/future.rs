@@ -8,6 +8,7 @@ use std::task::{Context, Poll};
 use pin_project_lite::pin_project;
 
 use super::error::Overloaded;
+use super::priority::InFlight;
 
 pin_project! {
     /// Future for the [`LoadShed`] service.
@@ -24,7 +25,8 @@ pin_project! {
     enum ResponseState<F> {
         Called {
             #[pin]
-            fut: F
+            fut: F,
+            in_flight: Option<InFlight>,
         },
         Overloaded,
     }
@@ -33,7 +35,19 @@ pin_project! {
 impl<F> ResponseFuture<F> {
     pub(crate) fn called(fut: F) -> Self {
         ResponseFuture {
-            state: ResponseState::Called { fut },
+            state: ResponseState::Called {
+                fut,
+                in_flight: None,
+            },
+        }
+    }
+
+    pub(crate) fn tracked(fut: F, in_flight: InFlight) -> Self {
+        ResponseFuture {
+            state: ResponseState::Called {
+                fut,
+                in_flight: Some(in_flight),
+            },
         }
     }
 
@@ -53,7 +67,7 @@ where
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.project().state.project() {
-            ResponseStateProj::Called { fut } => fut.poll(cx).map_err(Into::into),
+            ResponseStateProj::Called { fut, .. } => fut.poll(cx).map_err(Into::into),
             ResponseStateProj::Overloaded => Poll::Ready(Err(Overloaded::new().into())),
         }
     }
@@ -1,7 +1,7 @@
 use std::fmt;
 use tower_layer::Layer;
 
-use super::LoadShed;
+use super::{LoadShed, PriorityLoadShed};
 
 /// A [`Layer`] to wrap services in [`LoadShed`] middleware.
 ///
@@ -31,3 +31,44 @@ impl fmt::Debug for LoadShedLayer {
         f.debug_struct("LoadShedLayer").finish()
     }
 }
+
+/// A [`Layer`] to wrap services in [`PriorityLoadShed`] middleware.
+///
+/// [`Layer`]: crate::Layer
+#[derive(Clone)]
+pub struct PriorityLoadShedLayer<F> {
+    classify: F,
+    max: usize,
+    reserved: usize,
+}
+
+impl<F> PriorityLoadShedLayer<F> {
+    /// Creates a new layer.
+    ///
+    /// See [`PriorityLoadShed::new`] for details on the arguments.
+    pub const fn new(classify: F, max: usize, reserved: usize) -> Self {
+        PriorityLoadShedLayer {
+            classify,
+            max,
+            reserved,
+        }
+    }
+}
+
+impl<S, F: Clone> Layer<S> for PriorityLoadShedLayer<F> {
+    type Service = PriorityLoadShed<S, F>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        PriorityLoadShed::new(service, self.classify.clone(), self.max, self.reserved)
+    }
+}
+
+impl<F> fmt::Debug for PriorityLoadShedLayer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PriorityLoadShedLayer")
+            .field("classify", &format_args!("{}", std::any::type_name::<F>()))
+            .field("max", &self.max)
+            .field("reserved", &self.reserved)
+            .finish()
+    }
+}
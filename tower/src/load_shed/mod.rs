@@ -1,4 +1,9 @@
 //! Middleware for shedding load when inner services aren't ready.
+//!
+//! [`LoadShed`] rejects every request that arrives while the inner service is
+//! not ready. [`PriorityLoadShed`] additionally classifies each request and
+//! keeps part of its capacity reserved for high-priority traffic, so that
+//! low-priority requests are shed first.
 
 use std::task::{Context, Poll};
 use tower_service::Service;
@@ -6,9 +11,11 @@ use tower_service::Service;
 pub mod error;
 pub mod future;
 mod layer;
+mod priority;
 
 use self::future::ResponseFuture;
-pub use self::layer::LoadShedLayer;
+pub use self::layer::{LoadShedLayer, PriorityLoadShedLayer};
+pub use self::priority::{Priority, PriorityLoadShed};
 
 /// A [`Service`] that sheds load when the inner service isn't ready.
 ///
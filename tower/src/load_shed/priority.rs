@@ -0,0 +1,203 @@
+//! Priority-aware load shedding.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+use super::future::ResponseFuture;
+
+/// The importance of a request, as determined by a [`PriorityLoadShed`]'s
+/// classifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// A request that may be shed as soon as the service comes under
+    /// pressure.
+    Low,
+    /// A request that may use the headroom reserved for important traffic.
+    High,
+}
+
+/// A [`Service`] that sheds low-priority requests first when under load.
+///
+/// Each request is classified with a `Fn(&Req) -> Priority` function. The
+/// shedder allows at most `max` requests to be in flight at once, of which
+/// `reserved` are only available to [`Priority::High`] requests. Once more
+/// than `max - reserved` requests are in flight, [`Priority::Low`] requests
+/// are rejected, and once `max` requests are in flight, every request is
+/// rejected.
+///
+/// Like [`LoadShed`], requests are also rejected if the inner service is not
+/// ready when they are called. Rejected requests fail with an
+/// [`Overloaded`] error.
+///
+/// Clones of a `PriorityLoadShed` share the same in-flight count.
+///
+/// [`Service`]: crate::Service
+/// [`LoadShed`]: crate::load_shed::LoadShed
+/// [`Overloaded`]: crate::load_shed::error::Overloaded
+pub struct PriorityLoadShed<S, F> {
+    inner: S,
+    classify: F,
+    is_ready: bool,
+    limits: Limits,
+    in_flight: Arc<AtomicUsize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    max: usize,
+    reserved: usize,
+}
+
+/// Tracks a request admitted by [`PriorityLoadShed`] until its response
+/// future is dropped.
+#[derive(Debug)]
+pub(crate) struct InFlight {
+    count: Arc<AtomicUsize>,
+}
+
+// ===== impl PriorityLoadShed =====
+
+impl<S, F> PriorityLoadShed<S, F> {
+    /// Wraps a service in [`PriorityLoadShed`] middleware.
+    ///
+    /// At most `max` requests may be in flight at once, and `reserved` of
+    /// those are kept for [`Priority::High`] requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reserved` is greater than `max`.
+    pub fn new(inner: S, classify: F, max: usize, reserved: usize) -> Self {
+        assert!(
+            reserved <= max,
+            "reserved headroom ({}) must not exceed the maximum in-flight requests ({})",
+            reserved,
+            max
+        );
+        PriorityLoadShed {
+            inner,
+            classify,
+            is_ready: false,
+            limits: Limits { max, reserved },
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Returns the number of requests currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Attempts to admit a request of the given priority, returning a guard
+    /// that releases its slot when dropped.
+    fn admit(&self, priority: Priority) -> Option<InFlight> {
+        let limit = match priority {
+            Priority::High => self.limits.max,
+            Priority::Low => self.limits.max - self.limits.reserved,
+        };
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                if n < limit {
+                    Some(n + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()?;
+        Some(InFlight {
+            count: self.in_flight.clone(),
+        })
+    }
+}
+
+impl<S, F, Req> Service<Req> for PriorityLoadShed<S, F>
+where
+    S: Service<Req>,
+    S::Error: Into<crate::BoxError>,
+    F: Fn(&Req) -> Priority,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.is_ready = match self.inner.poll_ready(cx) {
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+            r => r.is_ready(),
+        };
+
+        // As with `LoadShed`, always report ready: whether a request is shed
+        // depends on its priority, which isn't known until `call`.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        if !self.is_ready {
+            return ResponseFuture::overloaded();
+        }
+
+        let priority = (self.classify)(&req);
+        match self.admit(priority) {
+            Some(in_flight) => {
+                // readiness only counts once, you need to check again!
+                self.is_ready = false;
+                ResponseFuture::tracked(self.inner.call(req), in_flight)
+            }
+            None => ResponseFuture::overloaded(),
+        }
+    }
+}
+
+impl<S: Clone, F: Clone> Clone for PriorityLoadShed<S, F> {
+    fn clone(&self) -> Self {
+        PriorityLoadShed {
+            inner: self.inner.clone(),
+            classify: self.classify.clone(),
+            // new clones shouldn't carry the readiness state, as a cloneable
+            // inner service likely tracks readiness per clone.
+            is_ready: false,
+            limits: self.limits,
+            in_flight: self.in_flight.clone(),
+        }
+    }
+}
+
+impl<S, F> fmt::Debug for PriorityLoadShed<S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PriorityLoadShed")
+            .field("inner", &self.inner)
+            .field("classify", &format_args!("{}", std::any::type_name::<F>()))
+            .field("max", &self.limits.max)
+            .field("reserved", &self.limits.reserved)
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+// ===== impl InFlight =====
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::AcqRel);
+    }
+}
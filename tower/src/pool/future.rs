@@ -0,0 +1,76 @@
+//! Future types for the [`Pool`] middleware.
+//!
+//! [`Pool`]: crate::pool::Pool
+
+use super::InFlight;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+pin_project! {
+    /// Future that resolves to the response of a pooled connection, or to the
+    /// error of a failed connection attempt.
+    #[derive(Debug)]
+    pub struct ResponseFuture<F> {
+        #[pin]
+        state: State<F>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    #[derive(Debug)]
+    enum State<F> {
+        Called {
+            #[pin]
+            fut: F,
+            // Released once the response completes, so that the connection
+            // may become idle.
+            in_flight: Option<InFlight>,
+        },
+        Failed {
+            error: Option<crate::BoxError>,
+        },
+    }
+}
+
+impl<F> ResponseFuture<F> {
+    pub(crate) fn called(fut: F, in_flight: InFlight) -> Self {
+        ResponseFuture {
+            state: State::Called {
+                fut,
+                in_flight: Some(in_flight),
+            },
+        }
+    }
+
+    pub(crate) fn failed(error: crate::BoxError) -> Self {
+        ResponseFuture {
+            state: State::Failed { error: Some(error) },
+        }
+    }
+}
+
+impl<F, T, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<crate::BoxError>,
+{
+    type Output = Result<T, crate::BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            StateProj::Called { fut, in_flight } => {
+                let result = ready!(fut.poll(cx));
+                in_flight.take();
+                Poll::Ready(result.map_err(Into::into))
+            }
+            StateProj::Failed { error } => {
+                Poll::Ready(Err(error.take().expect("polled after error")))
+            }
+        }
+    }
+}
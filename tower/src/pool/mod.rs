@@ -0,0 +1,589 @@
+//! A pool of interchangeable connections to the same target.
+//!
+//! [`Reconnect`] maintains a single connection to a target, and [`Balance`]
+//! distributes requests across many different endpoints. [`Pool`] sits
+//! in between: it uses a [`MakeService`] to maintain a number of connections
+//! to the *same* target, and dispatches each request to whichever connection
+//! is ready to handle it. This is useful for protocols where a connection can
+//! only handle a limited number of concurrent requests, such as HTTP/1.1.
+//!
+//! The pool creates connections lazily: a new connection is only created when
+//! none of the existing connections is ready to handle a request, up to a
+//! configured maximum. Connections that have not been used for a configured
+//! idle timeout are closed, down to a configured minimum. A connection is
+//! never closed while one of its responses is still in flight.
+//!
+//! The pool has no background task: connections are only opened and closed
+//! while the pool is polled for readiness. When an idle timeout is
+//! configured, the task that last polled the pool is woken once a connection
+//! becomes idle, so that it can be closed.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(feature = "util")]
+//! # async fn wrapper() -> Result<(), tower::BoxError> {
+//! use std::time::Duration;
+//! use tower::pool::Builder;
+//! use tower::{service_fn, ServiceExt};
+//!
+//! // A `MakeService` that "connects" to a target.
+//! let connect = service_fn(|target: &'static str| async move {
+//!     Ok::<_, tower::BoxError>(service_fn(move |req: String| async move {
+//!         Ok::<_, tower::BoxError>(format!("{}: {}", target, req))
+//!     }))
+//! });
+//!
+//! let pool = Builder::new()
+//!     .min_connections(1)
+//!     .max_connections(8)
+//!     .idle_timeout(Duration::from_secs(90))
+//!     .build(connect, "example.com");
+//!
+//! let rsp = pool.oneshot("hello".to_string()).await?;
+//! assert_eq!(rsp, "example.com: hello");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Reconnect`]: crate::reconnect::Reconnect
+//! [`Balance`]: crate::balance::p2c::Balance
+//! [`MakeService`]: crate::make::MakeService
+
+pub mod future;
+
+use self::future::ResponseFuture;
+use crate::make::MakeService;
+use crate::ready_cache::{error::Failed, ReadyCache};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+use tower_service::Service;
+use tracing::{debug, trace};
+
+/// Configures and builds a [`Pool`].
+#[derive(Debug, Clone)]
+pub struct Builder {
+    min: usize,
+    max: usize,
+    idle_timeout: Option<Duration>,
+}
+
+/// A pool of connections to the same target, created with a [`MakeService`].
+///
+/// See the [module-level documentation](crate::pool) for details.
+///
+/// [`MakeService`]: crate::make::MakeService
+pub struct Pool<MS, Target, Req>
+where
+    MS: MakeService<Target, Req>,
+{
+    maker: MS,
+    target: Target,
+    config: Builder,
+
+    /// The connection currently being established, if any.
+    making: Option<Pin<Box<MS::Future>>>,
+    /// A newly established connection that hasn't become ready yet.
+    ///
+    /// No further connections are made while this connection is pending, so
+    /// that a burst of requests doesn't open many connections at once.
+    fresh: Option<usize>,
+
+    services: ReadyCache<usize, MS::Service, Req>,
+    /// When each connection was last used, and its requests in flight.
+    usage: Arc<Usage>,
+    /// Fires when the next connection becomes idle.
+    idle_timer: Option<Pin<Box<Sleep>>>,
+    next_id: usize,
+    /// The connection selected to handle the next request.
+    ready_key: Option<usize>,
+    /// The error of a failed connection attempt, which is returned by the
+    /// next call.
+    error: Option<crate::BoxError>,
+}
+
+/// The usage of each connection of a pool.
+///
+/// This is shared with the response futures, so that a connection isn't
+/// considered idle until its responses have completed.
+#[derive(Debug, Default)]
+struct Usage {
+    state: Mutex<UsageState>,
+}
+
+#[derive(Debug, Default)]
+struct UsageState {
+    connections: HashMap<usize, Connection>,
+    /// The task to wake when a connection's last request completes, so that
+    /// it may be closed once it becomes idle.
+    waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+struct Connection {
+    last_used: Instant,
+    in_flight: usize,
+}
+
+/// Marks a request as in flight on a connection until it is dropped.
+#[derive(Debug)]
+pub(crate) struct InFlight {
+    usage: Arc<Usage>,
+    key: usize,
+}
+
+// ===== impl Builder =====
+
+impl Builder {
+    /// Returns a new builder with the default configuration.
+    ///
+    /// By default, a pool keeps no connections open when it isn't used, opens
+    /// at most 16 connections, and never closes idle connections.
+    pub fn new() -> Self {
+        Builder {
+            min: 0,
+            max: 16,
+            idle_timeout: None,
+        }
+    }
+
+    /// Sets the number of connections the pool keeps open, even if they are
+    /// idle.
+    pub fn min_connections(mut self, min: usize) -> Self {
+        self.min = min;
+        self
+    }
+
+    /// Sets the maximum number of connections the pool opens.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Closes connections that have not been used for `timeout`.
+    ///
+    /// A connection is used until its responses complete, so a connection
+    /// with a response in flight is never closed.
+    ///
+    /// Idle connections are closed while the pool is polled for readiness,
+    /// but only while more than the minimum number of connections are open.
+    /// The task that last polled the pool is woken when the next connection
+    /// becomes idle.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Builds a [`Pool`] of connections to `target`, created with `maker`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the maximum number of connections is zero, or less than the
+    /// minimum number of connections.
+    pub fn build<MS, Target, Req>(&self, maker: MS, target: Target) -> Pool<MS, Target, Req>
+    where
+        MS: MakeService<Target, Req>,
+        MS::Error: Into<crate::BoxError>,
+    {
+        assert!(self.max > 0, "pool must allow at least one connection");
+        assert!(
+            self.min <= self.max,
+            "minimum connections ({}) must not exceed maximum connections ({})",
+            self.min,
+            self.max
+        );
+        Pool {
+            maker,
+            target,
+            config: self.clone(),
+            making: None,
+            fresh: None,
+            services: ReadyCache::default(),
+            usage: Arc::default(),
+            idle_timer: None,
+            next_id: 0,
+            ready_key: None,
+            error: None,
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ===== impl Pool =====
+
+impl<MS, Target, Req> Pool<MS, Target, Req>
+where
+    MS: MakeService<Target, Req>,
+    MS::Error: Into<crate::BoxError>,
+{
+    /// Creates a pool of connections to `target` with the default
+    /// configuration.
+    ///
+    /// See [`Builder`] to configure the pool.
+    pub fn new(maker: MS, target: Target) -> Self {
+        Builder::new().build(maker, target)
+    }
+
+    /// Returns the number of open connections.
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    /// Returns whether the pool has no open connections.
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// Drives pending connections to readiness, dropping connections that
+    /// failed.
+    fn promote_pending(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(Failed(key, error))) => {
+                    debug!(%error, connection = key, "dropping failed connection");
+                    self.usage.remove(key);
+                }
+            }
+        }
+
+        // Once the newest connection is no longer pending, it has either
+        // become ready or failed, and further connections may be opened.
+        if let Some(key) = self.fresh {
+            if !self.services.pending_contains(&key) {
+                self.fresh = None;
+            }
+        }
+    }
+
+    /// Returns the ready connections that may be closed when idle.
+    fn evictable(&self) -> impl Iterator<Item = usize> + '_ {
+        self.services
+            .iter_ready()
+            .map(|(key, _)| *key)
+            .filter(move |key| Some(*key) != self.ready_key)
+    }
+
+    /// Closes ready connections that have been idle for longer than the idle
+    /// timeout, while more than the minimum number of connections are open.
+    ///
+    /// The idle timer is then reset to fire when the next connection becomes
+    /// idle. Connections with requests in flight are not idle, and wake the
+    /// pool when their last request completes instead.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) {
+        let timeout = match self.config.idle_timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        self.usage.register(cx);
+
+        loop {
+            let now = Instant::now();
+            let idle = {
+                let usage = self.usage.lock();
+                self.evictable()
+                    .filter(|key| {
+                        usage.idle_since(*key).map_or(false, |since| {
+                            now.saturating_duration_since(since) >= timeout
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            for key in idle {
+                if self.services.len() <= self.config.min {
+                    break;
+                }
+                trace!(connection = key, "closing idle connection");
+                self.services.evict(&key);
+                self.usage.remove(key);
+            }
+
+            let next = if self.services.len() > self.config.min {
+                let usage = self.usage.lock();
+                self.evictable()
+                    .filter_map(|key| usage.idle_since(key))
+                    .filter_map(|since| since.checked_add(timeout))
+                    .min()
+            } else {
+                None
+            };
+            let deadline = match next {
+                Some(deadline) => deadline,
+                None => {
+                    self.idle_timer = None;
+                    return;
+                }
+            };
+
+            let timer = match self.idle_timer.as_mut() {
+                Some(timer) => {
+                    timer.as_mut().reset(deadline);
+                    timer
+                }
+                None => self
+                    .idle_timer
+                    .insert(Box::pin(tokio::time::sleep_until(deadline))),
+            };
+            if timer.as_mut().poll(cx).is_pending() {
+                return;
+            }
+        }
+    }
+
+    /// Selects the ready connection that was used most recently, so that load
+    /// is concentrated on as few connections as possible and the others may
+    /// become idle.
+    fn select_ready(&self) -> Option<usize> {
+        let usage = self.usage.lock();
+        self.services
+            .iter_ready()
+            .map(|(key, _)| *key)
+            .max_by_key(|key| (usage.last_used(*key), *key))
+    }
+
+    /// Attempts to open a new connection.
+    ///
+    /// Returns `Ready(Ok(true))` if a new connection was added to the pool,
+    /// and `Ready(Ok(false))` if no connection may be opened right now.
+    ///
+    /// Unless fewer than the minimum number of connections are open, no
+    /// connection is opened while the newest one is still becoming ready.
+    fn poll_connect(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, crate::BoxError>>
+    where
+        MS::MakeError: Into<crate::BoxError>,
+        Target: Clone,
+    {
+        if self.making.is_none() {
+            let len = self.services.len();
+            if len >= self.config.max || (self.fresh.is_some() && len >= self.config.min) {
+                return Poll::Ready(Ok(false));
+            }
+
+            match self.maker.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+            trace!(connections = self.services.len(), "opening new connection");
+            let fut = self.maker.make_service(self.target.clone());
+            self.making = Some(Box::pin(fut));
+        }
+
+        let making = self
+            .making
+            .as_mut()
+            .expect("connection must be in progress");
+        let result = match making.as_mut().poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.making = None;
+
+        match result {
+            Ok(svc) => {
+                let key = self.next_id;
+                self.next_id = self.next_id.wrapping_add(1);
+                debug!(connection = key, "connection established");
+                self.services.push(key, svc);
+                self.usage.insert(key);
+                self.fresh = Some(key);
+                Poll::Ready(Ok(true))
+            }
+            Err(e) => {
+                let error = e.into();
+                debug!(%error, "failed to open connection");
+                Poll::Ready(Err(error))
+            }
+        }
+    }
+}
+
+impl<MS, Target, Req> Service<Req> for Pool<MS, Target, Req>
+where
+    MS: MakeService<Target, Req>,
+    MS::Error: Into<crate::BoxError>,
+    MS::MakeError: Into<crate::BoxError>,
+    Target: Clone,
+{
+    type Response = MS::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<<MS::Service as Service<Req>>::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.error.is_some() {
+            // The failed connection attempt must be reported by `call` first.
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            self.promote_pending(cx);
+            self.poll_idle(cx);
+
+            // If a connection has already been selected, ensure that it is
+            // still ready.
+            if let Some(key) = self.ready_key.take() {
+                match self.services.check_ready(cx, &key) {
+                    Ok(true) => {
+                        self.ready_key = Some(key);
+
+                        // Keep the minimum number of connections open.
+                        while self.services.len() < self.config.min {
+                            match self.poll_connect(cx) {
+                                Poll::Ready(Ok(true)) => {}
+                                Poll::Ready(Ok(false)) | Poll::Pending => break,
+                                Poll::Ready(Err(error)) => {
+                                    debug!(%error, "failed to open minimum connections");
+                                    break;
+                                }
+                            }
+                        }
+                        return Poll::Ready(Ok(()));
+                    }
+                    Ok(false) => trace!(connection = key, "connection became unavailable"),
+                    Err(Failed(key, error)) => {
+                        debug!(%error, connection = key, "dropping failed connection");
+                        self.usage.remove(key);
+                    }
+                }
+            }
+
+            if let Some(key) = self.select_ready() {
+                self.ready_key = Some(key);
+                continue;
+            }
+
+            // No connection is ready, so open a new one if possible.
+            match self.poll_connect(cx) {
+                Poll::Ready(Ok(true)) => continue,
+                Poll::Ready(Ok(false)) | Poll::Pending => {
+                    trace!(
+                        connections = self.services.len(),
+                        "waiting for a connection to become ready"
+                    );
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err(error)) => {
+                    // Report the error from `call`, so that the pool remains
+                    // usable for subsequent requests.
+                    self.error = Some(error);
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        if let Some(error) = self.error.take() {
+            return ResponseFuture::failed(error);
+        }
+
+        let key = self
+            .ready_key
+            .take()
+            .expect("service not ready; poll_ready must be called first");
+        let in_flight = self.usage.start(key);
+        ResponseFuture::called(self.services.call_ready(&key, req), in_flight)
+    }
+}
+
+// ===== impl Usage =====
+
+impl Usage {
+    fn lock(&self) -> MutexGuard<'_, UsageState> {
+        self.state.lock().expect("pool usage poisoned")
+    }
+
+    /// Records a new connection, which is considered used when it's opened.
+    fn insert(&self, key: usize) {
+        let connection = Connection {
+            last_used: Instant::now(),
+            in_flight: 0,
+        };
+        self.lock().connections.insert(key, connection);
+    }
+
+    fn remove(&self, key: usize) {
+        self.lock().connections.remove(&key);
+    }
+
+    /// Records that a request is dispatched to the given connection.
+    fn start(self: &Arc<Self>, key: usize) -> InFlight {
+        if let Some(connection) = self.lock().connections.get_mut(&key) {
+            connection.last_used = Instant::now();
+            connection.in_flight += 1;
+        }
+        InFlight {
+            usage: self.clone(),
+            key,
+        }
+    }
+
+    fn register(&self, cx: &mut Context<'_>) {
+        let mut state = self.lock();
+        match state.waker {
+            Some(ref waker) if waker.will_wake(cx.waker()) => {}
+            _ => state.waker = Some(cx.waker().clone()),
+        }
+    }
+}
+
+impl UsageState {
+    fn last_used(&self, key: usize) -> Option<Instant> {
+        self.connections.get(&key).map(|c| c.last_used)
+    }
+
+    /// Returns when the given connection became idle, or `None` if it has
+    /// requests in flight.
+    fn idle_since(&self, key: usize) -> Option<Instant> {
+        self.connections
+            .get(&key)
+            .filter(|c| c.in_flight == 0)
+            .map(|c| c.last_used)
+    }
+}
+
+// ===== impl InFlight =====
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut state = self.usage.lock();
+        // The connection may already have failed.
+        if let Some(connection) = state.connections.get_mut(&self.key) {
+            connection.last_used = Instant::now();
+            connection.in_flight -= 1;
+            if connection.in_flight == 0 {
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+}
+
+impl<MS, Target, Req> fmt::Debug for Pool<MS, Target, Req>
+where
+    MS: MakeService<Target, Req> + fmt::Debug,
+    MS::Service: fmt::Debug,
+    Target: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("maker", &self.maker)
+            .field("target", &self.target)
+            .field("config", &self.config)
+            .field("services", &self.services)
+            .finish()
+    }
+}
@@ -49,6 +49,11 @@ use tracing::{debug, trace};
 /// though the service may not be dropped (if it is currently pending) until
 /// [`ReadyCache::poll_pending`] is invoked.
 ///
+/// Pushing a service with the key of a service that is already in the cache
+/// replaces the existing service. The replacement is atomic: the existing
+/// service keeps being used, and is driven to readiness again after each call,
+/// until the replacement becomes ready.
+///
 /// Note that the by-index accessors are provided to support use cases (like
 /// power-of-two-choices load balancing) where the caller does not care to keep
 /// track of each service's key. Instead, it needs only to access _some_ ready
@@ -63,6 +68,12 @@ where
     pending: FuturesUnordered<Pending<K, S, Req>>,
     /// An index of cancelation handles for pending streams.
     pending_cancel_txs: IndexMap<K, CancelTx>,
+    /// An index of cancelation handles for pending services that are being
+    /// replaced by a newer service with the same key.
+    ///
+    /// Such a service is only added back to the ready set while its
+    /// replacement is still pending.
+    replaced_cancel_txs: IndexMap<K, CancelTx>,
 
     /// Services that have previously become ready. Readiness can become stale,
     /// so a given service should be polled immediately before use.
@@ -93,7 +104,7 @@ type CancelPair = (CancelTx, CancelRx);
 #[derive(Debug)]
 enum PendingError<K, E> {
     Canceled(K),
-    Inner(K, E),
+    Inner(K, E, CancelRx),
 }
 
 pin_project_lite::pin_project! {
@@ -120,6 +131,7 @@ where
             ready: IndexMap::default(),
             pending: FuturesUnordered::new(),
             pending_cancel_txs: IndexMap::default(),
+            replaced_cancel_txs: IndexMap::default(),
         }
     }
 }
@@ -133,11 +145,13 @@ where
         let Self {
             pending,
             pending_cancel_txs,
+            replaced_cancel_txs,
             ready,
         } = self;
         f.debug_struct("ReadyCache")
             .field("pending", pending)
             .field("pending_cancel_txs", pending_cancel_txs)
+            .field("replaced_cancel_txs", replaced_cancel_txs)
             .field("ready", ready)
             .finish()
     }
@@ -214,7 +228,13 @@ where
     /// Services are dropped from the ready set immediately. Services in the
     /// pending set are marked for cancellation, but [`ReadyCache::poll_pending`]
     /// must be called to cause the service to be dropped.
+    ///
+    /// If the service is being replaced, both the existing service and its
+    /// replacement are evicted.
     pub fn evict<Q: Hash + Equivalent<K>>(&mut self, key: &Q) -> bool {
+        if let Some(c) = self.replaced_cancel_txs.swap_remove(key) {
+            c.cancel();
+        }
         let canceled = if let Some(c) = self.pending_cancel_txs.swap_remove(key) {
             c.cancel();
             true
@@ -236,14 +256,54 @@ where
     <S as Service<Req>>::Error: Into<crate::BoxError>,
     S::Error: Into<crate::BoxError>,
 {
+    /// Removes all services with the given key from the cache, and returns
+    /// them.
+    ///
+    /// Unlike [`ReadyCache::evict`], this also takes services out of the
+    /// pending set immediately, so that callers may keep using services that
+    /// are still processing requests (e.g. to drain them gracefully). If the
+    /// service is being replaced, both the existing service and its
+    /// replacement are returned.
+    pub fn remove<Q: Hash + Equivalent<K>>(&mut self, key: &Q) -> Vec<S> {
+        let mut removed = Vec::new();
+        if let Some((_, _, (svc, _))) = self.ready.swap_remove_full(key) {
+            removed.push(svc);
+        }
+        if self.pending_cancel_txs.contains_key(key) || self.replaced_cancel_txs.contains_key(key) {
+            for pending in Pin::new(&mut self.pending).iter_pin_mut() {
+                let pending = pending.project();
+                let matches = pending.key.as_ref().map_or(false, |k| key.equivalent(k));
+                let canceled = pending
+                    .cancel
+                    .as_ref()
+                    .map_or(true, |CancelRx(c)| c.canceled.load(Ordering::SeqCst));
+                if matches && !canceled {
+                    removed.extend(pending.ready.take());
+                }
+            }
+        }
+        // The pending futures whose services were taken are canceled, so that
+        // they are dropped by `poll_pending` without being polled again.
+        if let Some(c) = self.replaced_cancel_txs.swap_remove(key) {
+            c.cancel();
+        }
+        if let Some(c) = self.pending_cancel_txs.swap_remove(key) {
+            c.cancel();
+        }
+        removed
+    }
+
     /// Pushes a new service onto the pending set.
     ///
     /// The service will be promoted to the ready set as [`poll_pending`] is invoked.
     ///
-    /// Note that this does **not** remove services from the ready set. Once the
-    /// old service is used, it will be dropped instead of being added back to
-    /// the pending set; OR, when the new service becomes ready, it will replace
-    /// the prior service in the ready set.
+    /// If there is already a service with the same key, the new service
+    /// replaces it once the new service becomes ready. Until then, the existing
+    /// service remains in the cache: it stays in the ready set, or is promoted
+    /// to it, and is driven to readiness again after it is called, so that the
+    /// key remains usable while its replacement is pending. If the replacement
+    /// fails before becoming ready, it is dropped without being reported by
+    /// [`poll_pending`], and the existing service is kept.
     ///
     /// [`poll_pending`]: crate::ready_cache::cache::ReadyCache::poll_pending
     pub fn push(&mut self, key: K, svc: S) {
@@ -253,8 +313,12 @@ where
 
     fn push_pending(&mut self, key: K, svc: S, (cancel_tx, cancel_rx): CancelPair) {
         if let Some(c) = self.pending_cancel_txs.insert(key.clone(), cancel_tx) {
-            // If there is already a service for this key, cancel it.
-            c.cancel();
+            // If there is already a pending service for this key, it is being
+            // replaced, but remains usable until the new service is ready.
+            // Any service it was itself replacing is no longer needed.
+            if let Some(c) = self.replaced_cancel_txs.insert(key.clone(), c) {
+                c.cancel();
+            }
         }
         self.pending.push(Pending {
             key: Some(key),
@@ -264,6 +328,26 @@ where
         });
     }
 
+    /// Adds a service that was in the ready set back to the pending set.
+    fn repend(&mut self, key: K, svc: S, (cancel_tx, cancel_rx): CancelPair) {
+        if self.pending_contains(&key) {
+            // A new version of this service has been added to the pending set,
+            // so this one is being replaced, but remains usable until the new
+            // version becomes ready.
+            if let Some(c) = self.replaced_cancel_txs.insert(key.clone(), cancel_tx) {
+                c.cancel();
+            }
+            self.pending.push(Pending {
+                key: Some(key),
+                cancel: Some(cancel_rx),
+                ready: Some(svc),
+                _pd: std::marker::PhantomData,
+            });
+        } else {
+            self.push_pending(key, svc, (cancel_tx, cancel_rx));
+        }
+    }
+
     /// Polls services pending readiness, adding ready services to the ready set.
     ///
     /// Returns [`Poll::Ready`] when there are no remaining unready services.
@@ -284,17 +368,26 @@ where
                 Poll::Pending => return Poll::Pending,
                 Poll::Ready(None) => return Poll::Ready(Ok(())),
                 Poll::Ready(Some(Ok((key, svc, cancel_rx)))) => {
-                    trace!("endpoint ready");
-                    let cancel_tx = self.pending_cancel_txs.swap_remove(&key);
-                    if let Some(cancel_tx) = cancel_tx {
+                    if let Some(cancel_tx) =
+                        take_cancel(&mut self.pending_cancel_txs, &key, &cancel_rx)
+                    {
+                        trace!("endpoint ready");
+                        // The service replaces any previous version.
+                        if let Some(c) = self.replaced_cancel_txs.swap_remove(&key) {
+                            c.cancel();
+                        }
                         // Keep track of the cancelation so that it need not be
                         // recreated after the service is used.
                         self.ready.insert(key, (svc, (cancel_tx, cancel_rx)));
                     } else {
-                        assert!(
-                            cancel_tx.is_some(),
-                            "services that become ready must have a pending cancelation"
-                        );
+                        let cancel_tx =
+                            take_cancel(&mut self.replaced_cancel_txs, &key, &cancel_rx).expect(
+                                "services that become ready must have a pending cancelation",
+                            );
+                        trace!("replaced endpoint ready");
+                        // The replacement must still be pending, since it would
+                        // have canceled this service once it became ready.
+                        self.ready.insert(key, (svc, (cancel_tx, cancel_rx)));
                     }
                 }
                 Poll::Ready(Some(Err(PendingError::Canceled(_)))) => {
@@ -302,13 +395,29 @@ where
                     // The cancellation for this service was removed in order to
                     // cause this cancellation.
                 }
-                Poll::Ready(Some(Err(PendingError::Inner(key, e)))) => {
-                    let cancel_tx = self.pending_cancel_txs.swap_remove(&key);
+                Poll::Ready(Some(Err(PendingError::Inner(key, e, cancel_rx)))) => {
+                    if take_cancel(&mut self.replaced_cancel_txs, &key, &cancel_rx).is_some() {
+                        // The replacement is still pending, so the key remains
+                        // in the cache.
+                        let e: crate::BoxError = e.into();
+                        debug!(error = %e, "replaced endpoint failed");
+                        continue;
+                    }
+
+                    let cancel_tx = take_cancel(&mut self.pending_cancel_txs, &key, &cancel_rx);
                     assert!(
                         cancel_tx.is_some(),
                         "services that return an error must have a pending cancelation"
                     );
-                    return Err(error::Failed(key, e.into())).into();
+                    // The service that was being replaced, if any, is kept in
+                    // place of its failed replacement.
+                    if let Some(c) = self.replaced_cancel_txs.swap_remove(&key) {
+                        self.pending_cancel_txs.insert(key, c);
+                    } else if !self.ready.contains_key(&key) {
+                        return Err(error::Failed(key, e.into())).into();
+                    }
+                    let e: crate::BoxError = e.into();
+                    debug!(error = %e, "replacement endpoint failed");
                 }
             }
         }
@@ -352,12 +461,7 @@ where
                     .ready
                     .swap_remove_index(index)
                     .expect("invalid ready index");
-
-                // If a new version of this service has been added to the
-                // unready set, don't overwrite it.
-                if !self.pending_contains(&key) {
-                    self.push_pending(key, svc, cancel);
-                }
+                self.repend(key, svc, cancel);
 
                 Ok(false)
             }
@@ -397,12 +501,7 @@ where
             .expect("check_ready_index was not called");
 
         let fut = svc.call(req);
-
-        // If a new version of this service has been added to the
-        // unready set, don't overwrite it.
-        if !self.pending_contains(&key) {
-            self.push_pending(key, svc, cancel);
-        }
+        self.repend(key, svc, cancel);
 
         fut
     }
@@ -430,6 +529,19 @@ impl CancelTx {
     }
 }
 
+/// Removes the cancelation handle for `key` from `txs`, if it belongs to the
+/// pending service with the cancelation receiver `rx`.
+fn take_cancel<K: Hash + Eq>(
+    txs: &mut IndexMap<K, CancelTx>,
+    key: &K,
+    CancelRx(rx): &CancelRx,
+) -> Option<CancelTx> {
+    match txs.get(key) {
+        Some(CancelTx(tx)) if Arc::ptr_eq(tx, rx) => txs.swap_remove(key),
+        _ => None,
+    }
+}
+
 // === Pending ===
 
 impl<K, S, Req> Future for Pending<K, S, Req>
@@ -476,7 +588,8 @@ where
             }
             Poll::Ready(Err(e)) => {
                 let key = this.key.take().expect("polled after compete");
-                Err(PendingError::Inner(key, e)).into()
+                let cancel = this.cancel.take().expect("polled after complete");
+                Err(PendingError::Inner(key, e, cancel)).into()
             }
         }
     }
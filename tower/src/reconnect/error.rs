@@ -0,0 +1,91 @@
+//! Error types for the [`Reconnect`] middleware.
+//!
+//! [`Reconnect`]: crate::reconnect::Reconnect
+
+use crate::BoxError;
+use std::{fmt, sync::Arc};
+
+/// An error returned by [`Reconnect`] once it has given up on connecting,
+/// after the configured maximum number of consecutive connection attempts
+/// failed.
+///
+/// [`Reconnect`]: crate::reconnect::Reconnect
+#[derive(Debug, Clone)]
+pub struct Exhausted {
+    attempts: usize,
+    source: Arc<BoxError>,
+}
+
+impl Exhausted {
+    pub(crate) fn new(attempts: usize, source: BoxError) -> Self {
+        Exhausted {
+            attempts,
+            source: Arc::new(source),
+        }
+    }
+
+    /// Returns the number of consecutive connection attempts that failed.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+}
+
+impl fmt::Display for Exhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gave up connecting after {} failed attempts: {}",
+            self.attempts, self.source
+        )
+    }
+}
+
+impl std::error::Error for Exhausted {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&**self.source)
+    }
+}
+
+/// An error returned by [`Reconnect`] when a connection attempt fails after an
+/// established connection was lost.
+///
+/// The error of the failed connection attempt is this error's [`source`], and
+/// the error that caused the previous connection to be lost is available
+/// through [`ReconnectFailed::disconnect_cause`]. Only errors of connection
+/// attempts are wrapped: errors returned by a connected service are passed
+/// through unchanged.
+///
+/// [`Reconnect`]: crate::reconnect::Reconnect
+/// [`source`]: std::error::Error::source
+#[derive(Debug)]
+pub struct ReconnectFailed {
+    error: BoxError,
+    disconnect: Arc<BoxError>,
+}
+
+impl ReconnectFailed {
+    pub(crate) fn new(error: BoxError, disconnect: Arc<BoxError>) -> Self {
+        ReconnectFailed { error, disconnect }
+    }
+
+    /// Returns the error that caused the previous connection to be lost.
+    pub fn disconnect_cause(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        &**self.disconnect
+    }
+}
+
+impl fmt::Display for ReconnectFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (reconnecting after connection was lost: {})",
+            self.error, self.disconnect
+        )
+    }
+}
+
+impl std::error::Error for ReconnectFailed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.error)
+    }
+}
@@ -1,7 +1,9 @@
+use super::error::ReconnectFailed;
 use pin_project_lite::pin_project;
 use std::{
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
@@ -24,6 +26,7 @@ pin_project! {
         },
         Error {
             error: Option<E>,
+            disconnect: Option<Arc<crate::BoxError>>,
         },
     }
 }
@@ -33,8 +36,8 @@ impl<F, E> Inner<F, E> {
         Self::Future { fut }
     }
 
-    fn error(error: Option<E>) -> Self {
-        Self::Error { error }
+    fn error(error: Option<E>, disconnect: Option<Arc<crate::BoxError>>) -> Self {
+        Self::Error { error, disconnect }
     }
 }
 
@@ -45,9 +48,9 @@ impl<F, E> ResponseFuture<F, E> {
         }
     }
 
-    pub(crate) fn error(error: E) -> Self {
+    pub(crate) fn error(error: E, disconnect: Option<Arc<crate::BoxError>>) -> Self {
         ResponseFuture {
-            inner: Inner::error(Some(error)),
+            inner: Inner::error(Some(error), disconnect),
         }
     }
 }
@@ -64,8 +67,12 @@ where
         let me = self.project();
         match me.inner.project() {
             InnerProj::Future { fut } => fut.poll(cx).map_err(Into::into),
-            InnerProj::Error { error } => {
+            InnerProj::Error { error, disconnect } => {
                 let e = error.take().expect("Polled after ready.").into();
+                let e = match disconnect.take() {
+                    Some(cause) => ReconnectFailed::new(e, cause).into(),
+                    None => e,
+                };
                 Poll::Ready(Err(e))
             }
         }
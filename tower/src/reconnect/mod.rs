@@ -9,39 +9,108 @@
 //! call the service again even if the inner `MakeService` was unable to
 //! connect on the last call.
 //!
+//! By default, `Reconnect` attempts to connect again as soon as it is polled
+//! after a failed connection attempt. [`Reconnect::with_backoff`] configures
+//! it to wait between failed connection attempts instead, and
+//! [`Reconnect::max_connect_attempts`] configures it to give up after a number
+//! of consecutive failed attempts.
+//!
+//! # Observability
+//!
+//! [`Reconnect::watch_state`] returns a handle that is notified whenever the
+//! [`ConnectionState`] changes, and state changes are also recorded as
+//! `tracing` events. When an established connection is lost, the error that
+//! caused it is kept, and attached to the errors of subsequent failed
+//! connection attempts as a [`ReconnectFailed`] error, whose [`source`] is the
+//! error returned by the `MakeService`. Errors returned by the connected
+//! service itself are never wrapped.
+//!
+//! [`source`]: std::error::Error::source
+//!
+//! [`ReconnectFailed`]: crate::reconnect::error::ReconnectFailed
+//!
 //! [`MakeService`]: crate::make::MakeService
 //! [`Service`]: crate::Service
 
+pub mod error;
 mod future;
 
 pub use future::ResponseFuture;
 
+use self::error::{Exhausted, ReconnectFailed};
 use crate::make::MakeService;
+use crate::retry::backoff::{Backoff, MakeBackoff};
 use std::fmt;
 use std::{
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
+use tokio::sync::watch;
 use tower_service::Service;
-use tracing::trace;
+use tracing::{debug, trace};
 
 /// Reconnect to failed services.
-pub struct Reconnect<M, Target>
+pub struct Reconnect<M, Target, B = NoBackoff>
 where
     M: Service<Target>,
+    B: MakeBackoff,
 {
     mk_service: M,
-    state: State<M::Future, M::Response>,
+    state: State<M::Future, M::Response, <B::Backoff as Backoff>::Future>,
     target: Target,
     error: Option<M::Error>,
+    make_backoff: B,
+    /// The backoff for the current sequence of failed connection attempts.
+    backoff: Option<B::Backoff>,
+    /// The number of consecutive failed connection attempts.
+    failures: usize,
+    max_attempts: Option<usize>,
+    /// The error that caused the last established connection to be lost.
+    disconnect: Option<Arc<crate::BoxError>>,
+    /// The last connection state that was reported.
+    reported: ConnectionState,
+    /// Notifies watchers of connection state changes, once the state is
+    /// first watched.
+    watch: Option<(
+        watch::Sender<ConnectionState>,
+        watch::Receiver<ConnectionState>,
+    )>,
+}
+
+/// The state of a [`Reconnect`]'s connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// No connection is established, and no connection attempt is in
+    /// progress.
+    Idle,
+    /// A connection attempt is in progress.
+    Connecting,
+    /// A connection is established.
+    Connected,
+    /// The last connection attempt failed.
+    ///
+    /// The `Reconnect` is either waiting before attempting to connect again,
+    /// or has given up connecting.
+    Failed,
+}
+
+/// A [`MakeBackoff`] that doesn't wait at all between connection attempts.
+///
+/// This is the default backoff used by [`Reconnect`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoBackoff {
+    _p: (),
 }
 
 #[derive(Debug)]
-enum State<F, S> {
+enum State<F, S, B> {
     Idle,
     Connecting(F),
     Connected(S),
+    Backoff(Pin<Box<B>>),
+    Failed(Exhausted),
 }
 
 impl<M, Target> Reconnect<M, Target>
@@ -55,6 +124,13 @@ where
             state: State::Idle,
             target,
             error: None,
+            make_backoff: NoBackoff { _p: () },
+            backoff: None,
+            failures: 0,
+            max_attempts: None,
+            disconnect: None,
+            reported: ConnectionState::Idle,
+            watch: None,
         }
     }
 
@@ -65,17 +141,107 @@ where
             state: State::Connected(init_conn),
             target,
             error: None,
+            make_backoff: NoBackoff { _p: () },
+            backoff: None,
+            failures: 0,
+            max_attempts: None,
+            disconnect: None,
+            reported: ConnectionState::Connected,
+            watch: None,
         }
     }
 }
 
-impl<M, Target, S, Request> Service<Request> for Reconnect<M, Target>
+impl<M, Target, B> Reconnect<M, Target, B>
+where
+    M: Service<Target>,
+    B: MakeBackoff,
+{
+    /// Lazily connect and reconnect to a [`Service`], waiting between failed
+    /// connection attempts.
+    ///
+    /// After a connection attempt fails, the error is returned from the next
+    /// call to [`Reconnect::call`] as usual, but the next connection attempt
+    /// isn't made until the backoff produced by `make_backoff` completes.
+    /// Subsequent failures use the same backoff, so that the delay can grow
+    /// between consecutive failures. Once a connection is established, the
+    /// backoff is reset.
+    pub fn with_backoff(mk_service: M, target: Target, make_backoff: B) -> Self {
+        Reconnect {
+            mk_service,
+            state: State::Idle,
+            target,
+            error: None,
+            make_backoff,
+            backoff: None,
+            failures: 0,
+            max_attempts: None,
+            disconnect: None,
+            reported: ConnectionState::Idle,
+            watch: None,
+        }
+    }
+
+    /// Give up after `attempts` consecutive connection attempts have failed.
+    ///
+    /// Once `Reconnect` gives up, [`poll_ready`] fails with an [`Exhausted`]
+    /// error, wrapping the error of the last connection attempt.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `attempts` is zero.
+    ///
+    /// [`poll_ready`]: crate::Service::poll_ready
+    /// [`Exhausted`]: crate::reconnect::error::Exhausted
+    pub fn max_connect_attempts(mut self, attempts: usize) -> Self {
+        assert!(
+            attempts > 0,
+            "at least one connection attempt must be allowed"
+        );
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Returns the current state of the connection.
+    pub fn state(&self) -> ConnectionState {
+        self.state.connection_state()
+    }
+
+    /// Returns a handle that is notified whenever the state of the connection
+    /// changes.
+    ///
+    /// Note that the state only changes while the `Reconnect` is being
+    /// polled.
+    pub fn watch_state(&mut self) -> watch::Receiver<ConnectionState> {
+        let reported = self.reported;
+        let (_, rx) = self.watch.get_or_insert_with(|| watch::channel(reported));
+        rx.clone()
+    }
+
+    /// Reports the current connection state, if it has changed.
+    fn report_state(&mut self) {
+        let state = self.state.connection_state();
+        if state == self.reported {
+            return;
+        }
+
+        debug!(from = ?self.reported, to = ?state, "connection state changed");
+        self.reported = state;
+        if let Some((ref tx, _)) = self.watch {
+            // We hold a receiver, so sending can't fail.
+            let _ = tx.send(state);
+        }
+    }
+}
+
+impl<M, Target, B, S, Request> Service<Request> for Reconnect<M, Target, B>
 where
     M: Service<Target, Response = S>,
     S: Service<Request>,
     M::Future: Unpin,
     crate::BoxError: From<M::Error> + From<S::Error>,
     Target: Clone,
+    B: MakeBackoff,
 {
     type Response = S::Response;
     type Error = crate::BoxError;
@@ -96,6 +262,7 @@ where
 
                     let fut = self.mk_service.make_service(self.target.clone());
                     self.state = State::Connecting(fut);
+                    self.report_state();
                     continue;
                 }
                 State::Connecting(ref mut f) => {
@@ -103,6 +270,10 @@ where
                     match Pin::new(f).poll(cx) {
                         Poll::Ready(Ok(service)) => {
                             self.state = State::Connected(service);
+                            self.backoff = None;
+                            self.failures = 0;
+                            self.disconnect = None;
+                            self.report_state();
                         }
                         Poll::Pending => {
                             trace!("poll_ready; not ready");
@@ -110,12 +281,50 @@ where
                         }
                         Poll::Ready(Err(e)) => {
                             trace!("poll_ready; error");
-                            self.state = State::Idle;
+                            self.failures += 1;
+                            debug!(attempts = self.failures, "connection attempt failed");
+                            if self.max_attempts.map_or(false, |max| self.failures >= max) {
+                                debug!(attempts = self.failures, "giving up connecting");
+                                let error = match self.disconnect {
+                                    Some(ref cause) => {
+                                        ReconnectFailed::new(e.into(), cause.clone()).into()
+                                    }
+                                    None => e.into(),
+                                };
+                                let error = Exhausted::new(self.failures, error);
+                                self.state = State::Failed(error.clone());
+                                self.report_state();
+                                return Poll::Ready(Err(error.into()));
+                            }
+
+                            let make_backoff = &mut self.make_backoff;
+                            let backoff = self
+                                .backoff
+                                .get_or_insert_with(|| make_backoff.make_backoff());
+                            self.state = State::Backoff(Box::pin(backoff.next_backoff()));
                             self.error = Some(e);
+                            self.report_state();
                             break;
                         }
                     }
                 }
+                State::Backoff(ref mut backoff) => {
+                    trace!("poll_ready; backing off");
+                    match backoff.as_mut().poll(cx) {
+                        Poll::Ready(()) => {
+                            self.state = State::Idle;
+                            self.report_state();
+                        }
+                        Poll::Pending => {
+                            trace!("poll_ready; not ready");
+                            return Poll::Pending;
+                        }
+                    }
+                }
+                State::Failed(ref error) => {
+                    trace!("poll_ready; failed");
+                    return Poll::Ready(Err(error.clone().into()));
+                }
                 State::Connected(ref mut inner) => {
                     trace!("poll_ready; connected");
                     match inner.poll_ready(cx) {
@@ -127,9 +336,13 @@ where
                             trace!("poll_ready; not ready");
                             return Poll::Pending;
                         }
-                        Poll::Ready(Err(_)) => {
+                        Poll::Ready(Err(e)) => {
                             trace!("poll_ready; error");
+                            let cause = crate::BoxError::from(e);
+                            debug!(error = %cause, "connection lost");
+                            self.disconnect = Some(Arc::new(cause));
                             self.state = State::Idle;
+                            self.report_state();
                         }
                     }
                 }
@@ -141,7 +354,7 @@ where
 
     fn call(&mut self, request: Request) -> Self::Future {
         if let Some(error) = self.error.take() {
-            return ResponseFuture::error(error);
+            return ResponseFuture::error(error, self.disconnect.clone());
         }
 
         let service = match self.state {
@@ -154,18 +367,58 @@ where
     }
 }
 
-impl<M, Target> fmt::Debug for Reconnect<M, Target>
+impl<M, Target, B> fmt::Debug for Reconnect<M, Target, B>
 where
     M: Service<Target> + fmt::Debug,
     M::Future: fmt::Debug,
     M::Response: fmt::Debug,
     Target: fmt::Debug,
+    B: MakeBackoff + fmt::Debug,
+    B::Backoff: fmt::Debug,
+    <B::Backoff as Backoff>::Future: fmt::Debug,
 {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("Reconnect")
             .field("mk_service", &self.mk_service)
             .field("state", &self.state)
             .field("target", &self.target)
+            .field("make_backoff", &self.make_backoff)
+            .field("backoff", &self.backoff)
+            .field("failures", &self.failures)
+            .field("max_attempts", &self.max_attempts)
+            .field("disconnect", &self.disconnect)
+            .field("reported", &self.reported)
             .finish()
     }
 }
+
+// ===== impl State =====
+
+impl<F, S, B> State<F, S, B> {
+    fn connection_state(&self) -> ConnectionState {
+        match self {
+            State::Idle => ConnectionState::Idle,
+            State::Connecting(_) => ConnectionState::Connecting,
+            State::Connected(_) => ConnectionState::Connected,
+            State::Backoff(_) | State::Failed(_) => ConnectionState::Failed,
+        }
+    }
+}
+
+// ===== impl NoBackoff =====
+
+impl MakeBackoff for NoBackoff {
+    type Backoff = NoBackoff;
+
+    fn make_backoff(&mut self) -> Self::Backoff {
+        *self
+    }
+}
+
+impl Backoff for NoBackoff {
+    type Future = std::future::Ready<()>;
+
+    fn next_backoff(&mut self) -> Self::Future {
+        std::future::ready(())
+    }
+}
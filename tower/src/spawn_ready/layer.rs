@@ -1,7 +1,16 @@
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+
 /// Spawns tasks to drive its inner service to readiness.
 #[derive(Clone, Debug, Default)]
-pub struct SpawnReadyLayer(());
+pub struct SpawnReadyLayer<
+    #[cfg(feature = "tokio-rt")] E = TokioExecutor,
+    #[cfg(not(feature = "tokio-rt"))] E,
+> {
+    executor: E,
+}
 
+#[cfg(feature = "tokio-rt")]
 impl SpawnReadyLayer {
     /// Builds a [`SpawnReadyLayer`].
     pub fn new() -> Self {
@@ -9,10 +18,17 @@ impl SpawnReadyLayer {
     }
 }
 
-impl<S> tower_layer::Layer<S> for SpawnReadyLayer {
-    type Service = super::SpawnReady<S>;
+impl<E> SpawnReadyLayer<E> {
+    /// Builds a [`SpawnReadyLayer`] that spawns tasks with `executor`.
+    pub const fn with_executor(executor: E) -> Self {
+        Self { executor }
+    }
+}
+
+impl<S, E: Clone> tower_layer::Layer<S> for SpawnReadyLayer<E> {
+    type Service = super::SpawnReady<S, E>;
 
     fn layer(&self, service: S) -> Self::Service {
-        super::SpawnReady::new(service)
+        super::SpawnReady::with_executor(service, self.executor.clone())
     }
 }
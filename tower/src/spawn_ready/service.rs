@@ -1,11 +1,17 @@
-use super::{future::ResponseFuture, SpawnReadyLayer};
+use super::future::ResponseFuture;
+#[cfg(feature = "tokio-rt")]
+use super::SpawnReadyLayer;
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
 use crate::{util::ServiceExt, BoxError};
-use futures_util::future::TryFutureExt;
+use futures_util::future::{poll_fn, TryFutureExt};
 use std::{
     future::Future,
     pin::Pin,
     task::{ready, Context, Poll},
 };
+use tokio::sync::oneshot;
 use tower_service::Service;
 use tracing::Instrument;
 
@@ -13,22 +19,28 @@ use tracing::Instrument;
 ///
 /// See crate level documentation for more details.
 #[derive(Debug)]
-pub struct SpawnReady<S> {
+pub struct SpawnReady<
+    S,
+    #[cfg(feature = "tokio-rt")] E = TokioExecutor,
+    #[cfg(not(feature = "tokio-rt"))] E,
+> {
     inner: Inner<S>,
+    executor: E,
 }
 
 #[derive(Debug)]
 enum Inner<S> {
     Service(Option<S>),
-    Future(tokio::task::JoinHandle<Result<S, BoxError>>),
+    Future(oneshot::Receiver<Result<S, BoxError>>),
 }
 
+#[cfg(feature = "tokio-rt")]
 impl<S> SpawnReady<S> {
     /// Creates a new [`SpawnReady`] wrapping `service`.
+    ///
+    /// Tasks are spawned on the current Tokio runtime.
     pub const fn new(service: S) -> Self {
-        Self {
-            inner: Inner::Service(Some(service)),
-        }
+        Self::with_executor(service, TokioExecutor::new())
     }
 
     /// Creates a layer that wraps services with [`SpawnReady`].
@@ -37,19 +49,24 @@ impl<S> SpawnReady<S> {
     }
 }
 
-impl<S> Drop for SpawnReady<S> {
-    fn drop(&mut self) {
-        if let Inner::Future(ref mut task) = self.inner {
-            task.abort();
+impl<S, E> SpawnReady<S, E> {
+    /// Creates a new [`SpawnReady`] wrapping `service`, that spawns its tasks with `executor`.
+    ///
+    /// A spawned task stops driving the service to readiness when the [`SpawnReady`] is dropped.
+    pub const fn with_executor(service: S, executor: E) -> Self {
+        Self {
+            inner: Inner::Service(Some(service)),
+            executor,
         }
     }
 }
 
-impl<S, Req> Service<Req> for SpawnReady<S>
+impl<S, E, Req> Service<Req> for SpawnReady<S, E>
 where
     Req: 'static,
     S: Service<Req> + Send + 'static,
     S::Error: Into<BoxError>,
+    E: Executor,
 {
     type Response = S::Response;
     type Error = BoxError;
@@ -64,12 +81,28 @@ where
                     }
 
                     let svc = svc.take().expect("illegal state");
-                    let rx =
-                        tokio::spawn(svc.ready_oneshot().map_err(Into::into).in_current_span());
+                    let (mut tx, rx) = oneshot::channel();
+                    let mut ready = Box::pin(svc.ready_oneshot().map_err(Into::into));
+                    let task = async move {
+                        // Stop driving the service if the `SpawnReady` is dropped.
+                        let ready = poll_fn(|cx| {
+                            if tx.poll_closed(cx).is_ready() {
+                                return Poll::Ready(None);
+                            }
+                            ready.as_mut().poll(cx).map(Some)
+                        });
+                        if let Some(result) = ready.await {
+                            let _ = tx.send(result);
+                        }
+                    };
+                    self.executor.spawn(Box::pin(task.in_current_span()));
                     Inner::Future(rx)
                 }
-                Inner::Future(ref mut fut) => {
-                    let svc = ready!(Pin::new(fut).poll(cx))??;
+                Inner::Future(ref mut rx) => {
+                    // The task is dropped without sending a result if it panics.
+                    let svc = ready!(Pin::new(rx).poll(cx)).map_err(|_| {
+                        BoxError::from("spawned task dropped before becoming ready")
+                    })??;
                     Inner::Service(Some(svc))
                 }
             }
@@ -0,0 +1,14 @@
+//! Time utilities shared by the middleware.
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Returns `instant + duration`, or a time in the far future if it can't be represented.
+pub(crate) fn saturating_add(instant: Instant, duration: Duration) -> Instant {
+    // Roughly 30 years from `instant`, like Tokio does for sleeps that are too long.
+    const FAR_FUTURE: Duration = Duration::from_secs(86400 * 365 * 30);
+    instant
+        .checked_add(duration)
+        .or_else(|| instant.checked_add(FAR_FUTURE))
+        .unwrap_or(instant)
+}
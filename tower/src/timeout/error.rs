@@ -20,3 +20,27 @@ impl fmt::Display for Elapsed {
 }
 
 impl error::Error for Elapsed {}
+
+/// The inner service did not become ready within the readiness timeout.
+///
+/// This error is returned by [`ReadyTimeout`]'s [`poll_ready`].
+///
+/// [`ReadyTimeout`]: crate::timeout::ReadyTimeout
+/// [`poll_ready`]: crate::Service::poll_ready
+#[derive(Debug, Default)]
+pub struct ReadyElapsed(pub(super) ());
+
+impl ReadyElapsed {
+    /// Construct a new ready elapsed error
+    pub const fn new() -> Self {
+        ReadyElapsed(())
+    }
+}
+
+impl fmt::Display for ReadyElapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("service readiness timed out")
+    }
+}
+
+impl error::Error for ReadyElapsed {}
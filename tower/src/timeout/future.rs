@@ -16,15 +16,49 @@ pin_project! {
     #[derive(Debug)]
     pub struct ResponseFuture<T> {
         #[pin]
-        response: T,
-        #[pin]
-        sleep: Sleep,
+        state: State<T>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    #[derive(Debug)]
+    enum State<T> {
+        Running {
+            #[pin]
+            response: T,
+            // `None` if the response never times out.
+            #[pin]
+            sleep: Option<Sleep>,
+        },
+        Elapsed,
     }
 }
 
 impl<T> ResponseFuture<T> {
     pub(crate) fn new(response: T, sleep: Sleep) -> Self {
-        ResponseFuture { response, sleep }
+        ResponseFuture {
+            state: State::Running {
+                response,
+                sleep: Some(sleep),
+            },
+        }
+    }
+
+    /// Returns a future that waits for `response` without a timeout.
+    pub(crate) fn without_timeout(response: T) -> Self {
+        ResponseFuture {
+            state: State::Running {
+                response,
+                sleep: None,
+            },
+        }
+    }
+
+    pub(crate) fn elapsed() -> Self {
+        ResponseFuture {
+            state: State::Elapsed,
+        }
     }
 }
 
@@ -36,18 +70,50 @@ where
     type Output = Result<T, crate::BoxError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
+        let (response, sleep) = match self.project().state.project() {
+            StateProj::Running { response, sleep } => (response, sleep),
+            StateProj::Elapsed => return Poll::Ready(Err(Elapsed(()).into())),
+        };
 
         // First, try polling the future
-        match this.response.poll(cx) {
+        match response.poll(cx) {
             Poll::Ready(v) => return Poll::Ready(v.map_err(Into::into)),
             Poll::Pending => {}
         }
 
         // Now check the sleep
-        match this.sleep.poll(cx) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(_) => Poll::Ready(Err(Elapsed(()).into())),
+        match sleep.as_pin_mut().map(|sleep| sleep.poll(cx)) {
+            Some(Poll::Ready(_)) => Poll::Ready(Err(Elapsed(()).into())),
+            Some(Poll::Pending) | None => Poll::Pending,
         }
     }
 }
+
+pin_project! {
+    /// [`ReadyTimeout`] response future
+    ///
+    /// [`ReadyTimeout`]: crate::timeout::ReadyTimeout
+    #[derive(Debug)]
+    pub struct ReadyResponseFuture<T> {
+        #[pin]
+        response: T,
+    }
+}
+
+impl<T> ReadyResponseFuture<T> {
+    pub(crate) fn new(response: T) -> Self {
+        ReadyResponseFuture { response }
+    }
+}
+
+impl<F, T, E> Future for ReadyResponseFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<crate::BoxError>,
+{
+    type Output = Result<T, crate::BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().response.poll(cx).map_err(Into::into)
+    }
+}
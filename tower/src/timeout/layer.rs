@@ -1,5 +1,5 @@
-use super::Timeout;
-use std::time::Duration;
+use super::{DeadlineTimeout, ReadyTimeout, RequestTimeout, Timeout, TotalTimeout};
+use std::{fmt, time::Duration};
 use tower_layer::Layer;
 
 /// Applies a timeout to requests via the supplied inner service.
@@ -22,3 +22,128 @@ impl<S> Layer<S> for TimeoutLayer {
         Timeout::new(service, self.timeout)
     }
 }
+
+/// Applies a timeout taken from each request via the supplied inner service.
+///
+/// See [`RequestTimeout`] for details.
+#[derive(Clone)]
+pub struct RequestTimeoutLayer<F> {
+    extract: F,
+    default: Duration,
+    max: Duration,
+}
+
+impl<F> RequestTimeoutLayer<F> {
+    /// Create a new [`RequestTimeoutLayer`].
+    pub const fn new(extract: F, default: Duration, max: Duration) -> Self {
+        RequestTimeoutLayer {
+            extract,
+            default,
+            max,
+        }
+    }
+}
+
+impl<S, F: Clone> Layer<S> for RequestTimeoutLayer<F> {
+    type Service = RequestTimeout<S, F>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RequestTimeout::new(service, self.extract.clone(), self.default, self.max)
+    }
+}
+
+impl<F> fmt::Debug for RequestTimeoutLayer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestTimeoutLayer")
+            .field("extract", &format_args!("{}", std::any::type_name::<F>()))
+            .field("default", &self.default)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+/// Applies a deadline taken from each request via the supplied inner service.
+///
+/// See [`DeadlineTimeout`] for details.
+#[derive(Clone)]
+pub struct DeadlineTimeoutLayer<F> {
+    extract: F,
+    default: Duration,
+    max: Duration,
+}
+
+impl<F> DeadlineTimeoutLayer<F> {
+    /// Create a new [`DeadlineTimeoutLayer`].
+    pub const fn new(extract: F, default: Duration, max: Duration) -> Self {
+        DeadlineTimeoutLayer {
+            extract,
+            default,
+            max,
+        }
+    }
+}
+
+impl<S, F: Clone> Layer<S> for DeadlineTimeoutLayer<F> {
+    type Service = DeadlineTimeout<S, F>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        DeadlineTimeout::new(service, self.extract.clone(), self.default, self.max)
+    }
+}
+
+impl<F> fmt::Debug for DeadlineTimeoutLayer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadlineTimeoutLayer")
+            .field("extract", &format_args!("{}", std::any::type_name::<F>()))
+            .field("default", &self.default)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+/// Applies a timeout covering both readiness and the response via the supplied
+/// inner service.
+///
+/// See [`TotalTimeout`] for details.
+#[derive(Debug, Clone)]
+pub struct TotalTimeoutLayer {
+    timeout: Duration,
+}
+
+impl TotalTimeoutLayer {
+    /// Create a total timeout from a duration
+    pub const fn new(timeout: Duration) -> Self {
+        TotalTimeoutLayer { timeout }
+    }
+}
+
+impl<S> Layer<S> for TotalTimeoutLayer {
+    type Service = TotalTimeout<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        TotalTimeout::new(service, self.timeout)
+    }
+}
+
+/// Applies a timeout to waiting for readiness via the supplied inner service.
+///
+/// See [`ReadyTimeout`] for details.
+#[derive(Debug, Clone)]
+pub struct ReadyTimeoutLayer {
+    timeout: Duration,
+}
+
+impl ReadyTimeoutLayer {
+    /// Create a readiness timeout from a duration
+    pub const fn new(timeout: Duration) -> Self {
+        ReadyTimeoutLayer { timeout }
+    }
+}
+
+impl<S> Layer<S> for ReadyTimeoutLayer {
+    type Service = ReadyTimeout<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        ReadyTimeout::new(service, self.timeout)
+    }
+}
@@ -2,12 +2,60 @@
 //!
 //! If the response does not complete within the specified timeout, the response
 //! will be aborted.
+//!
+//! [`Timeout`] applies the same fixed timeout to every request. When the timeout
+//! depends on the request, [`RequestTimeout`] takes a relative timeout from each
+//! request, and [`DeadlineTimeout`] takes an absolute deadline from each request.
+//! [`TotalTimeout`] also counts the time spent waiting for the inner service to
+//! become ready, while [`ReadyTimeout`] only bounds the time spent waiting for
+//! readiness.
+//!
+//! # Sharing a deadline across retries
+//!
+//! When a [`Retry`] wraps a [`Timeout`], each attempt gets the full timeout. If the
+//! request instead carries an absolute deadline, every attempt made by the
+//! [`Retry`] sees the same deadline, so that the attempts share one overall
+//! deadline. Alternatively, a [`TotalTimeout`] placed around the [`Retry`] bounds
+//! the time taken by all attempts together.
+//!
+//! For example, using a [`DeadlineTimeout`]:
+//!
+//! ```
+//! use std::time::Duration;
+//! use tokio::time::Instant;
+//! use tower::timeout::DeadlineTimeoutLayer;
+//!
+//! #[derive(Clone)]
+//! struct Request {
+//!     deadline: Option<Instant>,
+//!     // ...
+//! }
+//!
+//! let layer = DeadlineTimeoutLayer::new(
+//!     |req: &Request| req.deadline,
+//!     // Requests without a deadline time out after one second...
+//!     Duration::from_secs(1),
+//!     // ...and no request may take more than ten seconds.
+//!     Duration::from_secs(10),
+//! );
+//! # drop(layer);
+//! ```
+//!
+//! [`Retry`]: crate::retry::Retry
 
 pub mod error;
 pub mod future;
 mod layer;
+mod ready;
+mod request;
+mod total;
 
-pub use self::layer::TimeoutLayer;
+pub use self::layer::{
+    DeadlineTimeoutLayer, ReadyTimeoutLayer, RequestTimeoutLayer, TimeoutLayer, TotalTimeoutLayer,
+};
+pub use self::ready::ReadyTimeout;
+pub use self::request::{DeadlineTimeout, RequestTimeout};
+pub use self::total::TotalTimeout;
 
 use self::future::ResponseFuture;
 use std::task::{Context, Poll};
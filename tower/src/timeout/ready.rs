@@ -0,0 +1,126 @@
+use super::{error::ReadyElapsed, future::ReadyResponseFuture};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+use tower_service::Service;
+
+/// Applies a timeout to waiting for the inner service to become ready.
+///
+/// If the inner service's [`poll_ready`] doesn't return [`Poll::Ready`] within
+/// the timeout, [`poll_ready`] fails with a [`ReadyElapsed`] error. This
+/// prevents callers from waiting forever on a service that will never become
+/// ready, such as a [`Reconnect`] that can't connect or a [`Balance`] without
+/// any endpoints.
+///
+/// The timer starts when the inner service first reports that it isn't ready,
+/// and is reset once it becomes ready, so each readiness cycle gets the full
+/// timeout.
+///
+/// [`poll_ready`]: crate::Service::poll_ready
+/// [`ReadyElapsed`]: crate::timeout::error::ReadyElapsed
+/// [`Reconnect`]: crate::reconnect::Reconnect
+/// [`Balance`]: crate::balance::p2c::Balance
+#[derive(Debug)]
+pub struct ReadyTimeout<T> {
+    inner: T,
+    timeout: Duration,
+    /// The timer for the current readiness cycle.
+    ///
+    /// The timer is allocated the first time the inner service isn't ready,
+    /// and reused for subsequent readiness cycles.
+    sleep: Option<Pin<Box<Sleep>>>,
+    /// Whether `sleep` is tracking the current readiness cycle.
+    waiting: bool,
+}
+
+// ===== impl ReadyTimeout =====
+
+impl<T> ReadyTimeout<T> {
+    /// Creates a new [`ReadyTimeout`]
+    pub const fn new(inner: T, timeout: Duration) -> Self {
+        ReadyTimeout {
+            inner,
+            timeout,
+            sleep: None,
+            waiting: false,
+        }
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<S, Request> Service<Request> for ReadyTimeout<S>
+where
+    S: Service<Request>,
+    S::Error: Into<crate::BoxError>,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ReadyResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Poll::Ready(r) = self.inner.poll_ready(cx) {
+            self.waiting = false;
+            return Poll::Ready(r.map_err(Into::into));
+        }
+
+        // The inner service isn't ready. If this is the start of a new
+        // readiness cycle, (re)start the timer.
+        if !self.waiting {
+            self.waiting = true;
+            match Instant::now().checked_add(self.timeout) {
+                Some(deadline) => match self.sleep {
+                    Some(ref mut sleep) => sleep.as_mut().reset(deadline),
+                    None => self.sleep = Some(Box::pin(tokio::time::sleep_until(deadline))),
+                },
+                // A timeout too large to be represented as an `Instant` never
+                // elapses.
+                None => self.sleep = None,
+            }
+        }
+
+        let sleep = match self.sleep.as_mut() {
+            Some(sleep) => sleep,
+            None => return Poll::Pending,
+        };
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.waiting = false;
+                Poll::Ready(Err(ReadyElapsed(()).into()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        self.waiting = false;
+        ReadyResponseFuture::new(self.inner.call(request))
+    }
+}
+
+impl<T: Clone> Clone for ReadyTimeout<T> {
+    fn clone(&self) -> Self {
+        ReadyTimeout {
+            inner: self.inner.clone(),
+            timeout: self.timeout,
+            // Readiness is tracked per clone.
+            sleep: None,
+            waiting: false,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use super::future::ResponseFuture;
+use std::fmt;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Instant;
+use tower_service::Service;
+
+/// Applies a timeout taken from each request.
+///
+/// The timeout is determined by calling a `Fn(&Request) -> Option<Duration>`
+/// extractor on each request, for example to read a `grpc-timeout` header. If
+/// the extractor returns `None`, the `default` timeout is used instead.
+/// Timeouts are never longer than `max`, so that callers can't hold on to
+/// resources for arbitrarily long.
+pub struct RequestTimeout<T, F> {
+    inner: T,
+    extract: F,
+    default: Duration,
+    max: Duration,
+}
+
+/// Applies a deadline taken from each request.
+///
+/// Unlike [`RequestTimeout`], which measures time from when the request is
+/// called, the deadline is an absolute [`Instant`] determined by calling a
+/// `Fn(&Request) -> Option<Instant>` extractor on each request. When a
+/// deadline is set once, for example by an outer layer or the original
+/// caller, and carried along with the request, every attempt made by a
+/// [`Retry`] wrapping this middleware shares the same overall deadline,
+/// rather than each attempt getting the full time again.
+///
+/// If the extractor returns `None`, the request times out after the `default`
+/// duration. Deadlines are never further away than `max` from when the request
+/// is called.
+///
+/// The `default` timeout is not stamped onto the request, so when a [`Retry`]
+/// wraps this middleware, each attempt of a request without a deadline gets
+/// the full `default` timeout again. To share one deadline across attempts,
+/// set the deadline on the request before the [`Retry`], for example with a
+/// [`MapRequest`].
+///
+/// [`Retry`]: crate::retry::Retry
+/// [`MapRequest`]: crate::util::MapRequest
+pub struct DeadlineTimeout<T, F> {
+    inner: T,
+    extract: F,
+    default: Duration,
+    max: Duration,
+}
+
+// ===== impl RequestTimeout =====
+
+impl<T, F> RequestTimeout<T, F> {
+    /// Creates a new [`RequestTimeout`].
+    pub const fn new(inner: T, extract: F, default: Duration, max: Duration) -> Self {
+        RequestTimeout {
+            inner,
+            extract,
+            default,
+            max,
+        }
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<S, F, Request> Service<Request> for RequestTimeout<S, F>
+where
+    S: Service<Request>,
+    S::Error: Into<crate::BoxError>,
+    F: Fn(&Request) -> Option<Duration>,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let timeout = (self.extract)(&request)
+            .unwrap_or(self.default)
+            .min(self.max);
+        let response = self.inner.call(request);
+        let sleep = tokio::time::sleep(timeout);
+
+        ResponseFuture::new(response, sleep)
+    }
+}
+
+impl<T: Clone, F: Clone> Clone for RequestTimeout<T, F> {
+    fn clone(&self) -> Self {
+        RequestTimeout {
+            inner: self.inner.clone(),
+            extract: self.extract.clone(),
+            default: self.default,
+            max: self.max,
+        }
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for RequestTimeout<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestTimeout")
+            .field("inner", &self.inner)
+            .field("extract", &format_args!("{}", std::any::type_name::<F>()))
+            .field("default", &self.default)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+// ===== impl DeadlineTimeout =====
+
+impl<T, F> DeadlineTimeout<T, F> {
+    /// Creates a new [`DeadlineTimeout`].
+    pub const fn new(inner: T, extract: F, default: Duration, max: Duration) -> Self {
+        DeadlineTimeout {
+            inner,
+            extract,
+            default,
+            max,
+        }
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<S, F, Request> Service<Request> for DeadlineTimeout<S, F>
+where
+    S: Service<Request>,
+    S::Error: Into<crate::BoxError>,
+    F: Fn(&Request) -> Option<Instant>,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let now = Instant::now();
+        // A timeout too large to be represented as an `Instant` never elapses.
+        let max = now.checked_add(self.max);
+        let deadline = match (self.extract)(&request) {
+            Some(deadline) => Some(max.map_or(deadline, |max| deadline.min(max))),
+            None => now.checked_add(self.default.min(self.max)),
+        };
+        let response = self.inner.call(request);
+
+        match deadline {
+            Some(deadline) => ResponseFuture::new(response, tokio::time::sleep_until(deadline)),
+            None => ResponseFuture::without_timeout(response),
+        }
+    }
+}
+
+impl<T: Clone, F: Clone> Clone for DeadlineTimeout<T, F> {
+    fn clone(&self) -> Self {
+        DeadlineTimeout {
+            inner: self.inner.clone(),
+            extract: self.extract.clone(),
+            default: self.default,
+            max: self.max,
+        }
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for DeadlineTimeout<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadlineTimeout")
+            .field("inner", &self.inner)
+            .field("extract", &format_args!("{}", std::any::type_name::<F>()))
+            .field("default", &self.default)
+            .field("max", &self.max)
+            .finish()
+    }
+}
@@ -0,0 +1,188 @@
+use super::future::ResponseFuture;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+use tower_service::Service;
+
+/// Applies a timeout that covers both waiting for the inner service to become
+/// ready and waiting for its response.
+///
+/// [`Timeout`] only starts its clock once a request has been called, so time
+/// spent waiting for readiness, for example behind a [`Buffer`],
+/// [`ConcurrencyLimit`] or [`RateLimit`], isn't counted. `TotalTimeout` instead
+/// starts its clock when [`poll_ready`] is first called for a request, and the
+/// request fails with an [`Elapsed`] error if the response isn't available
+/// within the timeout of that point.
+///
+/// If the inner service doesn't become ready in time, `TotalTimeout` still
+/// reports readiness, so that the caller may proceed, but the subsequent
+/// [`call`] fails immediately without calling the inner service. This keeps
+/// the service usable for later requests, unlike failing [`poll_ready`]
+/// would. If [`poll_ready`] is called again instead, once the deadline has
+/// passed, the earlier readiness is considered abandoned, and a new readiness
+/// cycle starts with the full timeout.
+///
+/// When wrapping a [`Retry`], the timeout caps the total time spent across
+/// all attempts made by the retry middleware, including the time each attempt
+/// waits for readiness.
+///
+/// [`Timeout`]: crate::timeout::Timeout
+/// [`Buffer`]: crate::buffer::Buffer
+/// [`ConcurrencyLimit`]: crate::limit::ConcurrencyLimit
+/// [`RateLimit`]: crate::limit::RateLimit
+/// [`Retry`]: crate::retry::Retry
+/// [`Elapsed`]: crate::timeout::error::Elapsed
+/// [`poll_ready`]: crate::Service::poll_ready
+/// [`call`]: crate::Service::call
+#[derive(Debug)]
+pub struct TotalTimeout<T> {
+    inner: T,
+    timeout: Duration,
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    /// No readiness cycle is in progress.
+    Idle,
+    /// Waiting for the inner service to become ready.
+    ///
+    /// The deadline is `None` if the timeout is too large to be represented
+    /// as an `Instant`, in which case it never elapses.
+    Polling {
+        deadline: Option<Instant>,
+        sleep: Option<Pin<Box<Sleep>>>,
+    },
+    /// The inner service is ready, and the deadline for the request is known.
+    Ready { deadline: Option<Instant> },
+    /// The timeout elapsed before the inner service became ready.
+    Elapsed,
+}
+
+// ===== impl TotalTimeout =====
+
+impl<T> TotalTimeout<T> {
+    /// Creates a new [`TotalTimeout`]
+    pub const fn new(inner: T, timeout: Duration) -> Self {
+        TotalTimeout {
+            inner,
+            timeout,
+            state: State::Idle,
+        }
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<S, Request> Service<Request> for TotalTimeout<S>
+where
+    S: Service<Request>,
+    S::Error: Into<crate::BoxError>,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let deadline = match self.state {
+            // Readiness was already reached for the next request, and hasn't
+            // been consumed by `call` yet.
+            State::Ready { deadline } if deadline.map_or(true, |d| Instant::now() < d) => {
+                return Poll::Ready(Ok(()));
+            }
+            // The caller polls again after its deadline passed instead of
+            // calling, so the earlier readiness was abandoned. This starts a
+            // fresh readiness cycle, rather than failing the next request.
+            State::Ready { .. } | State::Elapsed | State::Idle => {
+                self.state = State::Idle;
+                Instant::now().checked_add(self.timeout)
+            }
+            State::Polling { deadline, .. } => deadline,
+        };
+
+        match self.inner.poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                self.state = State::Ready { deadline };
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Ready(Err(e)) => {
+                self.state = State::Idle;
+                return Poll::Ready(Err(e.into()));
+            }
+            Poll::Pending => {}
+        }
+
+        // The inner service isn't ready, so make sure we're woken up when the
+        // deadline passes. The timer is only allocated once readiness has to
+        // be waited for.
+        if let State::Idle = self.state {
+            self.state = State::Polling {
+                deadline,
+                sleep: None,
+            };
+        }
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => return Poll::Pending,
+        };
+        let sleep = match self.state {
+            State::Polling { ref mut sleep, .. } => {
+                sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)))
+            }
+            _ => unreachable!("state must be Polling"),
+        };
+
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.state = State::Elapsed;
+                Poll::Ready(Ok(()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        match std::mem::replace(&mut self.state, State::Idle) {
+            State::Ready { deadline } => {
+                let response = self.inner.call(request);
+
+                match deadline {
+                    Some(deadline) => {
+                        ResponseFuture::new(response, tokio::time::sleep_until(deadline))
+                    }
+                    None => ResponseFuture::without_timeout(response),
+                }
+            }
+            State::Elapsed => ResponseFuture::elapsed(),
+            State::Idle | State::Polling { .. } => {
+                panic!("service not ready; poll_ready must be called first")
+            }
+        }
+    }
+}
+
+impl<T: Clone> Clone for TotalTimeout<T> {
+    fn clone(&self) -> Self {
+        TotalTimeout {
+            inner: self.inner.clone(),
+            timeout: self.timeout,
+            // Readiness is tracked per clone.
+            state: State::Idle,
+        }
+    }
+}
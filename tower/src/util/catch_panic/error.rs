@@ -0,0 +1,37 @@
+use std::any::Any;
+use std::{error, fmt};
+
+/// Error returned by [`CatchPanic`] when the inner service panicked.
+///
+/// [`CatchPanic`]: crate::util::CatchPanic
+#[derive(Debug)]
+pub struct Panicked {
+    message: Option<String>,
+}
+
+impl Panicked {
+    pub(crate) fn new(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => Some(*message),
+            Err(payload) => payload.downcast_ref::<&str>().map(|s| s.to_string()),
+        };
+        Panicked { message }
+    }
+
+    /// Returns the message the service panicked with, if the panic payload was
+    /// a string.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message {
+            Some(ref message) => write!(f, "service panicked: {}", message),
+            None => f.pad("service panicked"),
+        }
+    }
+}
+
+impl error::Error for Panicked {}
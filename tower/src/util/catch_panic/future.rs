@@ -0,0 +1,71 @@
+use super::error::Panicked;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pin_project! {
+    /// Response future returned by [`CatchPanic`].
+    ///
+    /// [`CatchPanic`]: crate::util::CatchPanic
+    #[derive(Debug)]
+    pub struct ResponseFuture<F> {
+        #[pin]
+        state: State<F>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    #[derive(Debug)]
+    enum State<F> {
+        Called {
+            #[pin]
+            future: F,
+        },
+        Panicked {
+            error: Option<Panicked>,
+        },
+    }
+}
+
+impl<F> ResponseFuture<F> {
+    pub(crate) fn called(future: F) -> Self {
+        ResponseFuture {
+            state: State::Called { future },
+        }
+    }
+
+    pub(crate) fn panicked(error: Panicked) -> Self {
+        ResponseFuture {
+            state: State::Panicked { error: Some(error) },
+        }
+    }
+}
+
+impl<F, T, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<crate::BoxError>,
+{
+    type Output = Result<T, crate::BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.project().state;
+        let error = match state.as_mut().project() {
+            StateProj::Called { future } => {
+                match catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+                    Ok(poll) => return poll.map_err(Into::into),
+                    // The future must not be polled again after panicking.
+                    Err(payload) => Panicked::new(payload),
+                }
+            }
+            StateProj::Panicked { error } => error.take().expect("polled after completion"),
+        };
+        state.set(State::Panicked { error: None });
+        Poll::Ready(Err(error.into()))
+    }
+}
@@ -0,0 +1,110 @@
+//! Contains [`CatchPanic`] and related types and functions.
+//!
+//! See [`CatchPanic`] documentation for more details.
+
+/// Error types for [`CatchPanic`].
+pub mod error;
+/// Future types for [`CatchPanic`].
+pub mod future;
+
+use self::{error::Panicked, future::ResponseFuture};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Catches panics in the inner service's [`call`] and response future, and
+/// returns them as [`Panicked`] errors.
+///
+/// Without this, a panic while handling a single request unwinds through
+/// everything driving the request. For example, it kills the worker of a
+/// [`Buffer`], which fails every other request sharing the buffer. Wrapping
+/// the service given to the [`Buffer`] in [`CatchPanic`] confines the panic to
+/// the request that caused it.
+///
+/// Panics in [`poll_ready`] are not caught. Note that the inner service keeps
+/// being used after one of its calls panicked, so it must not be left in an
+/// inconsistent state by a panic.
+///
+/// [`call`]: crate::Service::call
+/// [`poll_ready`]: crate::Service::poll_ready
+/// [`Buffer`]: crate::buffer::Buffer
+#[derive(Clone, Debug)]
+pub struct CatchPanic<S> {
+    inner: S,
+}
+
+/// A [`Layer`] that produces [`CatchPanic`] services.
+///
+/// [`Layer`]: tower_layer::Layer
+#[derive(Clone, Debug, Default)]
+pub struct CatchPanicLayer {
+    _p: (),
+}
+
+impl<S> CatchPanic<S> {
+    /// Creates a new [`CatchPanic`] service.
+    pub const fn new(inner: S) -> Self {
+        CatchPanic { inner }
+    }
+
+    /// Returns a new [`Layer`] that produces [`CatchPanic`] services.
+    ///
+    /// This is a convenience function that simply calls [`CatchPanicLayer::new`].
+    ///
+    /// [`Layer`]: tower_layer::Layer
+    pub fn layer() -> CatchPanicLayer {
+        CatchPanicLayer::new()
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, Request> Service<Request> for CatchPanic<S>
+where
+    S: Service<Request>,
+    S::Error: Into<crate::BoxError>,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        match catch_unwind(AssertUnwindSafe(|| self.inner.call(request))) {
+            Ok(future) => ResponseFuture::called(future),
+            Err(payload) => ResponseFuture::panicked(Panicked::new(payload)),
+        }
+    }
+}
+
+impl CatchPanicLayer {
+    /// Creates a new [`CatchPanicLayer`].
+    pub const fn new() -> Self {
+        CatchPanicLayer { _p: () }
+    }
+}
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanic<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanic::new(inner)
+    }
+}
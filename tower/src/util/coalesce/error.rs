@@ -0,0 +1,40 @@
+use crate::BoxError;
+use std::{error, fmt, sync::Arc};
+
+/// Error returned by [`Coalesce`] when the call shared by every caller with
+/// the same key failed.
+///
+/// The inner service's error is available through [`Error::source`].
+///
+/// [`Coalesce`]: crate::util::Coalesce
+/// [`Error::source`]: std::error::Error::source
+#[derive(Debug)]
+pub struct ServiceError {
+    inner: Arc<BoxError>,
+}
+
+impl ServiceError {
+    pub(crate) fn new(inner: BoxError) -> ServiceError {
+        let inner = Arc::new(inner);
+        ServiceError { inner }
+    }
+
+    // Private to avoid exposing `Clone` trait as part of the public API
+    pub(crate) fn clone(&self) -> ServiceError {
+        ServiceError {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "coalesced service failed: {}", self.inner)
+    }
+}
+
+impl error::Error for ServiceError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&**self.inner)
+    }
+}
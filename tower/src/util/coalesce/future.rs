@@ -0,0 +1,49 @@
+use super::in_flight::Call;
+use futures_core::TryFuture;
+use std::{
+    fmt,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// Response future for [`Coalesce`].
+///
+/// Every caller with the same key gets a [`ResponseFuture`] for the same call
+/// to the inner service. The call is driven by whichever of them is polled,
+/// and is only canceled once all of them have been dropped.
+///
+/// [`Coalesce`]: crate::util::Coalesce
+pub struct ResponseFuture<F: TryFuture, K: Hash + Eq> {
+    call: Arc<Call<F, K>>,
+}
+
+impl<F: TryFuture, K: Hash + Eq> ResponseFuture<F, K> {
+    pub(crate) fn new(call: Arc<Call<F, K>>) -> Self {
+        ResponseFuture { call }
+    }
+}
+
+impl<F, K> Future for ResponseFuture<F, K>
+where
+    F: TryFuture,
+    F::Ok: Clone,
+    F::Error: Into<crate::BoxError>,
+    K: Hash + Eq,
+{
+    type Output = Result<F::Ok, crate::BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.call.poll(cx).map_err(Into::into)
+    }
+}
+
+impl<F: TryFuture, K: Hash + Eq + fmt::Debug> fmt::Debug for ResponseFuture<F, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture")
+            .field("key", self.call.key())
+            .finish()
+    }
+}
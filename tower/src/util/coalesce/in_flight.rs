@@ -0,0 +1,174 @@
+use super::error::ServiceError;
+use futures_core::TryFuture;
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    task::{Context, Poll, Wake, Waker},
+};
+
+/// The in-flight calls of a [`Coalesce`] and its clones, by key.
+///
+/// [`Coalesce`]: crate::util::Coalesce
+pub(crate) type InFlight<F, K> = Arc<Mutex<Calls<F, K>>>;
+
+/// The in-flight calls, by key.
+pub(crate) struct Calls<F: TryFuture, K: Hash + Eq> {
+    calls: HashMap<K, Weak<Call<F, K>>>,
+}
+
+/// A call to the inner service, shared by the callers with the same key.
+pub(crate) struct Call<F: TryFuture, K: Hash + Eq> {
+    state: Mutex<State<F>>,
+    /// Wakes every caller when the call makes progress, so that it is still
+    /// driven if the caller that polled it last is dropped.
+    notifier: Arc<Notifier>,
+    waker: Waker,
+    calls: InFlight<F, K>,
+    key: K,
+}
+
+enum State<F: TryFuture> {
+    Pending(Pin<Box<F>>),
+    Done(Result<F::Ok, ServiceError>),
+}
+
+#[derive(Default)]
+struct Notifier {
+    wakers: Mutex<Vec<Waker>>,
+}
+
+// ===== impl Calls =====
+
+impl<F: TryFuture, K: Hash + Eq> Calls<F, K> {
+    pub(crate) fn new() -> Self {
+        Calls {
+            calls: HashMap::new(),
+        }
+    }
+
+    /// Returns the in-flight call for `key`, if any.
+    pub(crate) fn get(&self, key: &K) -> Option<Arc<Call<F, K>>> {
+        self.calls.get(key).and_then(Weak::upgrade)
+    }
+
+    /// Starts tracking `future` as the in-flight call for `key`.
+    ///
+    /// `this` must be the locked `in_flight`.
+    pub(crate) fn insert(
+        this: &mut Self,
+        in_flight: &InFlight<F, K>,
+        key: K,
+        future: F,
+    ) -> Arc<Call<F, K>>
+    where
+        K: Clone,
+    {
+        let notifier = Arc::new(Notifier::default());
+        let call = Arc::new(Call {
+            state: Mutex::new(State::Pending(Box::pin(future))),
+            waker: Waker::from(notifier.clone()),
+            notifier,
+            calls: in_flight.clone(),
+            key: key.clone(),
+        });
+        this.calls.insert(key, Arc::downgrade(&call));
+        call
+    }
+
+    /// Stops tracking `call`, if it is still the in-flight call for its key.
+    fn remove(&mut self, call: *const Call<F, K>, key: &K) {
+        if self
+            .calls
+            .get(key)
+            .map_or(false, |stored| std::ptr::eq(stored.as_ptr(), call))
+        {
+            self.calls.remove(key);
+        }
+    }
+}
+
+impl<F: TryFuture, K: Hash + Eq + fmt::Debug> fmt::Debug for Calls<F, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.calls.keys()).finish()
+    }
+}
+
+// ===== impl Call =====
+
+impl<F: TryFuture, K: Hash + Eq> Call<F, K> {
+    pub(crate) fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub(crate) fn poll(&self, cx: &mut Context<'_>) -> Poll<Result<F::Ok, ServiceError>>
+    where
+        F::Ok: Clone,
+        F::Error: Into<crate::BoxError>,
+    {
+        self.notifier.register(cx.waker());
+
+        let mut state = self.state.lock().expect("coalesced call poisoned");
+        let result = match *state {
+            State::Done(ref result) => return Poll::Ready(clone_result(result)),
+            State::Pending(ref mut future) => {
+                let mut cx = Context::from_waker(&self.waker);
+                match future.as_mut().try_poll(&mut cx) {
+                    Poll::Ready(result) => result.map_err(|e| ServiceError::new(e.into())),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        };
+        *state = State::Done(clone_result(&result));
+        drop(state);
+
+        // New callers must make a new call.
+        lock(&self.calls).remove(self, &self.key);
+        self.notifier.wake_by_ref();
+        Poll::Ready(result)
+    }
+}
+
+impl<F: TryFuture, K: Hash + Eq> Drop for Call<F, K> {
+    fn drop(&mut self) {
+        // Every caller gave up on the call, which cancels it.
+        lock(&self.calls).remove(self, &self.key);
+    }
+}
+
+// ===== impl Notifier =====
+
+impl Notifier {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock().expect("coalesced call poisoned");
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl Wake for Notifier {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let wakers = std::mem::take(&mut *self.wakers.lock().expect("coalesced call poisoned"));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().expect("in-flight calls poisoned")
+}
+
+fn clone_result<T: Clone>(result: &Result<T, ServiceError>) -> Result<T, ServiceError> {
+    match result {
+        Ok(response) => Ok(response.clone()),
+        Err(error) => Err(error.clone()),
+    }
+}
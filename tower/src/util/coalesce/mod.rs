@@ -0,0 +1,246 @@
+//! Contains [`Coalesce`] and related types and functions.
+//!
+//! See [`Coalesce`] documentation for more details.
+
+/// Error types for [`Coalesce`].
+pub mod error;
+/// Future types for [`Coalesce`].
+pub mod future;
+mod in_flight;
+
+use self::{
+    future::ResponseFuture,
+    in_flight::{Calls, InFlight},
+};
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Coalesces concurrent requests with the same key into a single call to the
+/// inner service.
+///
+/// A key is extracted from each request with a function. If a call for the
+/// same key is already in flight, the request joins it instead of calling the
+/// inner service, and every caller gets a clone of its response. Once the
+/// call completes, the next request with that key calls the inner service
+/// again. This is sometimes called "singleflight", and is useful to avoid
+/// flooding a backend with identical requests, for example when many callers
+/// miss the same cache entry at once.
+///
+/// The call is shared by all of its callers: it is driven by whichever of
+/// their response futures is polled, so dropping the response future of the
+/// caller that started the call doesn't cancel it for the others. The call is
+/// only canceled once every caller has dropped its response future.
+///
+/// If the call fails, every caller gets a [`ServiceError`] wrapping the inner
+/// service's error.
+///
+/// Clones of a [`Coalesce`] share their in-flight calls. Note that requests
+/// that join an in-flight call don't use the readiness that [`poll_ready`]
+/// reserved on the inner service.
+///
+/// # Examples
+///
+/// ```
+/// use tower::util::Coalesce;
+/// use tower::{service_fn, Service, ServiceExt};
+///
+/// # async fn example() -> Result<(), tower::BoxError> {
+/// let lookup = service_fn(|user_id: u64| async move {
+///     Ok::<_, tower::BoxError>(format!("user {}", user_id))
+/// });
+///
+/// // Concurrent lookups of the same user share a single call.
+/// let mut lookup = Coalesce::new(lookup, |user_id: &u64| *user_id);
+/// let name = lookup.ready().await?.call(42).await?;
+/// assert_eq!(name, "user 42");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ServiceError`]: crate::util::error::coalesce::ServiceError
+/// [`poll_ready`]: crate::Service::poll_ready
+pub struct Coalesce<S, K, Request>
+where
+    S: Service<Request>,
+    K: CoalesceKey<Request>,
+{
+    inner: S,
+    key: K,
+    in_flight: InFlight<S::Future, K::Key>,
+}
+
+/// Extracts the key by which a [`Coalesce`] coalesces requests.
+///
+/// This is implemented for all `Fn(&Request) -> Key` closures, where `Key`
+/// can be hashed, compared and cloned.
+pub trait CoalesceKey<Request> {
+    /// The key of a request.
+    type Key: Hash + Eq + Clone;
+
+    /// Returns the key of `request`.
+    fn key(&self, request: &Request) -> Self::Key;
+}
+
+/// A [`Layer`] that produces [`Coalesce`] services.
+///
+/// [`Layer`]: tower_layer::Layer
+pub struct CoalesceLayer<K, Request> {
+    key: K,
+    _p: PhantomData<fn(Request)>,
+}
+
+impl<S, K, Request> Coalesce<S, K, Request>
+where
+    S: Service<Request>,
+    K: CoalesceKey<Request>,
+{
+    /// Creates a new [`Coalesce`] service, that coalesces requests for which
+    /// `key` returns the same key.
+    pub fn new(inner: S, key: K) -> Self {
+        Coalesce {
+            inner,
+            key,
+            in_flight: Arc::new(Mutex::new(Calls::new())),
+        }
+    }
+
+    /// Returns a new [`Layer`] that produces [`Coalesce`] services.
+    ///
+    /// This is a convenience function that simply calls [`CoalesceLayer::new`].
+    ///
+    /// [`Layer`]: tower_layer::Layer
+    pub fn layer(key: K) -> CoalesceLayer<K, Request> {
+        CoalesceLayer::new(key)
+    }
+
+    /// Get a reference to the inner service
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner service
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consume `self`, returning the inner service
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, K, Request> Service<Request> for Coalesce<S, K, Request>
+where
+    S: Service<Request>,
+    S::Response: Clone,
+    S::Error: Into<crate::BoxError>,
+    K: CoalesceKey<Request>,
+{
+    type Response = S::Response;
+    type Error = crate::BoxError;
+    type Future = ResponseFuture<S::Future, K::Key>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let key = self.key.key(&request);
+        // The lock is held while calling the inner service, so that requests
+        // with the same key never call it concurrently, even from clones.
+        let mut calls = in_flight::lock(&self.in_flight);
+        if let Some(call) = calls.get(&key) {
+            return ResponseFuture::new(call);
+        }
+
+        let future = self.inner.call(request);
+        ResponseFuture::new(Calls::insert(&mut calls, &self.in_flight, key, future))
+    }
+}
+
+impl<S, K, Request> Clone for Coalesce<S, K, Request>
+where
+    S: Service<Request> + Clone,
+    K: CoalesceKey<Request> + Clone,
+{
+    fn clone(&self) -> Self {
+        Coalesce {
+            inner: self.inner.clone(),
+            key: self.key.clone(),
+            in_flight: self.in_flight.clone(),
+        }
+    }
+}
+
+impl<S, K, Request> fmt::Debug for Coalesce<S, K, Request>
+where
+    S: Service<Request> + fmt::Debug,
+    K: CoalesceKey<Request>,
+    K::Key: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coalesce")
+            .field("inner", &self.inner)
+            .field("key", &format_args!("{}", std::any::type_name::<K>()))
+            .field("in_flight", &*in_flight::lock(&self.in_flight))
+            .finish()
+    }
+}
+
+// ===== impl CoalesceKey =====
+
+impl<F, Request, Key> CoalesceKey<Request> for F
+where
+    F: Fn(&Request) -> Key,
+    Key: Hash + Eq + Clone,
+{
+    type Key = Key;
+
+    fn key(&self, request: &Request) -> Key {
+        self(request)
+    }
+}
+
+// ===== impl CoalesceLayer =====
+
+impl<K, Request> CoalesceLayer<K, Request> {
+    /// Creates a new [`CoalesceLayer`], that coalesces requests for which
+    /// `key` returns the same key.
+    pub const fn new(key: K) -> Self {
+        CoalesceLayer {
+            key,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, K, Request> Layer<S> for CoalesceLayer<K, Request>
+where
+    S: Service<Request>,
+    K: CoalesceKey<Request> + Clone,
+{
+    type Service = Coalesce<S, K, Request>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Coalesce::new(inner, self.key.clone())
+    }
+}
+
+impl<K: Clone, Request> Clone for CoalesceLayer<K, Request> {
+    fn clone(&self) -> Self {
+        CoalesceLayer::new(self.key.clone())
+    }
+}
+
+impl<K, Request> fmt::Debug for CoalesceLayer<K, Request> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoalesceLayer")
+            .field("key", &format_args!("{}", std::any::type_name::<K>()))
+            .finish()
+    }
+}
@@ -0,0 +1,58 @@
+use pin_project_lite::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+/// Hands the primary response over to the shadow request, if it was mirrored.
+pub(crate) type OnResponse<T> = Box<dyn FnOnce(&T) + Send>;
+
+pin_project! {
+    /// Response future for [`Mirror`].
+    ///
+    /// This resolves to the primary service's response, without waiting for
+    /// the shadow service.
+    ///
+    /// [`Mirror`]: crate::util::Mirror
+    pub struct ResponseFuture<F, T> {
+        #[pin]
+        future: F,
+        on_response: Option<OnResponse<T>>,
+    }
+}
+
+impl<F, T> ResponseFuture<F, T> {
+    pub(crate) fn new(future: F, on_response: Option<OnResponse<T>>) -> Self {
+        ResponseFuture {
+            future,
+            on_response,
+        }
+    }
+}
+
+impl<F, T, E> Future for ResponseFuture<F, T>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.future.poll(cx));
+        if let (Ok(response), Some(on_response)) = (&result, this.on_response.take()) {
+            on_response(response);
+        }
+        Poll::Ready(result)
+    }
+}
+
+impl<F: fmt::Debug, T> fmt::Debug for ResponseFuture<F, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture")
+            .field("future", &self.future)
+            .field("mirrored", &self.on_response.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,428 @@
+//! Contains [`Mirror`] and related types and functions.
+//!
+//! See [`Mirror`] documentation for more details.
+
+/// Future types for [`Mirror`].
+pub mod future;
+mod shadow;
+
+use self::future::{OnResponse, ResponseFuture};
+use self::shadow::Permits;
+use crate::executor::Executor;
+#[cfg(feature = "tokio-rt")]
+use crate::executor::TokioExecutor;
+use crate::util::rng::{HasherRng, Rng};
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::oneshot;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Mirrors requests to a shadow service, such as a new version of a backend,
+/// without affecting the responses of the primary service.
+///
+/// Each request is sent to the primary service, whose response is returned to
+/// the caller. A sampled fraction of the requests is also cloned by the
+/// [`Policy`], and the clone is sent to the shadow service on a background
+/// task, spawned on the Tokio runtime or with an [`Executor`]. The shadow response is discarded, but
+/// it can be compared with the primary response with [`Policy::compare`].
+///
+/// The shadow service never delays or fails primary requests: a request is
+/// only mirrored if the shadow service is ready when the request is called,
+/// and if fewer than the shadow concurrency limit of shadow requests are in
+/// flight. Otherwise, the shadow request is skipped. If the shadow service
+/// fails in [`poll_ready`], requests are no longer mirrored.
+///
+/// Clones of a [`Mirror`] share the shadow concurrency limit.
+///
+/// [`poll_ready`]: crate::Service::poll_ready
+pub struct Mirror<S, M, P = CloneRequest> {
+    primary: S,
+    shadow: M,
+    policy: Arc<P>,
+    sample: f64,
+    rng: HasherRng,
+    state: ShadowState,
+    permits: Arc<Permits>,
+    executor: Arc<dyn Executor + Send + Sync>,
+}
+
+/// A [`Layer`] that produces [`Mirror`] services.
+///
+/// The services produced by this layer share the shadow concurrency limit.
+///
+/// [`Layer`]: tower_layer::Layer
+pub struct MirrorLayer<M, P = CloneRequest> {
+    shadow: M,
+    policy: Arc<P>,
+    sample: f64,
+    permits: Arc<Permits>,
+    executor: Arc<dyn Executor + Send + Sync>,
+}
+
+/// A policy that decides which requests are mirrored to the shadow service,
+/// and compares the responses of the primary and shadow services.
+pub trait Policy<Request, Response, ShadowResponse> {
+    /// Returns a clone of `req` to send to the shadow service, or `None` if
+    /// the request must not be mirrored.
+    fn clone_request(&self, req: &Request) -> Option<Request>;
+
+    /// Returns a clone of the primary response to compare with the shadow
+    /// response, or `None` if the responses aren't compared.
+    ///
+    /// By default, responses aren't compared.
+    fn clone_response(&self, res: &Response) -> Option<Response> {
+        let _ = res;
+        None
+    }
+
+    /// Compares the primary response with the result of the shadow request.
+    ///
+    /// This is only called once both requests have completed, on the
+    /// background task of the shadow request, and only if the primary request
+    /// succeeded and [`Policy::clone_response`] cloned its response.
+    fn compare(&self, primary: &Response, shadow: &Result<ShadowResponse, crate::BoxError>) {
+        let _ = (primary, shadow);
+    }
+}
+
+/// A [`Policy`] that mirrors every request by cloning it, without comparing
+/// the responses.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CloneRequest {
+    _p: (),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ShadowState {
+    NotReady,
+    Ready,
+    Failed,
+}
+
+// ===== impl Mirror =====
+
+impl<S, M> Mirror<S, M> {
+    /// Creates a new [`Mirror`] that mirrors every request to `shadow`, with
+    /// at most `max_concurrency` shadow requests in flight, spawned on the
+    /// default Tokio executor.
+    ///
+    /// Requests are mirrored by cloning them. Use [`Mirror::policy`] to
+    /// decide which requests are mirrored, and [`Mirror::sample`] to only
+    /// mirror a fraction of them.
+    #[cfg(feature = "tokio-rt")]
+    pub fn new(primary: S, shadow: M, max_concurrency: usize) -> Self {
+        Self::with_executor(primary, shadow, max_concurrency, TokioExecutor::new())
+    }
+
+    /// Creates a new [`Mirror`] that mirrors every request to `shadow`, with
+    /// at most `max_concurrency` shadow requests in flight, spawned on
+    /// `executor`.
+    ///
+    /// See [`Mirror::new`] for details.
+    pub fn with_executor<E>(primary: S, shadow: M, max_concurrency: usize, executor: E) -> Self
+    where
+        E: Executor + Send + Sync + 'static,
+    {
+        Mirror {
+            primary,
+            shadow,
+            policy: Arc::new(CloneRequest::new()),
+            sample: 1.0,
+            rng: HasherRng::new(),
+            state: ShadowState::NotReady,
+            permits: Permits::new(max_concurrency),
+            executor: Arc::new(executor),
+        }
+    }
+
+    /// Returns a new [`Layer`] that produces [`Mirror`] services.
+    ///
+    /// This is a convenience function that simply calls [`MirrorLayer::new`].
+    ///
+    /// [`Layer`]: tower_layer::Layer
+    #[cfg(feature = "tokio-rt")]
+    pub fn layer(shadow: M, max_concurrency: usize) -> MirrorLayer<M> {
+        MirrorLayer::new(shadow, max_concurrency)
+    }
+}
+
+impl<S, M, P> Mirror<S, M, P> {
+    /// Decides which requests are mirrored, and compares the responses, with
+    /// `policy`.
+    pub fn policy<P2>(self, policy: P2) -> Mirror<S, M, P2> {
+        Mirror {
+            primary: self.primary,
+            shadow: self.shadow,
+            policy: Arc::new(policy),
+            sample: self.sample,
+            rng: self.rng,
+            state: self.state,
+            permits: self.permits,
+            executor: self.executor,
+        }
+    }
+
+    /// Only mirrors a random fraction `rate` of the requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` isn't between 0 and 1.
+    pub fn sample(mut self, rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "sample rate must be between 0 and 1"
+        );
+        self.sample = rate;
+        self
+    }
+
+    /// Get a reference to the primary service
+    pub fn get_ref(&self) -> &S {
+        &self.primary
+    }
+
+    /// Get a mutable reference to the primary service
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.primary
+    }
+
+    /// Get a reference to the shadow service
+    pub fn shadow_ref(&self) -> &M {
+        &self.shadow
+    }
+
+    /// Consume `self`, returning the primary service
+    pub fn into_inner(self) -> S {
+        self.primary
+    }
+}
+
+impl<S, M, P, Request> Service<Request> for Mirror<S, M, P>
+where
+    S: Service<Request>,
+    S::Response: Send + 'static,
+    M: Service<Request>,
+    M::Response: Send + 'static,
+    M::Future: Send + 'static,
+    M::Error: Into<crate::BoxError>,
+    P: Policy<Request, S::Response, M::Response> + Send + Sync + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, S::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The shadow service's readiness is only checked, so that it never
+        // delays primary requests.
+        if self.state == ShadowState::NotReady {
+            match self.shadow.poll_ready(cx) {
+                Poll::Ready(Ok(())) => self.state = ShadowState::Ready,
+                Poll::Ready(Err(_)) => self.state = ShadowState::Failed,
+                Poll::Pending => {}
+            }
+        }
+        self.primary.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let shadow_request = self.clone_request(&request);
+        let future = self.primary.call(request);
+        let on_response = shadow_request.map(|(request, permit)| {
+            self.state = ShadowState::NotReady;
+            let shadow = self.shadow.call(request);
+            let (handoff, receive) = oneshot::channel();
+
+            let policy = self.policy.clone();
+            self.executor.spawn(Box::pin(async move {
+                let result = shadow.await.map_err(Into::into);
+                drop(permit);
+                if let Ok(Some(primary)) = receive.await {
+                    policy.compare(&primary, &result);
+                }
+            }));
+
+            let policy = self.policy.clone();
+            Box::new(move |response: &S::Response| {
+                let _ = handoff.send(policy.clone_response(response));
+            }) as OnResponse<S::Response>
+        });
+        ResponseFuture::new(future, on_response)
+    }
+}
+
+impl<S, M, P> Mirror<S, M, P> {
+    /// Returns a clone of `request` for the shadow service, if it is mirrored.
+    fn clone_request<Request, Response, ShadowResponse>(
+        &mut self,
+        request: &Request,
+    ) -> Option<(Request, shadow::Permit)>
+    where
+        P: Policy<Request, Response, ShadowResponse>,
+    {
+        if self.state != ShadowState::Ready {
+            return None;
+        }
+        if self.sample < 1.0 && self.rng.next_f64() >= self.sample {
+            return None;
+        }
+        let permit = self.permits.try_acquire()?;
+        let request = self.policy.clone_request(request)?;
+        Some((request, permit))
+    }
+}
+
+impl<S: Clone, M: Clone, P> Clone for Mirror<S, M, P> {
+    fn clone(&self) -> Self {
+        Mirror {
+            primary: self.primary.clone(),
+            shadow: self.shadow.clone(),
+            policy: self.policy.clone(),
+            sample: self.sample,
+            rng: self.rng.clone(),
+            // Clones don't share the readiness of the shadow service.
+            state: match self.state {
+                ShadowState::Failed => ShadowState::Failed,
+                _ => ShadowState::NotReady,
+            },
+            permits: self.permits.clone(),
+            executor: self.executor.clone(),
+        }
+    }
+}
+
+impl<S, M, P> fmt::Debug for Mirror<S, M, P>
+where
+    S: fmt::Debug,
+    M: fmt::Debug,
+    P: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mirror")
+            .field("primary", &self.primary)
+            .field("shadow", &self.shadow)
+            .field("policy", &self.policy)
+            .field("sample", &self.sample)
+            .field("max_concurrency", &self.permits.max())
+            .finish()
+    }
+}
+
+// ===== impl MirrorLayer =====
+
+impl<M> MirrorLayer<M> {
+    /// Creates a new [`MirrorLayer`], that spawns shadow requests on the
+    /// default Tokio executor.
+    ///
+    /// See [`Mirror::new`] for details.
+    #[cfg(feature = "tokio-rt")]
+    pub fn new(shadow: M, max_concurrency: usize) -> Self {
+        Self::with_executor(shadow, max_concurrency, TokioExecutor::new())
+    }
+
+    /// Creates a new [`MirrorLayer`], that spawns shadow requests on
+    /// `executor`.
+    ///
+    /// See [`Mirror::new`] for details.
+    pub fn with_executor<E>(shadow: M, max_concurrency: usize, executor: E) -> Self
+    where
+        E: Executor + Send + Sync + 'static,
+    {
+        MirrorLayer {
+            shadow,
+            policy: Arc::new(CloneRequest::new()),
+            sample: 1.0,
+            permits: Permits::new(max_concurrency),
+            executor: Arc::new(executor),
+        }
+    }
+}
+
+impl<M, P> MirrorLayer<M, P> {
+    /// Decides which requests are mirrored, and compares the responses, with
+    /// `policy`.
+    ///
+    /// See [`Mirror::policy`] for details.
+    pub fn policy<P2>(self, policy: P2) -> MirrorLayer<M, P2> {
+        MirrorLayer {
+            shadow: self.shadow,
+            policy: Arc::new(policy),
+            sample: self.sample,
+            permits: self.permits,
+            executor: self.executor,
+        }
+    }
+
+    /// Only mirrors a random fraction `rate` of the requests.
+    ///
+    /// See [`Mirror::sample`] for details.
+    pub fn sample(mut self, rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "sample rate must be between 0 and 1"
+        );
+        self.sample = rate;
+        self
+    }
+}
+
+impl<S, M: Clone, P> Layer<S> for MirrorLayer<M, P> {
+    type Service = Mirror<S, M, P>;
+
+    fn layer(&self, primary: S) -> Self::Service {
+        Mirror {
+            primary,
+            shadow: self.shadow.clone(),
+            policy: self.policy.clone(),
+            sample: self.sample,
+            rng: HasherRng::new(),
+            state: ShadowState::NotReady,
+            permits: self.permits.clone(),
+            executor: self.executor.clone(),
+        }
+    }
+}
+
+impl<M: Clone, P> Clone for MirrorLayer<M, P> {
+    fn clone(&self) -> Self {
+        MirrorLayer {
+            shadow: self.shadow.clone(),
+            policy: self.policy.clone(),
+            sample: self.sample,
+            permits: self.permits.clone(),
+            executor: self.executor.clone(),
+        }
+    }
+}
+
+impl<M: fmt::Debug, P: fmt::Debug> fmt::Debug for MirrorLayer<M, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorLayer")
+            .field("shadow", &self.shadow)
+            .field("policy", &self.policy)
+            .field("sample", &self.sample)
+            .field("max_concurrency", &self.permits.max())
+            .finish()
+    }
+}
+
+// ===== impl CloneRequest =====
+
+impl CloneRequest {
+    /// Creates a new [`CloneRequest`] policy.
+    pub const fn new() -> Self {
+        CloneRequest { _p: () }
+    }
+}
+
+impl<Request, Response, ShadowResponse> Policy<Request, Response, ShadowResponse> for CloneRequest
+where
+    Request: Clone,
+{
+    fn clone_request(&self, req: &Request) -> Option<Request> {
+        Some(req.clone())
+    }
+}
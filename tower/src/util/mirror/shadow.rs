@@ -0,0 +1,53 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Caps the number of shadow requests in flight.
+#[derive(Debug)]
+pub(crate) struct Permits {
+    in_flight: AtomicUsize,
+    max: usize,
+}
+
+/// A shadow request in flight.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    permits: Arc<Permits>,
+}
+
+// ===== impl Permits =====
+
+impl Permits {
+    pub(crate) fn new(max: usize) -> Arc<Self> {
+        Arc::new(Permits {
+            in_flight: AtomicUsize::new(0),
+            max,
+        })
+    }
+
+    pub(crate) fn try_acquire(self: &Arc<Self>) -> Option<Permit> {
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                if n < self.max {
+                    Some(n + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()?;
+        Some(Permit {
+            permits: self.clone(),
+        })
+    }
+
+    pub(crate) fn max(&self) -> usize {
+        self.max
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.permits.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
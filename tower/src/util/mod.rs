@@ -5,6 +5,8 @@ mod boxed;
 mod boxed_clone;
 mod boxed_clone_sync;
 mod call_all;
+mod catch_panic;
+mod coalesce;
 mod either;
 
 mod future_service;
@@ -14,6 +16,8 @@ mod map_response;
 mod map_result;
 
 mod map_future;
+#[cfg(feature = "mirror")]
+mod mirror;
 mod oneshot;
 mod optional;
 mod ready;
@@ -29,6 +33,8 @@ pub use self::{
     },
     boxed_clone::BoxCloneService,
     boxed_clone_sync::BoxCloneSyncService,
+    catch_panic::{CatchPanic, CatchPanicLayer},
+    coalesce::{Coalesce, CoalesceKey, CoalesceLayer},
     either::Either,
     future_service::{future_service, FutureService},
     map_err::{MapErr, MapErrLayer},
@@ -44,11 +50,14 @@ pub use self::{
 };
 
 pub use self::call_all::{CallAll, CallAllUnordered};
+#[cfg(feature = "mirror")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mirror")))]
+pub use self::mirror::{CloneRequest, Mirror, MirrorLayer, Policy as MirrorPolicy};
 use std::future::Future;
 
 use crate::layer::util::Identity;
 
-#[cfg(feature = "buffer")]
+#[cfg(all(feature = "buffer", feature = "tokio-rt"))]
 use crate::buffer::Buffer;
 
 #[cfg(feature = "retry")]
@@ -57,6 +66,8 @@ use crate::retry::Retry;
 pub mod error {
     //! Error types
 
+    pub use super::catch_panic::error as catch_panic;
+    pub use super::coalesce::error as coalesce;
     pub use super::optional::error as optional;
 }
 
@@ -64,10 +75,15 @@ pub mod future {
     //! Future types
 
     pub use super::and_then::AndThenFuture;
+    pub use super::catch_panic::future as catch_panic;
+    pub use super::coalesce::future as coalesce;
     pub use super::either::EitherResponseFuture;
     pub use super::map_err::MapErrFuture;
     pub use super::map_response::MapResponseFuture;
     pub use super::map_result::MapResultFuture;
+    #[cfg(feature = "mirror")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mirror")))]
+    pub use super::mirror::future as mirror;
     pub use super::optional::future as optional;
     pub use super::then::ThenFuture;
 }
@@ -953,7 +969,7 @@ pub trait ServiceExt<Request>: tower_service::Service<Request> {
     /// Returns a buffered version of this service.
     ///
     /// See [`Buffer::new()`] for the details.
-    #[cfg(feature = "buffer")]
+    #[cfg(all(feature = "buffer", feature = "tokio-rt"))]
     fn buffered(self, bound: usize) -> Buffer<Request, Self::Future>
     where
         Self: Send + Sized + 'static,
@@ -169,3 +169,172 @@ fn stress() {
         }
     }
 }
+
+#[cfg(feature = "discover-util")]
+mod drain {
+    use super::*;
+    use tower::discover;
+    use tower::load::{CompleteOnResponse, PendingRequests};
+
+    #[tokio::test]
+    async fn removed_endpoint_is_dropped_without_draining() {
+        let _t = support::trace_init();
+        let (tx, rx) = discover::channel();
+        let mut balance = mock::Spawn::new(Balance::<_, Req>::new(rx));
+
+        let (svc, mut handle) = mock::pair::<Req, Req>();
+        tx.insert(1, PendingRequests::new(svc, CompleteOnResponse::default()))
+            .unwrap();
+        handle.allow(1);
+        assert_ready!(balance.poll_ready()).unwrap();
+        let _rsp = balance.call("hello");
+
+        tx.remove(1).unwrap();
+        assert_pending!(balance.poll_ready());
+        assert_eq!(balance.get_ref().draining_len(), 0);
+        let (req, _send) = handle.next_request().await.unwrap();
+        assert_eq!(req, "hello");
+        assert!(
+            handle.next_request().await.is_none(),
+            "service must be dropped"
+        );
+    }
+
+    /// Drops every task it is given, like an executor that is shutting down.
+    struct DroppingExecutor;
+
+    impl tower::executor::Executor for DroppingExecutor {
+        fn spawn(&self, future: tower::executor::BoxFuture) {
+            drop(future);
+        }
+    }
+
+    #[tokio::test]
+    async fn dropped_drain_task_is_not_counted() {
+        let _t = support::trace_init();
+        let (tx, rx) = discover::channel();
+        let balance = Balance::<_, Req>::new(rx)
+            .drain_removed_with_executor(std::time::Duration::from_secs(10), DroppingExecutor);
+        let mut balance = mock::Spawn::new(balance);
+
+        let (svc, mut handle) = mock::pair::<Req, Req>();
+        tx.insert(1, PendingRequests::new(svc, CompleteOnResponse::default()))
+            .unwrap();
+        handle.allow(1);
+        assert_ready!(balance.poll_ready()).unwrap();
+
+        tx.remove(1).unwrap();
+        assert_pending!(balance.poll_ready());
+        assert_eq!(balance.get_ref().draining_len(), 0);
+        assert!(
+            handle.next_request().await.is_none(),
+            "service must be dropped"
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    mod executor {
+        use super::*;
+        use std::time::Duration;
+        use support::TestExecutor;
+        use tower::balance::p2c::Drained;
+
+        type Drains = std::sync::Arc<std::sync::Mutex<Vec<(usize, Drained)>>>;
+
+        type DrainingBalance = mock::Spawn<Balance<discover::Receiver<usize, PendingMock>, Req>>;
+
+        fn draining_balance(
+            timeout: Duration,
+        ) -> (
+            DrainingBalance,
+            discover::Sender<usize, PendingMock>,
+            Drains,
+            TestExecutor,
+        ) {
+            let (tx, rx) = discover::channel();
+            let drains = Drains::default();
+            let on_drained = drains.clone();
+            let executor = TestExecutor::new();
+            // The hook may be set before draining is enabled.
+            let balance = Balance::new(rx)
+                .on_drained(move |key: &usize, drained| {
+                    on_drained.lock().unwrap().push((*key, drained))
+                })
+                .drain_removed_with_executor(timeout, executor.clone());
+            (mock::Spawn::new(balance), tx, drains, executor)
+        }
+
+        type PendingMock = PendingRequests<mock::Mock<Req, Req>, CompleteOnResponse>;
+
+        #[tokio::test(flavor = "current_thread", start_paused = true)]
+        async fn drains_removed_endpoint_until_requests_complete() {
+            let _t = support::trace_init();
+            let (mut balance, tx, drains, executor) = draining_balance(Duration::from_secs(10));
+
+            let (svc, mut handle) = mock::pair::<Req, Req>();
+            tx.insert(1, PendingRequests::new(svc, CompleteOnResponse::default()))
+                .unwrap();
+            handle.allow(1);
+            assert_ready!(balance.poll_ready()).unwrap();
+            let mut rsp = task::spawn(balance.call("hello"));
+
+            tx.remove(1).unwrap();
+            handle.allow(1);
+            assert_pending!(balance.poll_ready());
+            assert_eq!(balance.get_ref().len(), 0);
+            assert_eq!(balance.get_ref().draining_len(), 1);
+            assert_eq!(executor.len(), 1);
+
+            // The removed service is kept while its request is outstanding.
+            let mut runner = task::spawn(());
+            assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+            let (req, send) = handle.next_request().await.unwrap();
+            assert_eq!(req, "hello");
+            assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+            assert_eq!(balance.get_ref().draining_len(), 1);
+            assert!(drains.lock().unwrap().is_empty());
+
+            // Completing the request finishes the drain, without polling the
+            // balancer.
+            send.send_response("world");
+            assert_eq!(assert_ready!(rsp.poll()).unwrap(), "world");
+            assert!(runner.is_woken(), "completion must wake the drain task");
+            assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+            assert_eq!(balance.get_ref().draining_len(), 0);
+            assert_eq!(*drains.lock().unwrap(), vec![(1, Drained::Completed)]);
+            assert!(
+                handle.next_request().await.is_none(),
+                "service must be dropped"
+            );
+        }
+
+        #[tokio::test(flavor = "current_thread", start_paused = true)]
+        async fn drain_times_out() {
+            let _t = support::trace_init();
+            let (mut balance, tx, drains, executor) = draining_balance(Duration::from_secs(10));
+
+            let (svc, mut handle) = mock::pair::<Req, Req>();
+            tx.insert(1, PendingRequests::new(svc, CompleteOnResponse::default()))
+                .unwrap();
+            handle.allow(1);
+            assert_ready!(balance.poll_ready()).unwrap();
+            let _rsp = balance.call("hello");
+
+            tx.remove(1).unwrap();
+            assert_pending!(balance.poll_ready());
+            assert_eq!(balance.get_ref().draining_len(), 1);
+
+            let mut runner = task::spawn(());
+            assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+            tokio::time::advance(Duration::from_secs(5)).await;
+            assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+            assert_eq!(balance.get_ref().draining_len(), 1);
+
+            tokio::time::advance(Duration::from_secs(6)).await;
+            assert!(runner.is_woken(), "drain timeout must wake the drain task");
+            assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+            assert_eq!(balance.get_ref().draining_len(), 0);
+            assert_eq!(*drains.lock().unwrap(), vec![(1, Drained::TimedOut)]);
+        }
+    }
+}
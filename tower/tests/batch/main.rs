@@ -0,0 +1,215 @@
+#![cfg(all(feature = "batch", feature = "tokio-rt"))]
+#[path = "../support.rs"]
+mod support;
+
+use std::time::Duration;
+#[cfg(feature = "test-util")]
+use support::TestExecutor;
+use tokio_test::{assert_pending, assert_ready, assert_ready_err, assert_ready_ok, task};
+use tower::batch::{error, Batch};
+use tower_test::{assert_request_eq, mock};
+
+type Handle = mock::Handle<Vec<&'static str>, Vec<&'static str>>;
+
+const LATENCY: Duration = Duration::from_millis(10);
+
+#[tokio::test(flavor = "current_thread")]
+async fn flushes_full_batch() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = new_service(2);
+    let (service, worker) = Batch::pair(service, 2, LATENCY);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut one = task::spawn(service.call("one"));
+    assert_ready_ok!(service.poll_ready());
+    let mut two = task::spawn(service.call("two"));
+
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, vec!["one", "two"]).send_response(vec!["1", "2"]);
+    assert_pending!(one.poll());
+
+    assert_pending!(worker.poll());
+    assert_eq!(assert_ready_ok!(one.poll()), "1");
+    assert_eq!(assert_ready_ok!(two.poll()), "2");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn flushes_after_max_latency() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = new_service(1);
+    let (service, worker) = Batch::pair(service, 10, LATENCY);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut one = task::spawn(service.call("one"));
+
+    // The batch isn't full yet.
+    assert_pending!(worker.poll());
+    assert_pending!(handle.poll_request());
+
+    tokio::time::advance(LATENCY).await;
+    assert!(worker.is_woken());
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, vec!["one"]).send_response(vec!["1"]);
+
+    assert_pending!(worker.poll());
+    assert_eq!(assert_ready_ok!(one.poll()), "1");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn flushes_by_size_with_huge_max_latency() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = new_service(1);
+    let (service, worker) = Batch::pair(service, 2, Duration::MAX);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut one = task::spawn(service.call("one"));
+    assert_pending!(worker.poll());
+    tokio::time::advance(Duration::from_secs(86400)).await;
+    assert_pending!(worker.poll());
+    assert_pending!(handle.poll_request());
+
+    assert_ready_ok!(service.poll_ready());
+    let mut two = task::spawn(service.call("two"));
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, vec!["one", "two"]).send_response(vec!["1", "2"]);
+
+    assert_pending!(worker.poll());
+    assert_eq!(assert_ready_ok!(one.poll()), "1");
+    assert_eq!(assert_ready_ok!(two.poll()), "2");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn batch_error_fails_every_request() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = new_service(1);
+    let (service, worker) = Batch::pair(service, 2, LATENCY);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut one = task::spawn(service.call("one"));
+    assert_ready_ok!(service.poll_ready());
+    let mut two = task::spawn(service.call("two"));
+
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, vec!["one", "two"]).send_error("boom");
+    assert_pending!(worker.poll());
+
+    for response in [&mut one, &mut two].iter_mut() {
+        let err = assert_ready_err!(response.poll());
+        assert!(
+            err.is::<error::ServiceError>(),
+            "should be a ServiceError: {:?}",
+            err
+        );
+    }
+
+    // The batch service is still usable.
+    assert_ready_ok!(service.poll_ready());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn mismatched_responses() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = new_service(1);
+    let (service, worker) = Batch::pair(service, 2, LATENCY);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut one = task::spawn(service.call("one"));
+    assert_ready_ok!(service.poll_ready());
+    let mut two = task::spawn(service.call("two"));
+
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, vec!["one", "two"]).send_response(vec!["1"]);
+    assert_pending!(worker.poll());
+
+    for response in [&mut one, &mut two].iter_mut() {
+        let err = assert_ready_err!(response.poll());
+        let err = err
+            .downcast_ref::<error::Mismatch>()
+            .expect("should be a Mismatch");
+        assert_eq!((err.requests(), err.responses()), (2, 1));
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn when_inner_fails() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = new_service(0);
+    let (service, worker) = Batch::pair(service, 1, LATENCY);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut one = task::spawn(service.call("one"));
+
+    handle.send_error("foobar");
+    assert_ready!(worker.poll());
+
+    let err = assert_ready_err!(one.poll());
+    assert!(
+        err.is::<error::ServiceError>(),
+        "should be a ServiceError: {:?}",
+        err
+    );
+    let err = assert_ready_err!(service.poll_ready());
+    assert!(
+        err.is::<error::ServiceError>(),
+        "should be a ServiceError: {:?}",
+        err
+    );
+}
+
+#[cfg(feature = "test-util")]
+#[tokio::test(flavor = "current_thread")]
+async fn runs_worker_on_executor() {
+    let _t = support::trace_init();
+
+    let executor = TestExecutor::new();
+    let (service, mut handle) = new_service(1);
+    let service = Batch::with_executor(service, 1, LATENCY, &executor);
+    let mut service = mock::Spawn::new(service);
+    assert_eq!(executor.len(), 1);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut one = task::spawn(service.call("one"));
+
+    let mut runner = task::spawn(());
+    assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+    assert_request_eq!(handle, vec!["one"]).send_response(vec!["1"]);
+    assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+    assert_eq!(assert_ready_ok!(one.poll()), "1");
+}
+
+#[test]
+fn pair_outside_runtime() {
+    let _t = support::trace_init();
+
+    let (service, _handle) = new_service(1);
+    let (service, worker) = Batch::pair(service, 2, LATENCY);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    assert_ready_ok!(service.poll_ready());
+    assert_pending!(worker.poll());
+}
+
+fn new_service(allow: u64) -> (mock::Mock<Vec<&'static str>, Vec<&'static str>>, Handle) {
+    let (service, mut handle) = mock::pair();
+    handle.allow(allow);
+    (service, handle)
+}
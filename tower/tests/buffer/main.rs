@@ -1,9 +1,13 @@
-#![cfg(feature = "buffer")]
+#![cfg(all(feature = "buffer", feature = "tokio-rt"))]
 #[path = "../support.rs"]
 mod support;
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
+#[cfg(feature = "test-util")]
+use support::TestExecutor;
 use tokio_test::{assert_pending, assert_ready, assert_ready_err, assert_ready_ok, task};
-use tower::buffer::{error, Buffer};
+use tower::buffer::{error, Buffer, Builder, Codel, Overflow};
 use tower::{util::ServiceExt, Service};
 use tower_test::{assert_request_eq, mock};
 
@@ -437,6 +441,650 @@ async fn doesnt_leak_permits() {
     assert_ready_ok!(ready3.poll());
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn codel_sheds_requests_from_standing_queue() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let codel = Codel::new(Duration::from_millis(10), Duration::from_millis(100));
+    let (service, worker) = Builder::new(10).codel(codel).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    // keep requests in the worker
+    handle.allow(0);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("hello1"));
+    assert_pending!(worker.poll());
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call("hello2"));
+    assert_ready_ok!(service.poll_ready());
+    let mut response3 = task::spawn(service.call("hello3"));
+
+    // The first request was dispatched without delay, so the queue isn't
+    // considered to be overloaded yet, even though the second request waited
+    // for a long time.
+    tokio::time::advance(Duration::from_millis(200)).await;
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "hello1").send_response("world1");
+    assert_pending!(worker.poll());
+    assert_eq!(assert_ready_ok!(response1.poll()), "world1");
+    assert_pending!(response2.poll());
+
+    // Over the next interval, every request waited past the target, so the
+    // queue is standing. The second request, which kept waiting for the
+    // service to become ready, and the third request are shed.
+    tokio::time::advance(Duration::from_millis(200)).await;
+    assert_pending!(worker.poll());
+    for response in [&mut response2, &mut response3] {
+        let err = assert_ready_err!(response.poll());
+        assert!(err.is::<error::Shed>(), "should be a Shed: {:?}", err);
+    }
+
+    // Requests that are dispatched promptly are not shed.
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+    let mut response4 = task::spawn(service.call("hello4"));
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "hello4").send_response("world4");
+    assert_pending!(worker.poll());
+    assert_eq!(assert_ready_ok!(response4.poll()), "world4");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn codel_sheds_requests_waiting_for_readiness() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let codel = Codel::new(Duration::from_millis(10), Duration::from_millis(100));
+    let (service, worker) = Builder::new(10).codel(codel).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    // The request is taken off the queue right away, but the service never
+    // becomes ready.
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+    assert_pending!(worker.poll());
+
+    tokio::time::advance(Duration::from_millis(200)).await;
+    assert_pending!(worker.poll());
+    assert_pending!(response.poll());
+
+    // Over the last interval, the request kept waiting past the target, so it
+    // is shed before it can be dispatched.
+    tokio::time::advance(Duration::from_millis(200)).await;
+    assert_pending!(worker.poll());
+    let err = assert_ready_err!(response.poll());
+    assert!(err.is::<error::Shed>(), "should be a Shed: {:?}", err);
+
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_pending!(handle.poll_request());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn codel_dispatches_newest_requests_first_while_overloaded() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let codel = Codel::new(Duration::from_millis(10), Duration::from_millis(100));
+    let (service, worker) = Builder::new(10).codel(codel).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("hello1"));
+    assert_pending!(worker.poll());
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call("hello2"));
+
+    // The second request waits past the target...
+    tokio::time::advance(Duration::from_millis(200)).await;
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "hello1").send_response("world1");
+    assert_eq!(assert_ready_ok!(response1.poll()), "world1");
+
+    // ... so the queue is overloaded over the next interval.
+    tokio::time::advance(Duration::from_millis(200)).await;
+    assert_pending!(worker.poll());
+    let err = assert_ready_err!(response2.poll());
+    assert!(err.is::<error::Shed>(), "should be a Shed: {:?}", err);
+
+    // While overloaded, the newest requests are dispatched first.
+    let mut responses = Vec::new();
+    for req in ["a", "b", "c"] {
+        assert_ready_ok!(service.poll_ready());
+        responses.push(task::spawn(service.call(req)));
+    }
+    handle.allow(3);
+    assert_pending!(worker.poll());
+    for req in ["c", "b", "a"] {
+        assert_request_eq!(handle, req).send_response(req);
+    }
+    for (response, req) in responses.iter_mut().zip(["a", "b", "c"]) {
+        assert_eq!(assert_ready_ok!(response.poll()), req);
+    }
+}
+
+#[cfg(feature = "test-util")]
+#[tokio::test(flavor = "current_thread")]
+async fn runs_worker_on_executor() {
+    let _t = support::trace_init();
+
+    let executor = TestExecutor::new();
+    let (mut service, mut handle) = mock::spawn_with(|s| Buffer::with_executor(s, 10, &executor));
+    assert_eq!(executor.len(), 1, "worker must be spawned on the executor");
+
+    let mut worker = task::spawn(());
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+
+    // The request is only dispatched once the executor runs the worker.
+    assert_pending!(handle.poll_request());
+    assert_pending!(worker.enter(|cx, _| executor.poll(cx)));
+    let (req, send_response) = assert_ready!(handle.poll_request()).unwrap();
+    assert_eq!(req, "hello");
+    send_response.send_response("world");
+
+    assert_pending!(worker.enter(|cx, _| executor.poll(cx)));
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+
+    drop(service);
+    assert_ready!(worker.enter(|cx, _| executor.poll(cx)));
+    assert!(executor.is_empty());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn shutdown_dispatches_queued_requests() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Buffer::pair(service, 10);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+    assert_pending!(worker.poll());
+
+    let shutdown = service.get_ref().shutdown_handle();
+    let mut drained = task::spawn(shutdown.shutdown());
+    assert!(shutdown.is_shutdown());
+    assert_pending!(drained.poll());
+    assert!(worker.is_woken(), "shutdown must wake the worker");
+    assert_pending!(worker.poll());
+
+    // New requests are rejected...
+    let err = assert_ready_err!(service.poll_ready());
+    assert!(err.is::<error::Closed>(), "should be a Closed: {:?}", err);
+
+    // ...but the queued request is still dispatched.
+    handle.allow(1);
+    assert_ready!(worker.poll());
+    assert!(drained.is_woken());
+    assert_ready!(drained.poll());
+
+    assert_request_eq!(handle, "hello").send_response("world");
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn shutdown_rejects_requests_before_worker_is_polled() {
+    let _t = support::trace_init();
+
+    let (service, _handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Buffer::pair(service, 10);
+    let mut service = mock::Spawn::new(service);
+    let mut other = mock::Spawn::new(service.get_ref().clone());
+    let mut worker = task::spawn(worker);
+    assert_pending!(worker.poll());
+
+    // One handle has already reserved capacity.
+    assert_ready_ok!(other.poll_ready());
+
+    let _drained = service.get_ref().shutdown_handle().shutdown();
+    let err = assert_ready_err!(service.poll_ready());
+    assert!(err.is::<error::Closed>(), "should be a Closed: {:?}", err);
+    let err = other.call("hello").await.unwrap_err();
+    assert!(err.is::<error::Closed>(), "should be a Closed: {:?}", err);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn drained_when_worker_is_dropped() {
+    let _t = support::trace_init();
+
+    let (service, _handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Buffer::pair(service, 10);
+
+    let shutdown = service.shutdown_handle();
+    let mut drained = task::spawn(shutdown.drained());
+    assert_pending!(drained.poll());
+    assert!(!shutdown.is_shutdown());
+
+    drop(worker);
+    assert!(drained.is_woken());
+    assert_ready!(drained.poll());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn records_metrics() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Buffer::pair(service, 10);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+    let metrics = service.get_ref().metrics();
+    assert_eq!(metrics.capacity(), 10);
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("hello"));
+    assert_ready_ok!(service.poll_ready());
+    let response2 = service.call("world");
+    assert_eq!(metrics.depth(), 2);
+    assert_eq!(metrics.enqueued(), 2);
+
+    tokio::time::advance(Duration::from_millis(3)).await;
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_eq!(metrics.depth(), 1);
+    assert_request_eq!(handle, "hello").send_response("hi");
+    assert_eq!(assert_ready_ok!(response1.poll()), "hi");
+
+    // The second request is canceled before it is dispatched.
+    drop(response2);
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_eq!(metrics.depth(), 0);
+    assert_eq!(metrics.enqueued(), 2);
+    assert_eq!(metrics.dropped(), 1);
+
+    let wait = metrics.queue_wait();
+    assert_eq!(wait.count(), 1);
+    assert_eq!(wait.sum(), Duration::from_millis(3));
+    assert_eq!(wait.quantile(0.5), Some(Duration::from_micros(4096)));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn workers_dispatch_in_parallel() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, workers) = Builder::new(10).workers(2).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut workers = workers.into_iter().map(task::spawn).collect::<Vec<_>>();
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("one"));
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call("two"));
+
+    // Each worker takes a request, and waits for its service to become ready.
+    assert_pending!(workers[0].poll());
+    assert_pending!(workers[1].poll());
+
+    // The second request doesn't have to wait for the first one.
+    handle.allow(1);
+    assert_pending!(workers[1].poll());
+    assert_request_eq!(handle, "two").send_response("2");
+    assert_eq!(assert_ready_ok!(response2.poll()), "2");
+
+    handle.allow(1);
+    assert_pending!(workers[0].poll());
+    assert_request_eq!(handle, "one").send_response("1");
+    assert_eq!(assert_ready_ok!(response1.poll()), "1");
+
+    // The buffer is drained once all workers have completed.
+    let mut drained = task::spawn(service.get_ref().shutdown_handle().drained());
+    drop(service);
+    assert_ready!(workers[0].poll());
+    assert_pending!(drained.poll());
+    assert_ready!(workers[1].poll());
+    assert_ready!(drained.poll());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn workers_fail_together() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, workers) = Builder::new(10).workers(2).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut workers = workers.into_iter().map(task::spawn).collect::<Vec<_>>();
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("one"));
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call("two"));
+    assert_ready_ok!(service.poll_ready());
+    let mut response3 = task::spawn(service.call("three"));
+
+    // Each worker takes a request, and waits for its service to become ready.
+    assert_pending!(workers[0].poll());
+    assert_pending!(workers[1].poll());
+
+    handle.send_error("foobar");
+    assert_ready!(workers[0].poll());
+    let err = assert_ready_err!(response1.poll());
+    assert!(
+        err.is::<error::ServiceError>(),
+        "response should fail with a ServiceError, got: {:?}",
+        err
+    );
+
+    // The other worker doesn't dispatch its request once its service is
+    // ready, but fails it, as well as the requests left in the queue.
+    assert!(workers[1].is_woken());
+    handle.allow(2);
+    assert_ready!(workers[1].poll());
+    for response in [&mut response2, &mut response3] {
+        let err = assert_ready_err!(response.poll());
+        assert!(
+            err.is::<error::ServiceError>(),
+            "response should fail with a ServiceError, got: {:?}",
+            err
+        );
+    }
+    assert_pending!(handle.poll_request());
+
+    let err = assert_ready_err!(service.poll_ready());
+    assert!(err.is::<error::ServiceError>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn workers_wake_idle_workers() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, workers) = Builder::new(10).workers(2).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut workers = workers.into_iter().map(task::spawn).collect::<Vec<_>>();
+
+    handle.allow(0);
+    assert_pending!(workers[0].poll());
+    assert_pending!(workers[1].poll());
+
+    assert_ready_ok!(service.poll_ready());
+    let _response1 = service.call("one");
+    assert_ready_ok!(service.poll_ready());
+    let _response2 = service.call("two");
+
+    // Only one worker is woken by the channel, and it wakes the other one
+    // when it takes a request.
+    let (woken, idle) = if workers[0].is_woken() {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+    assert!(!workers[idle].is_woken());
+    assert_pending!(workers[woken].poll());
+    assert!(workers[idle].is_woken());
+    assert_pending!(workers[idle].poll());
+
+    handle.allow(2);
+    assert_pending!(workers[0].poll());
+    assert_pending!(workers[1].poll());
+    assert_eq!(service.get_ref().metrics().depth(), 0);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn overflow_reject() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Builder::new(1).overflow(Overflow::Reject).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("one"));
+
+    // The queue is full, but the buffer remains ready.
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call("two"));
+    let err = assert_ready_err!(response2.poll());
+    assert!(err.is::<error::Full>(), "should be a Full: {:?}", err);
+
+    let metrics = service.get_ref().metrics();
+    assert_eq!(metrics.depth(), 1);
+    assert_eq!(metrics.enqueued(), 2);
+    assert_eq!(metrics.dropped(), 1);
+
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "one").send_response("1");
+    assert_eq!(assert_ready_ok!(response1.poll()), "1");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn metrics_count_requests_dropped_with_the_queue() {
+    let _t = support::trace_init();
+
+    for overflow in [Overflow::Wait, Overflow::Reject] {
+        let (service, _handle) = mock::pair::<&'static str, &'static str>();
+        let (service, worker) = Builder::new(10).overflow(overflow).pair(service);
+        assert_dropped_with_workers(service, vec![worker]);
+
+        let (service, _handle) = mock::pair::<&'static str, &'static str>();
+        let (service, workers) = Builder::new(10).overflow(overflow).workers(2).pair(service);
+        assert_dropped_with_workers(service, workers);
+    }
+}
+
+fn assert_dropped_with_workers<W>(service: MockBuffer, workers: Vec<W>) {
+    let mut service = mock::Spawn::new(service);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("one"));
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call("two"));
+
+    // The workers are dropped without dispatching the queued requests.
+    drop(workers);
+    assert_ready_err!(response1.poll());
+    assert_ready_err!(response2.poll());
+
+    let metrics = service.get_ref().metrics();
+    assert_eq!(metrics.depth(), 0);
+    assert_eq!(metrics.enqueued(), 2);
+    assert_eq!(metrics.dropped(), 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn metrics_count_request_held_by_dropped_worker() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Buffer::pair(service, 10);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+
+    // The worker takes the request off the queue, and waits for the service
+    // to become ready.
+    assert_pending!(worker.poll());
+    assert_eq!(service.get_ref().metrics().depth(), 1);
+
+    drop(worker);
+    assert_ready_err!(response.poll());
+
+    let metrics = service.get_ref().metrics();
+    assert_eq!(metrics.depth(), 0);
+    assert_eq!(metrics.dropped(), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn overflow_drop_oldest() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Builder::new(1).overflow(Overflow::DropOldest).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+    let metrics = service.get_ref().metrics();
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call("one"));
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call("two"));
+
+    let err = assert_ready_err!(response1.poll());
+    assert!(err.is::<error::Full>(), "should be a Full: {:?}", err);
+    assert_eq!(metrics.dropped(), 1);
+    assert_eq!(metrics.depth(), 1);
+
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "two").send_response("2");
+    assert_eq!(assert_ready_ok!(response2.poll()), "2");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn overflow_unbounded() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Builder::new(1).overflow(Overflow::Unbounded).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    let mut responses = Vec::new();
+    for req in ["one", "two", "three"] {
+        assert_ready_ok!(service.poll_ready());
+        responses.push(task::spawn(service.call(req)));
+    }
+    assert_eq!(service.get_ref().metrics().depth(), 3);
+
+    handle.allow(3);
+    assert_pending!(worker.poll());
+    for (&req, response) in ["one", "two", "three"].iter().zip(&mut responses) {
+        assert_request_eq!(handle, req).send_response(req);
+        assert_eq!(assert_ready_ok!(response.poll()), req);
+    }
+
+    // Dropping the worker closes the buffer.
+    drop(worker);
+    let err = assert_ready_err!(service.poll_ready());
+    assert!(err.is::<error::Closed>(), "should be a Closed: {:?}", err);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn priority_dispatches_urgent_requests_first() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Builder::new(2).priority(classify).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut bulk = task::spawn(service.call("bulk"));
+    assert_ready_ok!(service.poll_ready());
+    let mut health = task::spawn(service.call("health"));
+
+    // The queue is full.
+    assert_pending!(service.poll_ready());
+
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "health").send_response("ok");
+    assert_eq!(assert_ready_ok!(health.poll()), "ok");
+
+    // Dispatching a request makes room in the queue.
+    assert!(service.is_woken());
+    assert_ready_ok!(service.poll_ready());
+
+    handle.allow(1);
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "bulk").send_response("done");
+    assert_eq!(assert_ready_ok!(bulk.poll()), "done");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn priority_doesnt_starve_other_requests() {
+    let _t = support::trace_init();
+
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Builder::new(32).priority(classify).pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    let mut responses = Vec::new();
+    assert_ready_ok!(service.poll_ready());
+    responses.push(service.call("bulk"));
+    for _ in 0..20 {
+        assert_ready_ok!(service.poll_ready());
+        responses.push(service.call("health"));
+    }
+
+    handle.allow(21);
+    assert_pending!(worker.poll());
+    for _ in 0..16 {
+        assert_request_eq!(handle, "health").send_response("ok");
+    }
+    assert_request_eq!(handle, "bulk").send_response("done");
+    for _ in 0..4 {
+        assert_request_eq!(handle, "health").send_response("ok");
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn priority_with_capturing_classifier() {
+    let _t = support::trace_init();
+
+    let urgent = Arc::new(String::from("control"));
+    let (service, mut handle) = mock::pair::<&'static str, &'static str>();
+    let (service, worker) = Builder::new(2)
+        .priority(move |req: &&'static str| u8::from(**req == *urgent))
+        .pair(service);
+    let mut service = mock::Spawn::new(service);
+    let mut worker = task::spawn(worker);
+
+    handle.allow(0);
+    assert_ready_ok!(service.poll_ready());
+    let mut bulk = task::spawn(service.call("bulk"));
+    // Clones of the buffer share the classifier.
+    let mut service2 = mock::Spawn::new(service.get_ref().clone());
+    assert_ready_ok!(service2.poll_ready());
+    let mut control = task::spawn(service2.call("control"));
+
+    handle.allow(2);
+    assert_pending!(worker.poll());
+    assert_request_eq!(handle, "control").send_response("ok");
+    assert_request_eq!(handle, "bulk").send_response("done");
+    assert_eq!(assert_ready_ok!(control.poll()), "ok");
+    assert_eq!(assert_ready_ok!(bulk.poll()), "done");
+}
+
+fn classify(req: &&'static str) -> u8 {
+    match *req {
+        "health" => 1,
+        _ => 0,
+    }
+}
+
 type Handle = mock::Handle<&'static str, &'static str>;
 type MockBuffer = Buffer<&'static str, mock::future::ResponseFuture<&'static str>>;
 
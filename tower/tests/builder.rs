@@ -1,4 +1,9 @@
-#![cfg(all(feature = "buffer", feature = "limit", feature = "retry"))]
+#![cfg(all(
+    feature = "buffer",
+    feature = "limit",
+    feature = "retry",
+    feature = "tokio-rt"
+))]
 mod support;
 use futures_util::pin_mut;
 use std::{future::Ready, time::Duration};
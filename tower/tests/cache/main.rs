@@ -0,0 +1,232 @@
+#![cfg(all(feature = "cache", feature = "test-util", feature = "tokio-rt"))]
+#[path = "../support.rs"]
+mod support;
+
+use std::time::Duration;
+use support::TestExecutor;
+use tokio::time;
+use tokio_test::{assert_pending, assert_ready, assert_ready_err, assert_ready_ok, task};
+use tower::cache::{Cache, Expiry, MemoryStore, Store};
+use tower_test::{assert_request_eq, mock};
+
+const TTL: Duration = Duration::from_secs(10);
+
+type Mock = mock::Mock<&'static str, &'static str>;
+type Handle = mock::Handle<&'static str, &'static str>;
+type Key = fn(&&'static str) -> Option<&'static str>;
+type Policy = fn(&&'static str) -> Option<Expiry>;
+type MockCache<P> = Cache<Mock, Key, P, MemoryStore<&'static str, &'static str>>;
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn serves_fresh_responses() {
+    let _t = support::trace_init();
+    let (mut service, mut handle) = new_service(Expiry::new(TTL), 10);
+
+    assert_eq!(miss(&mut service, &mut handle, "a", "1").await, "1");
+    assert_eq!(hit(&mut service, &mut handle, "a"), "1");
+
+    // Responses expire after their time-to-live.
+    time::advance(TTL).await;
+    assert_eq!(miss(&mut service, &mut handle, "a", "2").await, "2");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn bypasses_requests_without_key() {
+    let _t = support::trace_init();
+    let (mut service, mut handle) = new_service(Expiry::new(TTL), 10);
+
+    assert_eq!(miss(&mut service, &mut handle, "uncached", "1").await, "1");
+    assert_eq!(miss(&mut service, &mut handle, "uncached", "2").await, "2");
+    assert!(service.get_ref().store().is_empty());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn follows_cacheability_policy() {
+    let _t = support::trace_init();
+    let policy: Policy = |response| match *response {
+        "private" => None,
+        _ => Some(Expiry::new(TTL)),
+    };
+    let (mut service, mut handle) = new_service(policy, 10);
+
+    assert_eq!(
+        miss(&mut service, &mut handle, "a", "private").await,
+        "private"
+    );
+    assert_eq!(
+        miss(&mut service, &mut handle, "a", "public").await,
+        "public"
+    );
+    assert_eq!(hit(&mut service, &mut handle, "a"), "public");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn doesnt_cache_errors() {
+    let _t = support::trace_init();
+    let (mut service, mut handle) = new_service(Expiry::new(TTL), 10);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("a"));
+    assert_pending!(response.poll());
+    assert_request_eq!(handle, "a").send_error("boom");
+    assert_ready_err!(response.poll());
+
+    assert_eq!(miss(&mut service, &mut handle, "a", "1").await, "1");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn serves_hits_while_inner_service_isnt_ready() {
+    let _t = support::trace_init();
+    let (mut service, mut handle) = new_service(Expiry::new(TTL), 10);
+
+    assert_eq!(miss(&mut service, &mut handle, "a", "1").await, "1");
+
+    // Hits don't wait for the inner service...
+    handle.allow(0);
+    assert_eq!(hit(&mut service, &mut handle, "a"), "1");
+
+    // ...but misses do.
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("b"));
+    assert_pending!(response.poll());
+    assert_pending!(handle.poll_request());
+
+    handle.allow(1);
+    assert!(response.is_woken());
+    assert_pending!(response.poll());
+    assert_request_eq!(handle, "b").send_response("2");
+    assert_eq!(assert_ready_ok!(response.poll()), "2");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn evicts_least_recently_used() {
+    let _t = support::trace_init();
+    let (mut service, mut handle) = new_service(Expiry::new(TTL), 2);
+
+    miss(&mut service, &mut handle, "a", "1").await;
+    miss(&mut service, &mut handle, "b", "2").await;
+    hit(&mut service, &mut handle, "a");
+    miss(&mut service, &mut handle, "c", "3").await;
+
+    let store = service.get_ref().store();
+    assert_eq!(store.len(), 2);
+    assert!(store.get(&"b").is_none());
+    hit(&mut service, &mut handle, "a");
+    hit(&mut service, &mut handle, "c");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn refreshes_stale_responses_in_background() {
+    let _t = support::trace_init();
+    let executor = TestExecutor::new();
+    let expiry = Expiry::new(TTL).stale_while_revalidate(TTL);
+    let (mut service, mut handle) = mock::spawn_with(|s| {
+        Cache::with_executor(
+            s,
+            key as Key,
+            expiry,
+            MemoryStore::new(10),
+            executor.clone(),
+        )
+    });
+
+    assert_eq!(miss(&mut service, &mut handle, "a", "1").await, "1");
+    time::advance(TTL).await;
+
+    // The stale response is served while it is refreshed, only once.
+    assert_eq!(hit_stale(&mut service, "a"), "1");
+    assert_eq!(hit_stale(&mut service, "a"), "1");
+    assert_eq!(executor.len(), 1);
+
+    let mut runner = task::spawn(());
+    assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+    assert_request_eq!(handle, "a").send_response("2");
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+    assert_eq!(hit(&mut service, &mut handle, "a"), "2");
+
+    // Stale responses expire too.
+    time::advance(TTL * 2).await;
+    assert_eq!(miss(&mut service, &mut handle, "a", "3").await, "3");
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn retries_failed_refresh() {
+    let _t = support::trace_init();
+    let executor = TestExecutor::new();
+    let expiry = Expiry::new(TTL).stale_while_revalidate(TTL);
+    let (mut service, mut handle) = mock::spawn_with(|s| {
+        Cache::with_executor(
+            s,
+            key as Key,
+            expiry,
+            MemoryStore::new(10),
+            executor.clone(),
+        )
+    });
+
+    miss(&mut service, &mut handle, "a", "1").await;
+    time::advance(TTL).await;
+
+    assert_eq!(hit_stale(&mut service, "a"), "1");
+    let mut runner = task::spawn(());
+    assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+    assert_request_eq!(handle, "a").send_error("boom");
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+
+    // The next stale hit refreshes the response again.
+    assert_eq!(hit_stale(&mut service, "a"), "1");
+    assert_eq!(executor.len(), 1);
+}
+
+fn new_service<P: Clone>(policy: P, capacity: usize) -> (mock::Spawn<MockCache<P>>, Handle) {
+    mock::spawn_with(|s| Cache::new(s, key as Key, policy.clone(), MemoryStore::new(capacity)))
+}
+
+fn key(req: &&'static str) -> Option<&'static str> {
+    match *req {
+        "uncached" => None,
+        req => Some(req),
+    }
+}
+
+/// Calls the cache, expecting it to call the inner service.
+async fn miss<P>(
+    service: &mut mock::Spawn<MockCache<P>>,
+    handle: &mut Handle,
+    req: &'static str,
+    res: &'static str,
+) -> &'static str
+where
+    P: tower::cache::Policy<&'static str> + Clone + Send + 'static,
+{
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call(req));
+    assert_pending!(response.poll());
+    assert_request_eq!(handle, req).send_response(res);
+    assert_ready_ok!(response.poll())
+}
+
+/// Calls the cache, expecting a fresh cached response.
+fn hit<P>(
+    service: &mut mock::Spawn<MockCache<P>>,
+    handle: &mut Handle,
+    req: &'static str,
+) -> &'static str
+where
+    P: tower::cache::Policy<&'static str> + Clone + Send + 'static,
+{
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call(req));
+    assert_pending!(handle.poll_request());
+    assert_ready_ok!(response.poll())
+}
+
+/// Calls the cache, expecting a stale cached response.
+fn hit_stale<P>(service: &mut mock::Spawn<MockCache<P>>, req: &'static str) -> &'static str
+where
+    P: tower::cache::Policy<&'static str> + Clone + Send + 'static,
+{
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call(req));
+    assert_ready_ok!(response.poll())
+}
@@ -0,0 +1,496 @@
+#![cfg(all(feature = "discover-util", feature = "util"))]
+#[path = "../support.rs"]
+mod support;
+
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_stream::StreamExt;
+use tokio_test::{assert_pending, assert_ready, task};
+use tower::discover::{self, Change, DiscoverExt, FileDiscover, MergeKey, PollingDiscover};
+use tower::service_fn;
+
+type Svc = tower::util::BoxService<(), &'static str, Infallible>;
+
+fn svc(name: &'static str) -> Svc {
+    Svc::new(service_fn(move |()| async move { Ok(name) }))
+}
+
+/// A resolver returning whatever targets the test last set.
+#[derive(Clone, Default)]
+struct StubResolver(Arc<Mutex<Vec<&'static str>>>);
+
+impl StubResolver {
+    fn set(&self, targets: &[&'static str]) {
+        *self.0.lock().unwrap() = targets.to_vec();
+    }
+
+    fn resolve(
+        &self,
+    ) -> impl FnMut() -> futures::future::Ready<Result<Vec<&'static str>, Infallible>> {
+        let targets = self.0.clone();
+        move || futures::future::ready(Ok(targets.lock().unwrap().clone()))
+    }
+}
+
+fn key<S>(change: Change<&'static str, S>) -> (&'static str, bool) {
+    match change {
+        Change::Insert(key, _) => (key, true),
+        Change::Remove(key) => (key, false),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn channel_yields_sent_changes() {
+    let _t = support::trace_init();
+
+    let (tx, mut rx) = discover::channel::<&'static str, Svc>();
+    tx.insert("a", svc("a")).unwrap();
+    tx.clone().remove("a").unwrap();
+
+    assert_eq!(key(rx.next().await.unwrap().unwrap()), ("a", true));
+    assert_eq!(key(rx.next().await.unwrap().unwrap()), ("a", false));
+
+    drop(tx);
+    assert!(rx.next().await.is_none());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn channel_send_fails_when_receiver_is_dropped() {
+    let _t = support::trace_init();
+
+    let (tx, rx) = discover::channel::<&'static str, Svc>();
+    assert!(!tx.is_closed());
+    drop(rx);
+    assert!(tx.is_closed());
+    let err = tx.remove("a").unwrap_err();
+    assert_eq!(err.to_string(), "discovery receiver dropped");
+    assert!(matches!(err.into_change(), Change::Remove("a")));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn polling_diffs_resolved_targets() {
+    let _t = support::trace_init();
+
+    let resolver = StubResolver::default();
+    resolver.set(&["a", "b"]);
+    let make = service_fn(|target: &'static str| async move { Ok::<_, Infallible>(svc(target)) });
+    let mut discover = PollingDiscover::new(resolver.resolve(), make, Duration::from_secs(10));
+
+    // The first lookup happens immediately.
+    let mut inserted = vec![
+        key(discover.next().await.unwrap().unwrap()),
+        key(discover.next().await.unwrap().unwrap()),
+    ];
+    inserted.sort();
+    assert_eq!(inserted, [("a", true), ("b", true)]);
+
+    // Nothing changes until the next lookup.
+    resolver.set(&["b", "c"]);
+    {
+        let mut next = task::spawn(discover.next());
+        assert_pending!(next.poll());
+    }
+
+    // Removals are yielded before insertions.
+    assert_eq!(key(discover.next().await.unwrap().unwrap()), ("a", false));
+    let (name, inserted) = match discover.next().await.unwrap().unwrap() {
+        Change::Insert(name, mut svc) => (name, tower::ServiceExt::oneshot(&mut svc, ()).await),
+        Change::Remove(name) => panic!("unexpected removal of {}", name),
+    };
+    assert_eq!(name, "c");
+    assert_eq!(inserted.unwrap(), "c");
+
+    // Unchanged lookups yield nothing.
+    tokio::time::advance(Duration::from_secs(10)).await;
+    let mut next = task::spawn(discover.next());
+    assert_pending!(next.poll());
+}
+
+#[test]
+fn polling_is_created_outside_runtime() {
+    let resolver = StubResolver::default();
+    let make = service_fn(|target: &'static str| async move { Ok::<_, Infallible>(svc(target)) });
+    let discover = PollingDiscover::new(resolver.resolve(), make, Duration::from_secs(10));
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    resolver.set(&["a"]);
+    rt.block_on(async move {
+        tokio::pin!(discover);
+        assert_eq!(key(discover.next().await.unwrap().unwrap()), ("a", true));
+    });
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn polling_retries_failed_targets() {
+    let _t = support::trace_init();
+
+    let resolver = StubResolver::default();
+    resolver.set(&["a"]);
+    let fail = Arc::new(Mutex::new(true));
+    let make = {
+        let fail = fail.clone();
+        service_fn(move |target: &'static str| {
+            let fail = *fail.lock().unwrap();
+            async move {
+                if fail {
+                    Err("connection refused")
+                } else {
+                    Ok(svc(target))
+                }
+            }
+        })
+    };
+    let mut discover = PollingDiscover::new(resolver.resolve(), make, Duration::from_secs(10));
+
+    // The failure isn't yielded...
+    assert!(timeout(Duration::from_secs(25), discover.next())
+        .await
+        .is_err());
+
+    // ...and the target is retried on the next lookup.
+    *fail.lock().unwrap() = false;
+    assert_eq!(key(discover.next().await.unwrap().unwrap()), ("a", true));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn polling_keeps_targets_when_lookup_fails() {
+    let _t = support::trace_init();
+
+    let targets = Arc::new(Mutex::new(Ok(vec!["a"])));
+    let resolve = {
+        let targets = targets.clone();
+        move || futures::future::ready(targets.lock().unwrap().clone())
+    };
+    let make = service_fn(|target: &'static str| async move { Ok::<_, Infallible>(svc(target)) });
+    let mut discover = PollingDiscover::new(resolve, make, Duration::from_secs(10));
+    assert_eq!(key(discover.next().await.unwrap().unwrap()), ("a", true));
+
+    // A failed lookup isn't yielded, and doesn't remove the current targets...
+    *targets.lock().unwrap() = Err("no such host");
+    assert!(timeout(Duration::from_secs(25), discover.next())
+        .await
+        .is_err());
+
+    // ...which are updated by the next successful lookup.
+    *targets.lock().unwrap() = Ok(vec!["b"]);
+    assert_eq!(key(discover.next().await.unwrap().unwrap()), ("a", false));
+    assert_eq!(key(discover.next().await.unwrap().unwrap()), ("b", true));
+}
+
+/// A file in the temporary directory that is removed when dropped.
+struct TempFile(std::path::PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("tower-{}-{}", std::process::id(), name));
+        TempFile(path)
+    }
+
+    fn write(&self, contents: &str) {
+        std::fs::write(&self.0, contents).unwrap();
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn file_is_created_outside_runtime() {
+    let make = service_fn(|_: String| async { Ok::<_, Infallible>(svc("backend")) });
+    let _discover = FileDiscover::new("targets", make, Duration::from_secs(1));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn file_reloads_changed_targets() {
+    let _t = support::trace_init();
+
+    let file = TempFile::new("file_reloads_changed_targets");
+    file.write("# backends\na\n\n  b  \n");
+    let make = service_fn(|_: String| async { Ok::<_, Infallible>(svc("backend")) });
+    let mut discover = FileDiscover::new(&file.0, make, Duration::from_secs(1));
+
+    let mut inserted = vec![
+        key_string(discover.next().await.unwrap().unwrap()),
+        key_string(discover.next().await.unwrap().unwrap()),
+    ];
+    inserted.sort();
+    assert_eq!(inserted, [("a".to_string(), true), ("b".to_string(), true)]);
+
+    // Only the difference is yielded when the file changes.
+    file.write("b\nc\n");
+    assert_eq!(
+        key_string(discover.next().await.unwrap().unwrap()),
+        ("a".to_string(), false)
+    );
+    assert_eq!(
+        key_string(discover.next().await.unwrap().unwrap()),
+        ("c".to_string(), true)
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn file_with_parser() {
+    let _t = support::trace_init();
+
+    let file = TempFile::new("file_with_parser");
+    file.write("a,b");
+    let make = service_fn(|_: String| async { Ok::<_, Infallible>(svc("backend")) });
+    let parse = |contents: &str| {
+        if contents.is_empty() {
+            return Err("empty target list");
+        }
+        Ok(contents.split(',').map(String::from).collect())
+    };
+    let mut discover = FileDiscover::with_parser(&file.0, parse, make, Duration::from_secs(1));
+
+    let mut inserted = vec![
+        key_string(discover.next().await.unwrap().unwrap()),
+        key_string(discover.next().await.unwrap().unwrap()),
+    ];
+    inserted.sort();
+    assert_eq!(inserted, [("a".to_string(), true), ("b".to_string(), true)]);
+
+    // Parse errors are logged, and the previous targets are kept...
+    file.write("");
+    assert!(timeout(Duration::from_secs(5), discover.next())
+        .await
+        .is_err());
+
+    // ...until the file changes again.
+    file.write("c");
+    let mut changes = vec![
+        key_string(discover.next().await.unwrap().unwrap()),
+        key_string(discover.next().await.unwrap().unwrap()),
+        key_string(discover.next().await.unwrap().unwrap()),
+    ];
+    changes.sort();
+    assert_eq!(
+        changes,
+        [
+            ("a".to_string(), false),
+            ("b".to_string(), false),
+            ("c".to_string(), true)
+        ]
+    );
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn file_keeps_targets_while_missing() {
+    let _t = support::trace_init();
+
+    let file = TempFile::new("file_keeps_targets_while_missing");
+    file.write("a\n");
+    let make = service_fn(|_: String| async { Ok::<_, Infallible>(svc("backend")) });
+    let mut discover = FileDiscover::new(&file.0, make, Duration::from_secs(1));
+    assert_eq!(
+        key_string(discover.next().await.unwrap().unwrap()),
+        ("a".to_string(), true)
+    );
+
+    // A missing file doesn't remove the targets...
+    std::fs::remove_file(&file.0).unwrap();
+    assert!(timeout(Duration::from_secs(5), discover.next())
+        .await
+        .is_err());
+
+    // ...and is read again once it's back.
+    file.write("b\nc\n");
+    let mut changes = vec![
+        key_string(discover.next().await.unwrap().unwrap()),
+        key_string(discover.next().await.unwrap().unwrap()),
+        key_string(discover.next().await.unwrap().unwrap()),
+    ];
+    changes.sort();
+    assert_eq!(
+        changes,
+        [
+            ("a".to_string(), false),
+            ("b".to_string(), true),
+            ("c".to_string(), true)
+        ]
+    );
+}
+
+fn key_string<S>(change: Change<String, S>) -> (String, bool) {
+    match change {
+        Change::Insert(key, _) => (key, true),
+        Change::Remove(key) => (key, false),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn map_service_wraps_inserted_services() {
+    let _t = support::trace_init();
+
+    let (tx, rx) = discover::channel::<&'static str, Svc>();
+    let mut discover = rx.map_service(tower::layer::layer_fn(|svc: Svc| {
+        Svc::new(tower::ServiceExt::map_response(svc, |rsp: &'static str| {
+            if rsp == "a" {
+                "mapped"
+            } else {
+                rsp
+            }
+        }))
+    }));
+
+    tx.insert("a", svc("a")).unwrap();
+    tx.remove("a").unwrap();
+    match discover.next().await.unwrap().unwrap() {
+        Change::Insert("a", svc) => {
+            assert_eq!(tower::ServiceExt::oneshot(svc, ()).await.unwrap(), "mapped")
+        }
+        _ => panic!("expected insertion of a"),
+    }
+    assert_eq!(key(discover.next().await.unwrap().unwrap()), ("a", false));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn service_builder_layers_inserted_services() {
+    let _t = support::trace_init();
+
+    let (tx, rx) = discover::channel::<&'static str, Svc>();
+    let mut discover = tower::ServiceBuilder::new()
+        .map_response(|rsp: &'static str| rsp.len())
+        .discover(rx);
+
+    tx.insert("a", svc("abc")).unwrap();
+    match discover.next().await.unwrap().unwrap() {
+        Change::Insert("a", svc) => {
+            assert_eq!(tower::ServiceExt::oneshot(svc, ()).await.unwrap(), 3)
+        }
+        _ => panic!("expected insertion of a"),
+    }
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn filter_keys_skips_insertions_and_removals() {
+    let _t = support::trace_init();
+
+    let (tx, rx) = discover::channel::<&'static str, Svc>();
+    let mut discover = rx.filter_keys(|key| key.starts_with("prod-"));
+
+    tx.insert("staging-a", svc("a")).unwrap();
+    tx.insert("prod-b", svc("b")).unwrap();
+    tx.remove("staging-a").unwrap();
+    tx.remove("prod-b").unwrap();
+    drop(tx);
+
+    assert_eq!(
+        key(discover.next().await.unwrap().unwrap()),
+        ("prod-b", true)
+    );
+    assert_eq!(
+        key(discover.next().await.unwrap().unwrap()),
+        ("prod-b", false)
+    );
+    assert!(discover.next().await.is_none());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn merge_namespaces_keys() {
+    let _t = support::trace_init();
+
+    let (tx1, rx1) = discover::channel::<&'static str, Svc>();
+    let (tx2, rx2) = discover::channel::<&'static str, Svc>();
+    // `tokio_stream::StreamExt` also has a `merge` method.
+    let mut discover = DiscoverExt::merge(rx1, rx2);
+
+    tx1.insert("a", svc("1")).unwrap();
+    tx2.insert("a", svc("2")).unwrap();
+    let mut keys = Vec::new();
+    for _ in 0..2 {
+        match discover.next().await.unwrap().unwrap() {
+            Change::Insert(key, _) => keys.push(key),
+            Change::Remove(_) => panic!("unexpected removal"),
+        }
+    }
+    keys.sort();
+    assert_eq!(keys, [MergeKey::First("a"), MergeKey::Second("a")]);
+
+    // The merged discovery ends once both sources have ended.
+    drop(tx1);
+    tx2.remove("a").unwrap();
+    match discover.next().await.unwrap().unwrap() {
+        Change::Remove(key) => assert_eq!(key, MergeKey::Second("a")),
+        Change::Insert(..) => panic!("unexpected insertion"),
+    }
+    drop(tx2);
+    assert!(discover.next().await.is_none());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn debounce_coalesces_flapping() {
+    let _t = support::trace_init();
+
+    let (tx, rx) = discover::channel::<&'static str, Svc>();
+    let mut discover = task::spawn(rx.debounce(Duration::from_secs(1)));
+
+    // A service that's removed within the window is never yielded.
+    tx.insert("a", svc("a")).unwrap();
+    tx.insert("b", svc("b")).unwrap();
+    tx.remove("a").unwrap();
+    assert_pending!(discover.poll_next());
+    tokio::time::advance(Duration::from_millis(500)).await;
+    tx.insert("b", svc("b2")).unwrap();
+    assert_pending!(discover.poll_next());
+
+    tokio::time::advance(Duration::from_millis(502)).await;
+    assert!(discover.is_woken());
+    match assert_ready!(discover.poll_next()).unwrap().unwrap() {
+        Change::Insert("b", svc) => {
+            assert_eq!(tower::ServiceExt::oneshot(svc, ()).await.unwrap(), "b2")
+        }
+        _ => panic!("expected insertion of b"),
+    }
+    assert_pending!(discover.poll_next());
+
+    // Flapping removals of a yielded service are coalesced.
+    tx.remove("b").unwrap();
+    tx.insert("b", svc("b3")).unwrap();
+    tx.remove("b").unwrap();
+    assert_pending!(discover.poll_next());
+    tokio::time::advance(Duration::from_millis(1002)).await;
+    assert_eq!(
+        key(assert_ready!(discover.poll_next()).unwrap().unwrap()),
+        ("b", false)
+    );
+
+    // Held changes are yielded once the source ends.
+    tx.insert("c", svc("c")).unwrap();
+    drop(tx);
+    assert_eq!(
+        key(assert_ready!(discover.poll_next()).unwrap().unwrap()),
+        ("c", true)
+    );
+    assert!(assert_ready!(discover.poll_next()).is_none());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn debounce_with_huge_delay() {
+    let _t = support::trace_init();
+
+    let (tx, rx) = discover::channel::<&'static str, Svc>();
+    let mut discover = task::spawn(rx.debounce(Duration::MAX));
+
+    // The change is held back without overflowing its deadline.
+    tx.insert("a", svc("a")).unwrap();
+    assert_pending!(discover.poll_next());
+    tokio::time::advance(Duration::from_secs(86400)).await;
+    assert_pending!(discover.poll_next());
+
+    drop(tx);
+    assert_eq!(
+        key(assert_ready!(discover.poll_next()).unwrap().unwrap()),
+        ("a", true)
+    );
+    assert!(assert_ready!(discover.poll_next()).is_none());
+}
@@ -3,7 +3,7 @@
 mod support;
 
 use tokio_test::{assert_ready_err, assert_ready_ok, task};
-use tower::load_shed::LoadShedLayer;
+use tower::load_shed::{LoadShedLayer, Priority, PriorityLoadShedLayer};
 use tower_test::{assert_request_eq, mock};
 
 #[tokio::test(flavor = "current_thread")]
@@ -37,3 +37,80 @@ async fn when_not_ready() {
     let err = assert_ready_err!(fut.poll());
     assert!(err.is::<tower::load_shed::error::Overloaded>());
 }
+
+#[tokio::test(flavor = "current_thread")]
+async fn priority_sheds_low_before_high() {
+    let _t = support::trace_init();
+
+    let classify = |req: &&'static str| {
+        if req.starts_with("high") {
+            Priority::High
+        } else {
+            Priority::Low
+        }
+    };
+    let layer = PriorityLoadShedLayer::new(classify, 2, 1);
+    let (mut service, mut handle) = mock::spawn_layer(layer);
+
+    // The first low-priority request uses the only unreserved slot.
+    assert_ready_ok!(service.poll_ready());
+    let mut low1 = task::spawn(service.call("low 1"));
+    let low1_req = assert_request_eq!(handle, "low 1");
+
+    // Another low-priority request is shed...
+    assert_ready_ok!(service.poll_ready());
+    let mut low2 = task::spawn(service.call("low 2"));
+    let err = assert_ready_err!(low2.poll());
+    assert!(err.is::<tower::load_shed::error::Overloaded>());
+
+    // ...but a high-priority request may use the reserved headroom.
+    assert_ready_ok!(service.poll_ready());
+    let mut high1 = task::spawn(service.call("high 1"));
+    assert_request_eq!(handle, "high 1").send_response("ok");
+    assert_eq!(assert_ready_ok!(high1.poll()), "ok");
+
+    // Once the first request completes, low-priority traffic is admitted again.
+    low1_req.send_response("ok");
+    assert_eq!(assert_ready_ok!(low1.poll()), "ok");
+    drop(low1);
+    drop(high1);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut low3 = task::spawn(service.call("low 3"));
+    assert_request_eq!(handle, "low 3").send_response("ok");
+    assert_eq!(assert_ready_ok!(low3.poll()), "ok");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn priority_sheds_all_when_full() {
+    let _t = support::trace_init();
+
+    let layer = PriorityLoadShedLayer::new(|_: &&'static str| Priority::High, 1, 1);
+    let (mut service, mut handle) = mock::spawn_layer::<_, &'static str, _>(layer);
+
+    assert_ready_ok!(service.poll_ready());
+    let _first = service.call("first");
+    let _req = assert_request_eq!(handle, "first");
+
+    assert_ready_ok!(service.poll_ready());
+    let mut second = task::spawn(service.call("second"));
+    let err = assert_ready_err!(second.poll());
+    assert!(err.is::<tower::load_shed::error::Overloaded>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn priority_when_not_ready() {
+    let _t = support::trace_init();
+
+    let layer = PriorityLoadShedLayer::new(|_: &&'static str| Priority::High, 2, 1);
+    let (mut service, mut handle) = mock::spawn_layer::<_, (), _>(layer);
+
+    handle.allow(0);
+
+    assert_ready_ok!(service.poll_ready());
+
+    let mut fut = task::spawn(service.call("hello"));
+
+    let err = assert_ready_err!(fut.poll());
+    assert!(err.is::<tower::load_shed::error::Overloaded>());
+}
@@ -0,0 +1,211 @@
+#![cfg(feature = "pool")]
+#[path = "../support.rs"]
+mod support;
+
+use std::time::Duration;
+use tokio_test::{assert_pending, assert_ready_ok, task};
+use tower::pool::Builder;
+use tower_test::{assert_request_eq, mock};
+
+type Conn = mock::Mock<&'static str, &'static str>;
+
+#[tokio::test(flavor = "current_thread")]
+async fn opens_connections_under_load() {
+    let _t = support::trace_init();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut pool = mock::Spawn::new(Builder::new().max_connections(2).build(mk, "target"));
+    assert!(pool.get_ref().is_empty());
+
+    // The first connection is opened lazily.
+    assert_pending!(pool.poll_ready());
+    let (conn1, mut conn1_handle) = mock::pair();
+    conn1_handle.allow(1);
+    assert_request_eq!(mk_handle, "target").send_response(conn1);
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp1 = task::spawn(pool.call("one"));
+    let send1 = assert_request_eq!(conn1_handle, "one");
+
+    // The first connection is busy, so a second one is opened.
+    assert_pending!(pool.poll_ready());
+    let (conn2, mut conn2_handle) = mock::pair();
+    conn2_handle.allow(1);
+    assert_request_eq!(mk_handle, "target").send_response(conn2);
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp2 = task::spawn(pool.call("two"));
+    let send2 = assert_request_eq!(conn2_handle, "two");
+    assert_eq!(pool.get_ref().len(), 2);
+
+    // Both connections are busy, and no more connections may be opened.
+    assert_pending!(pool.poll_ready());
+    assert_pending!(mk_handle.poll_request());
+
+    // Once a connection becomes ready again, it's reused.
+    send1.send_response("uno");
+    assert_eq!(assert_ready_ok!(rsp1.poll()), "uno");
+    conn1_handle.allow(1);
+    assert!(pool.is_woken());
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp3 = task::spawn(pool.call("three"));
+    assert_request_eq!(conn1_handle, "three").send_response("tres");
+    assert_eq!(assert_ready_ok!(rsp3.poll()), "tres");
+
+    send2.send_response("dos");
+    assert_eq!(assert_ready_ok!(rsp2.poll()), "dos");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn closes_idle_connections() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut pool = mock::Spawn::new(
+        Builder::new()
+            .max_connections(2)
+            .idle_timeout(Duration::from_secs(10))
+            .build(mk, "target"),
+    );
+
+    // Open two connections.
+    assert_pending!(pool.poll_ready());
+    let (conn1, mut conn1_handle) = mock::pair();
+    conn1_handle.allow(1);
+    assert_request_eq!(mk_handle, "target").send_response(conn1);
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp1 = task::spawn(pool.call("one"));
+
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert_pending!(pool.poll_ready());
+    let (conn2, mut conn2_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn2);
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp2 = task::spawn(pool.call("two"));
+
+    assert_request_eq!(conn1_handle, "one").send_response("uno");
+    assert_eq!(assert_ready_ok!(rsp1.poll()), "uno");
+    assert_request_eq!(conn2_handle, "two").send_response("dos");
+    assert_eq!(assert_ready_ok!(rsp2.poll()), "dos");
+
+    // Only the second connection is used for a while...
+    conn1_handle.allow(1);
+    tokio::time::advance(Duration::from_secs(6)).await;
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp3 = task::spawn(pool.call("three"));
+    assert_request_eq!(conn2_handle, "three").send_response("tres");
+    assert_eq!(assert_ready_ok!(rsp3.poll()), "tres");
+
+    // ...so the first connection is closed once it has been idle for too long.
+    tokio::time::advance(Duration::from_secs(6)).await;
+    assert!(pool.is_woken(), "idle connection must wake the pool");
+    assert_ready_ok!(pool.poll_ready());
+    assert_eq!(pool.get_ref().len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn keeps_connections_with_requests_in_flight() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut pool = mock::Spawn::new(
+        Builder::new()
+            .max_connections(2)
+            .idle_timeout(Duration::from_secs(10))
+            .build(mk, "target"),
+    );
+
+    // Send a request whose response takes a long time on the first
+    // connection...
+    assert_pending!(pool.poll_ready());
+    let (conn1, mut conn1_handle) = mock::pair();
+    conn1_handle.allow(1);
+    assert_request_eq!(mk_handle, "target").send_response(conn1);
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp1 = task::spawn(pool.call("one"));
+    let send1 = assert_request_eq!(conn1_handle, "one");
+
+    // ...and another one on a second connection.
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert_pending!(pool.poll_ready());
+    let (conn2, mut conn2_handle) = mock::pair();
+    conn2_handle.allow(1);
+    assert_request_eq!(mk_handle, "target").send_response(conn2);
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp2 = task::spawn(pool.call("two"));
+    let send2 = assert_request_eq!(conn2_handle, "two");
+
+    // The connections are ready for more requests, but aren't idle while
+    // their responses are in flight.
+    conn1_handle.allow(1);
+    conn2_handle.allow(1);
+    tokio::time::advance(Duration::from_secs(15)).await;
+    assert_ready_ok!(pool.poll_ready());
+    assert_eq!(pool.get_ref().len(), 2);
+
+    // Once the response completes, the connection becomes idle...
+    send1.send_response("uno");
+    assert_eq!(assert_ready_ok!(rsp1.poll()), "uno");
+    assert!(pool.is_woken(), "completed request must wake the pool");
+    tokio::time::advance(Duration::from_secs(5)).await;
+    assert_ready_ok!(pool.poll_ready());
+    assert_eq!(pool.get_ref().len(), 2);
+
+    // ...and is closed once it has been idle for too long.
+    tokio::time::advance(Duration::from_secs(6)).await;
+    assert!(pool.is_woken(), "idle connection must wake the pool");
+    assert_ready_ok!(pool.poll_ready());
+    assert_eq!(pool.get_ref().len(), 1);
+
+    send2.send_response("dos");
+    assert_eq!(assert_ready_ok!(rsp2.poll()), "dos");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn opens_minimum_connections() {
+    let _t = support::trace_init();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut pool = mock::Spawn::new(Builder::new().min_connections(3).build(mk, "target"));
+
+    assert_pending!(pool.poll_ready());
+    let (conn1, mut conn1_handle) = mock::pair();
+    conn1_handle.allow(1);
+    assert_request_eq!(mk_handle, "target").send_response(conn1);
+    assert_ready_ok!(pool.poll_ready());
+
+    // The remaining connections are opened without waiting for each new
+    // connection to become ready.
+    let (conn2, _conn2_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn2);
+    assert!(pool.is_woken());
+    assert_ready_ok!(pool.poll_ready());
+    let (conn3, _conn3_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn3);
+    assert_ready_ok!(pool.poll_ready());
+    assert_eq!(pool.get_ref().len(), 3);
+    assert_pending!(mk_handle.poll_request());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn failed_connection_is_returned_from_call() {
+    let _t = support::trace_init();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut pool = mock::Spawn::new(Builder::new().build(mk, "target"));
+
+    assert_pending!(pool.poll_ready());
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    assert_ready_ok!(pool.poll_ready());
+    let err = pool.call("hello").await.unwrap_err();
+    assert_eq!(err.to_string(), "refused");
+
+    // The pool remains usable.
+    assert_pending!(pool.poll_ready());
+    let (conn, mut conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+    assert_ready_ok!(pool.poll_ready());
+    let mut rsp = task::spawn(pool.call("hello"));
+    assert_request_eq!(conn_handle, "hello").send_response("world");
+    assert_eq!(assert_ready_ok!(rsp.poll()), "world");
+}
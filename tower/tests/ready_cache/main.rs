@@ -221,3 +221,156 @@ async fn cancelation_observed() {
     }
     Ready(cache).await.unwrap();
 }
+
+#[test]
+fn replaced_service_is_used_until_replacement_is_ready() {
+    let _t = support::trace_init();
+
+    let mut task = task::spawn(());
+    let mut cache = ReadyCache::<usize, Mock, Req>::default();
+
+    let (service0, mut handle0) = mock::pair::<Req, Req>();
+    handle0.allow(1);
+    cache.push(0, service0);
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+
+    // The replacement isn't ready yet, so the existing service stays ready.
+    let (service1, mut handle1) = mock::pair::<Req, Req>();
+    handle1.allow(0);
+    cache.push(0, service1);
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+    assert!(task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+    let _rsp_a = cache.call_ready(&0, "a");
+    assert_eq!(assert_ready!(handle0.poll_request()).unwrap().0, "a");
+
+    // Once called, the existing service is driven to readiness again.
+    assert!(!task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+    handle0.allow(1);
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+    assert!(task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+    let _rsp_b = cache.call_ready(&0, "b");
+    assert_eq!(assert_ready!(handle0.poll_request()).unwrap().0, "b");
+    assert_eq!(cache.len(), 2);
+
+    // The replacement takes over once it's ready.
+    handle0.allow(1);
+    handle1.allow(1);
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert!(task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+    let _rsp_c = cache.call_ready(&0, "c");
+    assert_eq!(assert_ready!(handle1.poll_request()).unwrap().0, "c");
+    assert!(assert_ready!(handle0.poll_request()).is_none());
+}
+
+#[test]
+fn failed_replacement_keeps_replaced_service() {
+    let _t = support::trace_init();
+
+    let mut task = task::spawn(());
+    let mut cache = ReadyCache::<usize, Mock, Req>::default();
+
+    let (service0, mut handle0) = mock::pair::<Req, Req>();
+    handle0.allow(1);
+    cache.push(0, service0);
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+
+    let (service1, mut handle1) = mock::pair::<Req, Req>();
+    handle1.allow(0);
+    cache.push(0, service1);
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+
+    // The failure of the replacement isn't reported, and the existing service
+    // remains in use.
+    handle1.send_error("doom");
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert!(task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+    let _rsp = cache.call_ready(&0, "a");
+    assert_eq!(assert_ready!(handle0.poll_request()).unwrap().0, "a");
+
+    // It's driven to readiness again after it's called.
+    handle0.allow(1);
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+    assert!(task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+}
+
+#[test]
+fn failed_replacement_keeps_pending_replaced_service() {
+    let _t = support::trace_init();
+
+    let mut task = task::spawn(());
+    let mut cache = ReadyCache::<usize, Mock, Req>::default();
+
+    let (service0, mut handle0) = mock::pair::<Req, Req>();
+    handle0.allow(0);
+    cache.push(0, service0);
+
+    let (service1, mut handle1) = mock::pair::<Req, Req>();
+    handle1.allow(0);
+    cache.push(0, service1);
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+
+    handle1.send_error("doom");
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+    assert_eq!(cache.len(), 1);
+
+    // The existing service becomes ready as usual.
+    handle0.allow(1);
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+    assert!(task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+    let _rsp = cache.call_ready(&0, "a");
+    assert_eq!(assert_ready!(handle0.poll_request()).unwrap().0, "a");
+}
+
+#[test]
+fn failed_replaced_service_keeps_replacement() {
+    let _t = support::trace_init();
+
+    let mut task = task::spawn(());
+    let mut cache = ReadyCache::<usize, Mock, Req>::default();
+
+    let (service0, mut handle0) = mock::pair::<Req, Req>();
+    handle0.allow(0);
+    cache.push(0, service0);
+
+    let (service1, mut handle1) = mock::pair::<Req, Req>();
+    handle1.allow(0);
+    cache.push(0, service1);
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+
+    // The failure of the replaced service isn't reported.
+    handle0.send_error("doom");
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+
+    handle1.allow(1);
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+    assert!(task.enter(|cx, _| cache.check_ready(cx, &0)).unwrap());
+}
+
+#[test]
+fn remove_takes_ready_and_pending_services() {
+    let _t = support::trace_init();
+
+    let mut task = task::spawn(());
+    let mut cache = ReadyCache::<usize, Mock, Req>::default();
+
+    let (service0, mut handle0) = mock::pair::<Req, Req>();
+    handle0.allow(1);
+    cache.push(0, service0);
+
+    let (service1, mut handle1) = mock::pair::<Req, Req>();
+    handle1.allow(0);
+    cache.push(1, service1);
+
+    assert_pending!(task.enter(|cx, _| cache.poll_pending(cx)));
+    assert_eq!(cache.ready_len(), 1);
+
+    assert_eq!(cache.remove(&0).len(), 1);
+    assert_eq!(cache.remove(&1).len(), 1);
+    assert!(cache.remove(&1).is_empty());
+
+    // The canceled pending entry is cleaned up without polling the service.
+    assert_ready!(task.enter(|cx, _| cache.poll_pending(cx))).unwrap();
+    assert!(cache.is_empty());
+}
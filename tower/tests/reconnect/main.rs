@@ -0,0 +1,239 @@
+#![cfg(feature = "reconnect")]
+#[path = "../support.rs"]
+mod support;
+
+use std::time::Duration;
+use tokio_test::{assert_pending, assert_ready_err, assert_ready_ok, task};
+use tower::reconnect::{
+    error::{Exhausted, ReconnectFailed},
+    ConnectionState, Reconnect,
+};
+use tower::retry::backoff::ExponentialBackoffMaker;
+use tower::util::rng::HasherRng;
+use tower_test::{assert_request_eq, mock};
+
+type Conn = mock::Mock<&'static str, &'static str>;
+type MakeHandle = mock::Handle<&'static str, Conn>;
+
+fn backoff() -> ExponentialBackoffMaker {
+    ExponentialBackoffMaker::new(
+        Duration::from_millis(100),
+        Duration::from_secs(10),
+        0.0,
+        HasherRng::default(),
+    )
+    .unwrap()
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn waits_between_failed_attempts() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut svc = mock::Spawn::new(Reconnect::with_backoff(mk, "target", backoff()));
+
+    // The first attempt fails, and the error is returned from `call`.
+    assert_pending!(svc.poll_ready());
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    assert_ready_ok!(svc.poll_ready());
+    let err = svc.call("hello").await.unwrap_err();
+    assert_eq!(err.to_string(), "refused");
+
+    // The next attempt isn't made until the backoff elapsed.
+    assert_pending!(svc.poll_ready());
+    assert_pending!(mk_handle.poll_request());
+    tokio::time::advance(Duration::from_millis(101)).await;
+    assert_pending!(svc.poll_ready());
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    assert_ready_ok!(svc.poll_ready());
+    assert!(svc.call("hello").await.is_err());
+
+    // The backoff grows between consecutive failures.
+    assert_pending!(svc.poll_ready());
+    tokio::time::advance(Duration::from_millis(101)).await;
+    assert_pending!(svc.poll_ready());
+    assert_pending!(mk_handle.poll_request());
+    tokio::time::advance(Duration::from_millis(100)).await;
+    assert_pending!(svc.poll_ready());
+    let (conn, mut conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+
+    // Once connected, requests are sent to the connection.
+    assert_ready_ok!(svc.poll_ready());
+    let mut response = task::spawn(svc.call("hello"));
+    assert_request_eq!(conn_handle, "hello").send_response("world");
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn resets_backoff_after_connecting() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut svc = mock::Spawn::new(Reconnect::with_backoff(mk, "target", backoff()));
+
+    fail_attempt(&mut svc, &mut mk_handle).await;
+    tokio::time::advance(Duration::from_millis(101)).await;
+    fail_attempt(&mut svc, &mut mk_handle).await;
+    tokio::time::advance(Duration::from_millis(201)).await;
+
+    // Connect, then lose the connection.
+    assert_pending!(svc.poll_ready());
+    let (conn, mut conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+    assert_ready_ok!(svc.poll_ready());
+    conn_handle.send_error("disconnected");
+
+    // The next failure only waits for the minimum backoff again.
+    assert_pending!(svc.poll_ready());
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    assert_ready_ok!(svc.poll_ready());
+    assert!(svc.call("hello").await.is_err());
+    assert_pending!(svc.poll_ready());
+    tokio::time::advance(Duration::from_millis(101)).await;
+    assert_pending!(svc.poll_ready());
+    assert_request_eq!(mk_handle, "target");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn gives_up_after_max_attempts() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut svc =
+        mock::Spawn::new(Reconnect::with_backoff(mk, "target", backoff()).max_connect_attempts(2));
+
+    fail_attempt(&mut svc, &mut mk_handle).await;
+    tokio::time::advance(Duration::from_millis(101)).await;
+
+    assert_pending!(svc.poll_ready());
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    let err = assert_ready_err!(svc.poll_ready());
+    let err = err
+        .downcast_ref::<Exhausted>()
+        .expect("should be Exhausted");
+    assert_eq!(err.attempts(), 2);
+
+    // No further attempts are made.
+    assert!(assert_ready_err!(svc.poll_ready()).is::<Exhausted>());
+    assert_pending!(mk_handle.poll_request());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn reports_state_changes() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut svc = mock::Spawn::new(Reconnect::with_backoff(mk, "target", backoff()));
+    let mut state = svc.get_mut().watch_state();
+    assert_eq!(*state.borrow_and_update(), ConnectionState::Idle);
+
+    assert_pending!(svc.poll_ready());
+    assert!(state.has_changed().unwrap());
+    assert_eq!(*state.borrow_and_update(), ConnectionState::Connecting);
+
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    assert_ready_ok!(svc.poll_ready());
+    assert_eq!(*state.borrow_and_update(), ConnectionState::Failed);
+    assert!(svc.call("hello").await.is_err());
+
+    tokio::time::advance(Duration::from_millis(101)).await;
+    assert_pending!(svc.poll_ready());
+    assert_eq!(*state.borrow_and_update(), ConnectionState::Connecting);
+
+    let (conn, mut conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+    assert_ready_ok!(svc.poll_ready());
+    assert_eq!(*state.borrow_and_update(), ConnectionState::Connected);
+    assert_eq!(svc.get_ref().state(), ConnectionState::Connected);
+
+    // Losing the connection starts connecting again right away.
+    conn_handle.send_error("reset");
+    assert_pending!(svc.poll_ready());
+    assert_eq!(*state.borrow_and_update(), ConnectionState::Connecting);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn attaches_disconnect_cause() {
+    let _t = support::trace_init();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut svc = mock::Spawn::new(Reconnect::new(mk, "target"));
+
+    assert_pending!(svc.poll_ready());
+    let (conn, mut conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+    assert_ready_ok!(svc.poll_ready());
+
+    conn_handle.send_error("connection reset");
+    assert_pending!(svc.poll_ready());
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    assert_ready_ok!(svc.poll_ready());
+
+    let err = svc.call("hello").await.unwrap_err();
+    let err = err
+        .downcast_ref::<ReconnectFailed>()
+        .expect("should be ReconnectFailed");
+    assert_eq!(err.disconnect_cause().to_string(), "connection reset");
+    assert_eq!(
+        std::error::Error::source(err).unwrap().to_string(),
+        "refused"
+    );
+
+    // Once connected again, the cause is no longer attached.
+    assert_pending!(svc.poll_ready());
+    let (conn, _conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+    assert_ready_ok!(svc.poll_ready());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn passes_service_errors_through_after_reconnecting() {
+    let _t = support::trace_init();
+
+    let (mk, mut mk_handle) = mock::pair::<&'static str, Conn>();
+    let mut svc = mock::Spawn::new(Reconnect::new(mk, "target"));
+
+    assert_pending!(svc.poll_ready());
+    let (conn, mut conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+    assert_ready_ok!(svc.poll_ready());
+
+    conn_handle.send_error("connection reset");
+    assert_pending!(svc.poll_ready());
+    let (conn, mut conn_handle) = mock::pair();
+    assert_request_eq!(mk_handle, "target").send_response(conn);
+    assert_ready_ok!(svc.poll_ready());
+
+    let fut = svc.call("hello");
+    assert_request_eq!(conn_handle, "hello").send_error(ServiceFailed);
+    let err = fut.await.unwrap_err();
+    assert!(err.is::<ServiceFailed>(), "unexpected error: {:?}", err);
+}
+
+#[derive(Debug)]
+struct ServiceFailed;
+
+impl std::fmt::Display for ServiceFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("service failed")
+    }
+}
+
+impl std::error::Error for ServiceFailed {}
+
+async fn fail_attempt(
+    svc: &mut mock::Spawn<
+        Reconnect<mock::Mock<&'static str, Conn>, &'static str, ExponentialBackoffMaker>,
+    >,
+    mk_handle: &mut MakeHandle,
+) {
+    assert_pending!(svc.poll_ready());
+    assert_request_eq!(mk_handle, "target").send_error("refused");
+    assert_ready_ok!(svc.poll_ready());
+    assert!(svc.call("hello").await.is_err());
+}
@@ -1,7 +1,9 @@
-#![cfg(feature = "spawn-ready")]
+#![cfg(all(feature = "spawn-ready", feature = "tokio-rt"))]
 #[path = "../support.rs"]
 mod support;
 
+#[cfg(feature = "test-util")]
+use support::TestExecutor;
 use tokio::time;
 use tokio_test::{assert_pending, assert_ready, assert_ready_err, assert_ready_ok};
 use tower::spawn_ready::{SpawnReady, SpawnReadyLayer};
@@ -84,3 +86,43 @@ async fn abort_on_drop() {
     tokio::task::yield_now().await;
     assert!(tokio_test::assert_ready!(handle.poll_request()).is_none());
 }
+
+#[cfg(feature = "test-util")]
+#[tokio::test(flavor = "current_thread")]
+async fn drives_readiness_on_executor() {
+    let _t = support::trace_init();
+
+    let executor = TestExecutor::new();
+    let layer = SpawnReadyLayer::with_executor(executor.clone());
+    let (mut service, mut handle) = mock::spawn_layer::<(), (), _>(layer);
+
+    // Make the service NotReady
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+    assert_eq!(executor.len(), 1, "task must be spawned on the executor");
+
+    // The service becomes ready once the executor runs the task.
+    handle.allow(1);
+    assert_pending!(service.poll_ready());
+    let mut task = tokio_test::task::spawn(());
+    assert_ready!(task.enter(|cx, _| executor.poll(cx)));
+    assert!(service.is_woken());
+    assert_ready_ok!(service.poll_ready());
+}
+
+#[cfg(feature = "test-util")]
+#[tokio::test(flavor = "current_thread")]
+async fn executor_task_stops_on_drop() {
+    let _t = support::trace_init();
+
+    let executor = TestExecutor::new();
+    let (mock, mut handle) = mock::pair::<(), ()>();
+    let mut svc = mock::Spawn::new(SpawnReady::with_executor(mock, executor.clone()));
+    handle.allow(0);
+    assert_pending!(svc.poll_ready());
+
+    drop(svc);
+    let mut task = tokio_test::task::spawn(());
+    assert_ready!(task.enter(|cx, _| executor.poll(cx)));
+    assert!(tokio_test::assert_ready!(handle.poll_request()).is_none());
+}
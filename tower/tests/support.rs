@@ -5,6 +5,10 @@ use std::future;
 use std::task::{Context, Poll};
 use tower::Service;
 
+#[cfg(feature = "test-util")]
+#[allow(unused_imports)]
+pub(crate) use tower::executor::TestExecutor;
+
 pub(crate) fn trace_init() -> tracing::subscriber::DefaultGuard {
     let subscriber = tracing_subscriber::fmt()
         .with_test_writer()
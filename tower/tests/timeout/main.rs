@@ -0,0 +1,390 @@
+#![cfg(feature = "timeout")]
+#[path = "../support.rs"]
+mod support;
+
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_test::{assert_pending, assert_ready_err, assert_ready_ok, task};
+use tower::timeout::{
+    error::{Elapsed, ReadyElapsed},
+    DeadlineTimeoutLayer, ReadyTimeoutLayer, RequestTimeoutLayer, TotalTimeoutLayer,
+};
+use tower_test::{assert_request_eq, mock};
+
+#[tokio::test(flavor = "current_thread")]
+async fn request_timeout_from_request() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = RequestTimeoutLayer::new(
+        |req: &&'static str| {
+            req.strip_suffix("ms")
+                .and_then(|ms| ms.parse().ok())
+                .map(Duration::from_millis)
+        },
+        Duration::from_secs(1),
+        Duration::from_secs(5),
+    );
+    let (mut service, mut handle) = mock::spawn_layer::<_, &'static str, _>(layer);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("100ms"));
+    let _send_response = assert_request_eq!(handle, "100ms");
+
+    tokio::time::advance(Duration::from_millis(99)).await;
+    assert_pending!(response.poll());
+
+    tokio::time::advance(Duration::from_millis(2)).await;
+    let err = assert_ready_err!(response.poll());
+    assert!(err.is::<Elapsed>(), "should be Elapsed: {:?}", err);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn request_timeout_default_and_max() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = RequestTimeoutLayer::new(
+        |req: &&'static str| (*req == "forever").then(|| Duration::from_secs(3600)),
+        Duration::from_secs(1),
+        Duration::from_secs(5),
+    );
+    let (mut service, mut handle) = mock::spawn_layer::<_, &'static str, _>(layer);
+
+    // Requests without a timeout use the default.
+    assert_ready_ok!(service.poll_ready());
+    let mut default = task::spawn(service.call("hello"));
+    let _send_response = assert_request_eq!(handle, "hello");
+
+    // Requests may not ask for more than the maximum.
+    assert_ready_ok!(service.poll_ready());
+    let mut capped = task::spawn(service.call("forever"));
+    let _send_response = assert_request_eq!(handle, "forever");
+
+    assert_pending!(default.poll());
+    assert_pending!(capped.poll());
+
+    tokio::time::advance(Duration::from_millis(1001)).await;
+    assert!(assert_ready_err!(default.poll()).is::<Elapsed>());
+    assert_pending!(capped.poll());
+
+    tokio::time::advance(Duration::from_secs(4)).await;
+    assert!(assert_ready_err!(capped.poll()).is::<Elapsed>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn deadline_shared_across_calls() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = DeadlineTimeoutLayer::new(
+        |req: &(&'static str, Option<Instant>)| req.1,
+        Duration::from_secs(1),
+        Duration::from_secs(5),
+    );
+    let (mut service, mut handle) = mock::spawn_layer::<_, &'static str, _>(layer);
+
+    let deadline = Instant::now() + Duration::from_secs(2);
+
+    // The first attempt fails after some time...
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call(("attempt", Some(deadline))));
+    let (_, send_response) = handle.next_request().await.unwrap();
+    tokio::time::advance(Duration::from_millis(1500)).await;
+    send_response.send_error("oops");
+    assert_ready_err!(response1.poll());
+
+    // ...and the retried attempt only gets the time remaining until the
+    // deadline, rather than a fresh timeout.
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call(("attempt", Some(deadline))));
+    let _send_response = handle.next_request().await.unwrap();
+
+    tokio::time::advance(Duration::from_millis(499)).await;
+    assert_pending!(response2.poll());
+
+    tokio::time::advance(Duration::from_millis(2)).await;
+    assert!(assert_ready_err!(response2.poll()).is::<Elapsed>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn deadline_default_without_deadline() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = DeadlineTimeoutLayer::new(
+        |req: &(&'static str, Option<Instant>)| req.1,
+        Duration::from_secs(1),
+        Duration::from_secs(5),
+    );
+    let (mut service, mut handle) = mock::spawn_layer::<_, &'static str, _>(layer);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call(("hello", None)));
+    let _send_response = handle.next_request().await.unwrap();
+
+    tokio::time::advance(Duration::from_millis(999)).await;
+    assert_pending!(response.poll());
+
+    tokio::time::advance(Duration::from_millis(2)).await;
+    assert!(assert_ready_err!(response.poll()).is::<Elapsed>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn deadline_without_max() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = DeadlineTimeoutLayer::new(
+        |req: &(&'static str, Option<Instant>)| req.1,
+        Duration::MAX,
+        Duration::MAX,
+    );
+    let (mut service, mut handle) = mock::spawn_layer::<_, &'static str, _>(layer);
+
+    // A request without a deadline never times out.
+    assert_ready_ok!(service.poll_ready());
+    let mut response1 = task::spawn(service.call(("hello", None)));
+    let send_response1 = handle.next_request().await.unwrap().1;
+
+    // A request with a deadline isn't capped by the maximum.
+    let deadline = Instant::now() + Duration::from_secs(1);
+    assert_ready_ok!(service.poll_ready());
+    let mut response2 = task::spawn(service.call(("hello", Some(deadline))));
+    let _send_response2 = handle.next_request().await.unwrap();
+
+    tokio::time::advance(Duration::from_millis(1001)).await;
+    assert!(assert_ready_err!(response2.poll()).is::<Elapsed>());
+
+    tokio::time::advance(Duration::from_secs(3600)).await;
+    assert_pending!(response1.poll());
+    send_response1.send_response("world");
+    assert_eq!(assert_ready_ok!(response1.poll()), "world");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn total_timeout_counts_readiness() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = TotalTimeoutLayer::new(Duration::from_secs(1));
+    let (mut service, mut handle) = mock::spawn_layer::<&'static str, &'static str, _>(layer);
+
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+
+    tokio::time::advance(Duration::from_millis(600)).await;
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+
+    let mut response = task::spawn(service.call("hello"));
+    let _send_response = assert_request_eq!(handle, "hello");
+
+    // Only the time remaining after waiting for readiness is left.
+    tokio::time::advance(Duration::from_millis(399)).await;
+    assert_pending!(response.poll());
+
+    tokio::time::advance(Duration::from_millis(2)).await;
+    assert!(assert_ready_err!(response.poll()).is::<Elapsed>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn total_timeout_elapses_before_ready() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = TotalTimeoutLayer::new(Duration::from_secs(1));
+    let (mut service, mut handle) = mock::spawn_layer::<&'static str, &'static str, _>(layer);
+
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+
+    tokio::time::advance(Duration::from_millis(1001)).await;
+    assert!(service.is_woken());
+    assert_ready_ok!(service.poll_ready());
+
+    // The request fails without being sent to the inner service.
+    let mut response = task::spawn(service.call("hello"));
+    assert!(assert_ready_err!(response.poll()).is::<Elapsed>());
+    assert_pending!(handle.poll_request());
+
+    // The service can still be used for subsequent requests.
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+    assert_request_eq!(handle, "hello").send_response("world");
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn total_timeout_restarts_abandoned_readiness() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = TotalTimeoutLayer::new(Duration::from_secs(1));
+    let (mut service, mut handle) = mock::spawn_layer::<&'static str, &'static str, _>(layer);
+
+    // The timeout elapses, but the caller doesn't call the service.
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+    tokio::time::advance(Duration::from_millis(1001)).await;
+    assert_ready_ok!(service.poll_ready());
+
+    // Polling again much later starts a fresh readiness cycle.
+    tokio::time::advance(Duration::from_secs(10)).await;
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+    assert_request_eq!(handle, "hello").send_response("world");
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+
+    // The same goes for readiness that was reached, but not used before the
+    // deadline.
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+    tokio::time::advance(Duration::from_secs(10)).await;
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+    let send_response = assert_request_eq!(handle, "hello");
+    tokio::time::advance(Duration::from_millis(999)).await;
+    assert_pending!(response.poll());
+    send_response.send_response("world");
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn total_timeout_without_limit() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = TotalTimeoutLayer::new(Duration::MAX);
+    let (mut service, mut handle) = mock::spawn_layer::<&'static str, &'static str, _>(layer);
+
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+
+    tokio::time::advance(Duration::from_secs(3600)).await;
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+
+    let mut response = task::spawn(service.call("hello"));
+    let send_response = assert_request_eq!(handle, "hello");
+    tokio::time::advance(Duration::from_secs(3600)).await;
+    assert_pending!(response.poll());
+
+    send_response.send_response("world");
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn ready_timeout_fails_poll_ready() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = ReadyTimeoutLayer::new(Duration::from_secs(1));
+    let (mut service, mut handle) = mock::spawn_layer::<&'static str, &'static str, _>(layer);
+
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+
+    tokio::time::advance(Duration::from_millis(999)).await;
+    assert_pending!(service.poll_ready());
+
+    tokio::time::advance(Duration::from_millis(2)).await;
+    assert!(service.is_woken());
+    let err = assert_ready_err!(service.poll_ready());
+    assert!(
+        err.is::<ReadyElapsed>(),
+        "should be ReadyElapsed: {:?}",
+        err
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn ready_timeout_resets_each_cycle() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = ReadyTimeoutLayer::new(Duration::from_secs(1));
+    let (mut service, mut handle) = mock::spawn_layer::<&'static str, &'static str, _>(layer);
+
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+
+    // The service becomes ready shortly before the timeout.
+    tokio::time::advance(Duration::from_millis(900)).await;
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("hello"));
+    assert_request_eq!(handle, "hello").send_response("world");
+    assert_eq!(assert_ready_ok!(response.poll()), "world");
+
+    // The next readiness cycle gets the full timeout again.
+    assert_pending!(service.poll_ready());
+    tokio::time::advance(Duration::from_millis(900)).await;
+    assert_pending!(service.poll_ready());
+
+    tokio::time::advance(Duration::from_millis(101)).await;
+    assert!(assert_ready_err!(service.poll_ready()).is::<ReadyElapsed>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn ready_timeout_without_limit() {
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let layer = ReadyTimeoutLayer::new(Duration::MAX);
+    let (mut service, mut handle) = mock::spawn_layer::<&'static str, &'static str, _>(layer);
+
+    handle.allow(0);
+    assert_pending!(service.poll_ready());
+    tokio::time::advance(Duration::from_secs(3600)).await;
+    assert_pending!(service.poll_ready());
+
+    handle.allow(1);
+    assert_ready_ok!(service.poll_ready());
+}
+
+#[cfg(all(feature = "retry", feature = "util"))]
+#[tokio::test(flavor = "current_thread")]
+async fn total_timeout_caps_retries() {
+    use std::future;
+    use tower::{retry::Policy, ServiceBuilder, ServiceExt};
+
+    #[derive(Clone)]
+    struct RetryErrors;
+
+    impl Policy<&'static str, &'static str, tower::BoxError> for RetryErrors {
+        type Future = future::Ready<()>;
+
+        fn retry(
+            &mut self,
+            _: &mut &'static str,
+            result: &mut Result<&'static str, tower::BoxError>,
+        ) -> Option<Self::Future> {
+            result.is_err().then(|| future::ready(()))
+        }
+
+        fn clone_request(&mut self, req: &&'static str) -> Option<&'static str> {
+            Some(*req)
+        }
+    }
+
+    let _t = support::trace_init();
+    tokio::time::pause();
+
+    let service = ServiceBuilder::new()
+        .layer(TotalTimeoutLayer::new(Duration::from_secs(1)))
+        .retry(RetryErrors)
+        .timeout(Duration::from_millis(400))
+        .service_fn(|_: &'static str| future::pending::<Result<&'static str, tower::BoxError>>());
+
+    let start = tokio::time::Instant::now();
+    let err = service.oneshot("hello").await.unwrap_err();
+    assert!(err.is::<Elapsed>(), "should be Elapsed: {:?}", err);
+
+    // Each attempt times out after 400ms and is retried, but all attempts
+    // together are stopped after one second.
+    assert_eq!(start.elapsed().as_millis() / 100, 10);
+}
@@ -0,0 +1,90 @@
+use std::future::{ready, Ready};
+use std::task::{Context, Poll};
+use tower::util::error::catch_panic::Panicked;
+use tower::util::{service_fn, CatchPanic, ServiceExt};
+use tower_service::Service;
+
+#[tokio::test(flavor = "current_thread")]
+async fn passes_through_responses() {
+    let _t = super::support::trace_init();
+
+    let mut svc = CatchPanic::new(service_fn(|req: u32| ready(Ok::<_, &str>(req + 1))));
+    assert_eq!(svc.ready().await.unwrap().call(1).await.unwrap(), 2);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn catches_panic_in_call() {
+    let _t = super::support::trace_init();
+
+    struct PanicInCall;
+
+    impl Service<&'static str> for PanicInCall {
+        type Response = ();
+        type Error = &'static str;
+        type Future = Ready<Result<(), &'static str>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            panic!("bad request: {}", req)
+        }
+    }
+
+    let mut svc = CatchPanic::new(PanicInCall);
+    let err = svc.ready().await.unwrap().call("boom").await.unwrap_err();
+    let panicked = err
+        .downcast_ref::<Panicked>()
+        .expect("error must be Panicked");
+    assert_eq!(panicked.message(), Some("bad request: boom"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn catches_panic_in_future() {
+    let _t = super::support::trace_init();
+
+    let mut svc = CatchPanic::new(service_fn(|req: &'static str| async move {
+        if req == "boom" {
+            panic!("boom");
+        }
+        Ok::<_, &str>(req)
+    }));
+    let err = svc.ready().await.unwrap().call("boom").await.unwrap_err();
+    let panicked = err
+        .downcast_ref::<Panicked>()
+        .expect("error must be Panicked");
+    assert_eq!(panicked.message(), Some("boom"));
+    assert_eq!(err.to_string(), "service panicked: boom");
+
+    let rsp = svc.ready().await.unwrap().call("ok").await.unwrap();
+    assert_eq!(rsp, "ok");
+}
+
+#[cfg(all(feature = "buffer", feature = "tokio-rt"))]
+#[tokio::test(flavor = "current_thread")]
+async fn keeps_buffer_worker_alive() {
+    use tower::buffer::Buffer;
+
+    let _t = super::support::trace_init();
+
+    let svc = CatchPanic::new(service_fn(|req: &'static str| {
+        if req == "boom" {
+            panic!("boom");
+        }
+        ready(Ok::<_, &str>(req))
+    }));
+    let mut buffer = Buffer::new(svc, 8);
+
+    let err = buffer
+        .ready()
+        .await
+        .unwrap()
+        .call("boom")
+        .await
+        .unwrap_err();
+    assert!(err.is::<Panicked>(), "unexpected error: {}", err);
+
+    let rsp = buffer.ready().await.unwrap().call("ok").await.unwrap();
+    assert_eq!(rsp, "ok");
+}
@@ -0,0 +1,137 @@
+use tokio_test::{assert_pending, assert_ready_err, assert_ready_ok, task};
+use tower::util::error::coalesce::ServiceError;
+use tower::util::Coalesce;
+use tower_test::{assert_request_eq, mock};
+
+type Handle = mock::Handle<&'static str, &'static str>;
+
+#[tokio::test(flavor = "current_thread")]
+async fn coalesces_requests_with_same_key() {
+    let _t = super::support::trace_init();
+    let (mut service, mut handle) = new_service();
+
+    assert_ready_ok!(service.poll_ready());
+    let mut a1 = task::spawn(service.call("a"));
+    assert_ready_ok!(service.poll_ready());
+    let mut b = task::spawn(service.call("b"));
+    assert_ready_ok!(service.poll_ready());
+    let mut a2 = task::spawn(service.call("a"));
+
+    assert_request_eq!(handle, "a").send_response("A");
+    assert_request_eq!(handle, "b").send_response("B");
+    assert_pending!(handle.poll_request());
+
+    assert_eq!(assert_ready_ok!(a2.poll()), "A");
+    assert_eq!(assert_ready_ok!(a1.poll()), "A");
+    assert_eq!(assert_ready_ok!(b.poll()), "B");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn clones_share_in_flight_calls() {
+    let _t = super::support::trace_init();
+    let (mut service, mut handle) = new_service();
+    let mut clone = mock::Spawn::new(service.get_ref().clone());
+
+    assert_ready_ok!(service.poll_ready());
+    let mut first = task::spawn(service.call("a"));
+    assert_ready_ok!(clone.poll_ready());
+    let mut second = task::spawn(clone.call("a"));
+
+    assert_request_eq!(handle, "a").send_response("A");
+    assert_pending!(handle.poll_request());
+    assert_eq!(assert_ready_ok!(first.poll()), "A");
+    assert_eq!(assert_ready_ok!(second.poll()), "A");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn calls_again_once_completed() {
+    let _t = super::support::trace_init();
+    let (mut service, mut handle) = new_service();
+
+    assert_ready_ok!(service.poll_ready());
+    let mut first = task::spawn(service.call("a"));
+    assert_request_eq!(handle, "a").send_response("1");
+    assert_eq!(assert_ready_ok!(first.poll()), "1");
+
+    assert_ready_ok!(service.poll_ready());
+    let mut second = task::spawn(service.call("a"));
+    assert_request_eq!(handle, "a").send_response("2");
+    assert_eq!(assert_ready_ok!(second.poll()), "2");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn leader_cancellation_doesnt_cancel_call() {
+    let _t = super::support::trace_init();
+    let (mut service, mut handle) = new_service();
+
+    assert_ready_ok!(service.poll_ready());
+    let mut leader = task::spawn(service.call("a"));
+    assert_ready_ok!(service.poll_ready());
+    let mut follower = task::spawn(service.call("a"));
+
+    // Only the leader has polled the call when it is dropped.
+    assert_pending!(leader.poll());
+    drop(leader);
+
+    let response = assert_request_eq!(handle, "a");
+    assert_pending!(follower.poll());
+    response.send_response("A");
+    assert!(follower.is_woken());
+    assert_eq!(assert_ready_ok!(follower.poll()), "A");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn canceled_once_every_caller_is_dropped() {
+    let _t = super::support::trace_init();
+    let (mut service, mut handle) = new_service();
+
+    assert_ready_ok!(service.poll_ready());
+    let first = service.call("a");
+    assert_ready_ok!(service.poll_ready());
+    let second = service.call("a");
+    let _canceled = assert_request_eq!(handle, "a");
+    drop((first, second));
+
+    // The canceled call isn't shared with new callers.
+    assert_ready_ok!(service.poll_ready());
+    let mut third = task::spawn(service.call("a"));
+    assert_request_eq!(handle, "a").send_response("A");
+    assert_eq!(assert_ready_ok!(third.poll()), "A");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn shares_errors() {
+    let _t = super::support::trace_init();
+    let (mut service, mut handle) = new_service();
+
+    assert_ready_ok!(service.poll_ready());
+    let mut first = task::spawn(service.call("a"));
+    assert_ready_ok!(service.poll_ready());
+    let mut second = task::spawn(service.call("a"));
+    assert_request_eq!(handle, "a").send_error("boom");
+
+    for response in [&mut first, &mut second].iter_mut() {
+        let err = assert_ready_err!(response.poll());
+        let err = err
+            .downcast_ref::<ServiceError>()
+            .expect("error must be a ServiceError");
+        assert_eq!(err.to_string(), "coalesced service failed: boom");
+    }
+}
+
+fn new_service() -> (
+    mock::Spawn<
+        Coalesce<
+            mock::Mock<&'static str, &'static str>,
+            fn(&&'static str) -> &'static str,
+            &'static str,
+        >,
+    >,
+    Handle,
+) {
+    mock::spawn_with(|service| Coalesce::new(service, key as fn(&&'static str) -> &'static str))
+}
+
+fn key(req: &&'static str) -> &'static str {
+    req
+}
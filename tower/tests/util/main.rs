@@ -2,6 +2,10 @@
 #![allow(clippy::type_complexity)]
 
 mod call_all;
+mod catch_panic;
+mod coalesce;
+#[cfg(all(feature = "mirror", feature = "test-util"))]
+mod mirror;
 mod oneshot;
 mod service_fn;
 #[path = "../support.rs"]
@@ -0,0 +1,212 @@
+use super::support::TestExecutor;
+use std::sync::{Arc, Mutex};
+use tokio_test::{assert_pending, assert_ready, assert_ready_ok, task};
+use tower::util::{Mirror, MirrorPolicy};
+use tower::BoxError;
+use tower_test::{assert_request_eq, mock};
+
+type Mock = mock::Mock<&'static str, &'static str>;
+type Handle = mock::Handle<&'static str, &'static str>;
+
+#[tokio::test(flavor = "current_thread")]
+async fn mirrors_requests() {
+    let _t = super::support::trace_init();
+    let (mut service, mut primary, mut shadow, executor) = new_service(10, Mirror::with_executor);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("a"));
+    assert_request_eq!(primary, "a").send_response("A");
+    assert_request_eq!(shadow, "a").send_response("shadow");
+    assert_eq!(assert_ready_ok!(response.poll()), "A");
+
+    let mut runner = task::spawn(());
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn shadow_errors_dont_affect_primary() {
+    let _t = super::support::trace_init();
+    let (mut service, mut primary, mut shadow, executor) = new_service(10, Mirror::with_executor);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("a"));
+    assert_request_eq!(shadow, "a").send_error("shadow failed");
+    assert_request_eq!(primary, "a").send_response("A");
+    assert_eq!(assert_ready_ok!(response.poll()), "A");
+
+    let mut runner = task::spawn(());
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn slow_shadow_doesnt_delay_primary() {
+    let _t = super::support::trace_init();
+    let (mut service, mut primary, mut shadow, executor) = new_service(10, Mirror::with_executor);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("a"));
+    assert_request_eq!(primary, "a").send_response("A");
+    assert_eq!(assert_ready_ok!(response.poll()), "A");
+
+    let mut runner = task::spawn(());
+    assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+    assert_request_eq!(shadow, "a").send_response("shadow");
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn skips_shadow_when_not_ready() {
+    let _t = super::support::trace_init();
+    let (mut service, mut primary, mut shadow, executor) = new_service(10, Mirror::with_executor);
+    shadow.allow(0);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("a"));
+    assert_request_eq!(primary, "a").send_response("A");
+    assert_eq!(assert_ready_ok!(response.poll()), "A");
+    assert_pending!(shadow.poll_request());
+    assert!(executor.is_empty());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn limits_shadow_concurrency() {
+    let _t = super::support::trace_init();
+    let (mut service, mut primary, mut shadow, executor) = new_service(1, Mirror::with_executor);
+
+    assert_ready_ok!(service.poll_ready());
+    let mut first = task::spawn(service.call("a"));
+    let shadow_a = assert_request_eq!(shadow, "a");
+
+    // The shadow request for "a" is still in flight, so "b" isn't mirrored.
+    assert_ready_ok!(service.poll_ready());
+    let mut second = task::spawn(service.call("b"));
+    assert_pending!(shadow.poll_request());
+
+    assert_request_eq!(primary, "a").send_response("A");
+    assert_request_eq!(primary, "b").send_response("B");
+    assert_eq!(assert_ready_ok!(first.poll()), "A");
+    assert_eq!(assert_ready_ok!(second.poll()), "B");
+
+    shadow_a.send_response("shadow");
+    let mut runner = task::spawn(());
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+
+    assert_ready_ok!(service.poll_ready());
+    let mut third = task::spawn(service.call("c"));
+    assert_request_eq!(shadow, "c").send_response("shadow");
+    assert_request_eq!(primary, "c").send_response("C");
+    assert_eq!(assert_ready_ok!(third.poll()), "C");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn samples_requests() {
+    let _t = super::support::trace_init();
+    let (mut service, mut primary, mut shadow, executor) =
+        new_service(10, |primary, shadow, max, executor| {
+            Mirror::with_executor(primary, shadow, max, executor).sample(0.0)
+        });
+
+    for _ in 0..10 {
+        assert_ready_ok!(service.poll_ready());
+        let mut response = task::spawn(service.call("a"));
+        assert_request_eq!(primary, "a").send_response("A");
+        assert_eq!(assert_ready_ok!(response.poll()), "A");
+    }
+    assert_pending!(shadow.poll_request());
+    assert!(executor.is_empty());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn compares_responses() {
+    let _t = super::support::trace_init();
+    let compare = Compare::default();
+    let (mut service, mut primary, mut shadow, executor) = {
+        let compare = compare.clone();
+        new_service(10, move |primary, shadow, max, executor| {
+            Mirror::with_executor(primary, shadow, max, executor).policy(compare)
+        })
+    };
+    let mut runner = task::spawn(());
+
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("a"));
+    assert_request_eq!(shadow, "a").send_response("shadow");
+    assert_pending!(runner.enter(|cx, _| executor.poll(cx)));
+    assert_request_eq!(primary, "a").send_response("A");
+    assert_eq!(assert_ready_ok!(response.poll()), "A");
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+
+    // Responses of failed primary requests aren't compared.
+    assert_ready_ok!(service.poll_ready());
+    let mut response = task::spawn(service.call("b"));
+    assert_request_eq!(shadow, "b").send_error("shadow failed");
+    assert_request_eq!(primary, "b").send_error("primary failed");
+    assert!(response.poll().is_ready());
+    assert_ready!(runner.enter(|cx, _| executor.poll(cx)));
+
+    assert_eq!(*compare.seen.lock().unwrap(), vec![("A", Ok("shadow"))],);
+}
+
+#[cfg(feature = "tokio-rt")]
+#[tokio::test(flavor = "current_thread")]
+async fn spawns_shadow_requests_on_tokio() {
+    use tower::{Service, ServiceExt};
+
+    let _t = super::support::trace_init();
+    let compare = Compare::default();
+    let (primary, mut primary_handle) = mock::pair();
+    let (shadow, mut shadow_handle) = mock::pair();
+    let mut service: Mirror<Mock, Mock, _> =
+        Mirror::new(primary, shadow, 10).policy(compare.clone());
+
+    let response = service.ready().await.unwrap().call("a");
+    assert_request_eq!(shadow_handle, "a").send_response("shadow");
+    assert_request_eq!(primary_handle, "a").send_response("A");
+    assert_eq!(response.await.unwrap(), "A");
+
+    while compare.seen.lock().unwrap().is_empty() {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(*compare.seen.lock().unwrap(), vec![("A", Ok("shadow"))]);
+}
+
+#[derive(Clone, Debug, Default)]
+struct Compare {
+    seen: Arc<Mutex<Vec<(&'static str, Result<&'static str, String>)>>>,
+}
+
+impl MirrorPolicy<&'static str, &'static str, &'static str> for Compare {
+    fn clone_request(&self, req: &&'static str) -> Option<&'static str> {
+        Some(*req)
+    }
+
+    fn clone_response(&self, res: &&'static str) -> Option<&'static str> {
+        Some(*res)
+    }
+
+    fn compare(&self, primary: &&'static str, shadow: &Result<&'static str, BoxError>) {
+        let shadow = shadow.as_ref().map(|res| *res).map_err(|e| e.to_string());
+        self.seen.lock().unwrap().push((*primary, shadow));
+    }
+}
+
+fn new_service<P>(
+    max_concurrency: usize,
+    new: impl FnOnce(Mock, Mock, usize, TestExecutor) -> Mirror<Mock, Mock, P>,
+) -> (
+    mock::Spawn<Mirror<Mock, Mock, P>>,
+    Handle,
+    Handle,
+    TestExecutor,
+) {
+    let (primary, primary_handle) = mock::pair();
+    let (shadow, shadow_handle) = mock::pair();
+    let executor = TestExecutor::new();
+    let service = new(primary, shadow, max_concurrency, executor.clone());
+    (
+        mock::Spawn::new(service),
+        primary_handle,
+        shadow_handle,
+        executor,
+    )
+}